@@ -0,0 +1,30 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE chats ADD COLUMN subscribed_vehicles TEXT;
+
+ALTER TABLE vehicles ADD COLUMN subscribers_ids TEXT;
+
+UPDATE chats c
+SET
+    subscribed_vehicles = subs.plates
+FROM (
+        SELECT chat_id, string_agg(plate || ',', '' ORDER BY created_at) AS plates
+        FROM subscriptions
+        GROUP BY
+            chat_id
+    ) subs
+WHERE
+    subs.chat_id = c.id;
+
+UPDATE vehicles v
+SET
+    subscribers_ids = subs.ids
+FROM (
+        SELECT plate, string_agg(chat_id || ',', '' ORDER BY created_at) AS ids
+        FROM subscriptions
+        GROUP BY
+            plate
+    ) subs
+WHERE
+    subs.plate = v.plate;
+
+DROP TABLE subscriptions;
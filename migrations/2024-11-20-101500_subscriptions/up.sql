@@ -0,0 +1,40 @@
+-- Your SQL goes here
+
+-- SUBSCRIPTIONS (chat <-> vehicle)
+
+CREATE TABLE subscriptions (
+    chat_id BIGINT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    plate VARCHAR NOT NULL REFERENCES vehicles (plate) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (chat_id, plate)
+);
+
+CREATE INDEX subscriptions_plate_index ON subscriptions (plate);
+
+-- Backfill from both comma-separated lists, they may have drifted apart
+
+INSERT INTO
+    subscriptions (chat_id, plate)
+SELECT c.id, v.plate
+FROM
+    chats c
+    CROSS JOIN UNNEST(
+        string_to_array(c.subscribed_vehicles, ',')
+    ) AS subbed (plate)
+    JOIN vehicles v ON v.plate = TRIM(subbed.plate)
+ON CONFLICT DO NOTHING;
+
+INSERT INTO
+    subscriptions (chat_id, plate)
+SELECT c.id, v.plate
+FROM
+    vehicles v
+    CROSS JOIN UNNEST(
+        string_to_array(v.subscribers_ids, ',')
+    ) AS subscriber (id)
+    JOIN chats c ON CAST(c.id AS TEXT) = TRIM(subscriber.id)
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE chats DROP COLUMN subscribed_vehicles;
+
+ALTER TABLE vehicles DROP COLUMN subscribers_ids;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- The original spelling of the plates is lost, nothing to undo
+SELECT 1;
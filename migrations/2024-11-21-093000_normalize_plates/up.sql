@@ -0,0 +1,59 @@
+-- Your SQL goes here
+
+-- Plates are stored uppercase and without separators ("1234 bcd" -> "1234BCD"),
+-- vehicles that end up with the same plate are merged
+
+INSERT INTO
+    vehicles (plate, found_at)
+SELECT UPPER(
+        regexp_replace(plate, '[\s.-]', '', 'g')
+    ), MIN(found_at)
+FROM vehicles
+WHERE
+    plate <> UPPER(
+        regexp_replace(plate, '[\s.-]', '', 'g')
+    )
+GROUP BY
+    1
+ON CONFLICT (plate) DO
+UPDATE
+SET
+    found_at = LEAST(
+        vehicles.found_at,
+        EXCLUDED.found_at
+    );
+
+INSERT INTO
+    subscriptions (chat_id, plate, created_at)
+SELECT chat_id, UPPER(
+        regexp_replace(plate, '[\s.-]', '', 'g')
+    ), MIN(created_at)
+FROM subscriptions
+WHERE
+    plate <> UPPER(
+        regexp_replace(plate, '[\s.-]', '', 'g')
+    )
+GROUP BY
+    1,
+    2
+ON CONFLICT (chat_id, plate) DO NOTHING;
+
+UPDATE fang_tasks
+SET
+    metadata = jsonb_set(
+        metadata,
+        '{plate}',
+        to_jsonb(
+            UPPER(
+                regexp_replace(metadata ->> 'plate', '[\s.-]', '', 'g')
+            )
+        )
+    )
+WHERE (metadata ->> 'type') = 'FetchTask';
+
+-- Removes the old subscriptions too (ON DELETE CASCADE)
+DELETE FROM vehicles
+WHERE
+    plate <> UPPER(
+        regexp_replace(plate, '[\s.-]', '', 'g')
+    );
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE vehicles
+DROP COLUMN location,
+DROP COLUMN registered_at,
+DROP COLUMN reference,
+DROP COLUMN contact;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- Details returned by tucochedana.es once the vehicle is found
+ALTER TABLE vehicles
+ADD COLUMN location TEXT,
+ADD COLUMN registered_at DATE,
+ADD COLUMN reference TEXT,
+ADD COLUMN contact TEXT;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM fang_tasks WHERE (metadata ->> 'type') = 'SweepTask';
+
+ALTER TABLE vehicles DROP COLUMN checked_at, DROP COLUMN check_status;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+-- Result of the last lookup made by the sweep
+ALTER TABLE vehicles
+ADD COLUMN checked_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN check_status VARCHAR(20);
+
+-- One cron task per plate is replaced by a single SweepTask
+DELETE FROM fang_tasks WHERE (metadata ->> 'type') = 'FetchTask';
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM fang_tasks WHERE (metadata ->> 'type') = 'NotificationTask';
+
+DROP TABLE notifications;
+
+DROP TYPE notification_status;
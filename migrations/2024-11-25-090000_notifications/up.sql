@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+CREATE TYPE notification_status AS ENUM('pending', 'sending', 'delivered', 'failed');
+
+-- Outbox of messages for the subscribers of a vehicle,
+-- one row per chat and event so each one is delivered once
+CREATE TABLE notifications (
+    id BIGSERIAL PRIMARY KEY,
+    chat_id BIGINT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    plate VARCHAR NOT NULL REFERENCES vehicles (plate) ON DELETE CASCADE,
+    event VARCHAR(20) NOT NULL,
+    event_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    status notification_status DEFAULT 'pending' NOT NULL,
+    attempts INTEGER DEFAULT 0 NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    delivered_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (chat_id, plate, event, event_at)
+);
+
+CREATE INDEX notifications_status_idx ON notifications (status);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM fang_tasks WHERE (metadata ->> 'type') = 'CleanupTask';
+
+DROP TABLE processed_updates;
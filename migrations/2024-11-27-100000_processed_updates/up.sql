@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+-- Updates already received, Telegram may deliver the same one more than once
+CREATE TABLE processed_updates (
+    update_id BIGINT PRIMARY KEY,
+    received_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX processed_updates_received_at_idx ON processed_updates (received_at);
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+-- Partners using the REST API, only the SHA-256 of the key is stored
+CREATE TABLE api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+-- Plates registered through the API for people that don't use Telegram
+CREATE TABLE api_subscriptions (
+    api_key_id BIGINT NOT NULL REFERENCES api_keys (id) ON DELETE CASCADE,
+    plate VARCHAR NOT NULL REFERENCES vehicles (plate) ON DELETE CASCADE,
+    reference TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (api_key_id, plate)
+);
+
+CREATE INDEX api_subscriptions_plate_idx ON api_subscriptions (plate);
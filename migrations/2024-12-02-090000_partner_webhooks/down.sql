@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM fang_tasks WHERE (metadata ->> 'type') = 'WebhookDeliveryTask';
+
+DROP TABLE webhook_attempts;
+
+DROP TABLE webhook_deliveries;
+
+DROP TABLE partner_webhooks;
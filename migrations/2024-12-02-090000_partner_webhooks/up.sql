@@ -0,0 +1,41 @@
+-- Your SQL goes here
+
+-- Callback URLs of the partners, they receive the events of the plates registered with their key
+CREATE TABLE partner_webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    api_key_id BIGINT NOT NULL REFERENCES api_keys (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    -- Key of the HMAC signature
+    secret VARCHAR(64) NOT NULL,
+    -- Only these plates when not NULL
+    plates VARCHAR[],
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX partner_webhooks_api_key_id_idx ON partner_webhooks (api_key_id);
+
+-- One row per webhook and event, the payload is stored as it's signed
+CREATE TABLE webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL REFERENCES partner_webhooks (id) ON DELETE CASCADE,
+    event VARCHAR(40) NOT NULL,
+    plate VARCHAR NOT NULL,
+    payload TEXT NOT NULL,
+    status notification_status DEFAULT 'pending' NOT NULL,
+    attempts INTEGER DEFAULT 0 NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    delivered_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX webhook_deliveries_status_idx ON webhook_deliveries (status);
+
+-- Every request made for a delivery
+CREATE TABLE webhook_attempts (
+    id BIGSERIAL PRIMARY KEY,
+    delivery_id BIGINT NOT NULL REFERENCES webhook_deliveries (id) ON DELETE CASCADE,
+    attempted_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    response_status INTEGER,
+    error TEXT
+);
+
+CREATE INDEX webhook_attempts_delivery_id_idx ON webhook_attempts (delivery_id);
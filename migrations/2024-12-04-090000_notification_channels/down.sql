@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM notifications WHERE channel <> 'telegram';
+
+ALTER TABLE notifications
+DROP CONSTRAINT notifications_chat_id_plate_event_event_at_channel_key;
+
+ALTER TABLE notifications
+ADD CONSTRAINT notifications_chat_id_plate_event_event_at_key UNIQUE (chat_id, plate, event, event_at);
+
+ALTER TABLE notifications DROP COLUMN channel;
+
+DROP TABLE chat_channels;
+
+DROP TYPE notification_channel;
+
+-- Values can't be removed from an enum
+UPDATE chats SET state = 'initial' WHERE state = 'set_email';
@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+CREATE TYPE notification_channel AS ENUM('telegram', 'email');
+
+ALTER TYPE client_state ADD VALUE 'set_email';
+
+-- Channels chosen by each chat, Telegram is used unless it has been disabled
+CREATE TABLE chat_channels (
+    chat_id BIGINT NOT NULL REFERENCES chats (id) ON DELETE CASCADE,
+    channel notification_channel NOT NULL,
+    -- Email address, Telegram doesn't need one
+    address VARCHAR(254),
+    enabled BOOLEAN DEFAULT true NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    PRIMARY KEY (chat_id, channel)
+);
+
+-- Each event is delivered once per channel
+ALTER TABLE notifications
+ADD COLUMN channel notification_channel DEFAULT 'telegram' NOT NULL;
+
+ALTER TABLE notifications
+DROP CONSTRAINT notifications_chat_id_plate_event_event_at_key;
+
+ALTER TABLE notifications
+ADD CONSTRAINT notifications_chat_id_plate_event_event_at_channel_key UNIQUE (
+    chat_id,
+    plate,
+    event,
+    event_at,
+    channel
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE chats
+ALTER COLUMN language_code TYPE VARCHAR(3) USING LEFT(language_code, 2);
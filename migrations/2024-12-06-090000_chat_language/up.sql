@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Telegram sends IETF tags such as pt-br or ca-ES-valencia
+ALTER TABLE chats ALTER COLUMN language_code TYPE VARCHAR(35);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- IANA name chosen by the chat, DEFAULT_TIMEZONE when NULL
+ALTER TABLE chats ADD COLUMN timezone VARCHAR(64);
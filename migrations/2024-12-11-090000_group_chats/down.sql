@@ -0,0 +1,19 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE chat_channels
+    DROP CONSTRAINT chat_channels_chat_id_fkey,
+    ADD CONSTRAINT chat_channels_chat_id_fkey FOREIGN KEY (chat_id)
+        REFERENCES chats (id) ON DELETE CASCADE;
+
+ALTER TABLE notifications
+    DROP CONSTRAINT notifications_chat_id_fkey,
+    ADD CONSTRAINT notifications_chat_id_fkey FOREIGN KEY (chat_id)
+        REFERENCES chats (id) ON DELETE CASCADE;
+
+ALTER TABLE subscriptions
+    DROP CONSTRAINT subscriptions_chat_id_fkey,
+    ADD CONSTRAINT subscriptions_chat_id_fkey FOREIGN KEY (chat_id)
+        REFERENCES chats (id) ON DELETE CASCADE;
+
+ALTER TABLE chats DROP COLUMN kind;
+
+DROP TYPE chat_kind;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TYPE chat_kind AS ENUM ('private', 'group');
+
+ALTER TABLE chats ADD COLUMN kind chat_kind NOT NULL DEFAULT 'private';
+
+-- The id of a group changes when it becomes a supergroup
+ALTER TABLE subscriptions
+    DROP CONSTRAINT subscriptions_chat_id_fkey,
+    ADD CONSTRAINT subscriptions_chat_id_fkey FOREIGN KEY (chat_id)
+        REFERENCES chats (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE notifications
+    DROP CONSTRAINT notifications_chat_id_fkey,
+    ADD CONSTRAINT notifications_chat_id_fkey FOREIGN KEY (chat_id)
+        REFERENCES chats (id) ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE chat_channels
+    DROP CONSTRAINT chat_channels_chat_id_fkey,
+    ADD CONSTRAINT chat_channels_chat_id_fkey FOREIGN KEY (chat_id)
+        REFERENCES chats (id) ON DELETE CASCADE ON UPDATE CASCADE;
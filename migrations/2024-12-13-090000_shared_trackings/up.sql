@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Links to follow a plate along with the chat that shared it
+CREATE TABLE shared_trackings (
+    token VARCHAR(32) PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    plate VARCHAR NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() NOT NULL,
+    UNIQUE (chat_id, plate),
+    FOREIGN KEY (chat_id, plate) REFERENCES subscriptions (chat_id, plate)
+        ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- When a worker claimed the notification, a claim that is too old belongs to a worker that died
+ALTER TABLE notifications ADD COLUMN claimed_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE notifications SET claimed_at = NOW() WHERE status = 'sending';
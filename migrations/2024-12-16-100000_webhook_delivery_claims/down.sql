@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE webhook_deliveries DROP COLUMN claimed_at;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- When a worker claimed the delivery, a claim that is too old belongs to a worker that died
+ALTER TABLE webhook_deliveries ADD COLUMN claimed_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE webhook_deliveries SET claimed_at = NOW() WHERE status = 'sending';
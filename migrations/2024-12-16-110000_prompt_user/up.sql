@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Member that opened the prompt of the state, only their answer is taken in groups
+ALTER TABLE chats ADD COLUMN state_user_id BIGINT;
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+
+-- Claims that weren't processed would be taken as processed
+DELETE FROM processed_updates WHERE processed_at IS NULL;
+
+ALTER TABLE processed_updates DROP COLUMN processed_at;
+
+ALTER TABLE processed_updates DROP COLUMN claimed_at;
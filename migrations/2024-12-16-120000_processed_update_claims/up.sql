@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Updates are claimed before being processed so a duplicate delivered meanwhile is skipped,
+-- a claim that is too old without being processed belongs to a worker that died
+ALTER TABLE processed_updates ADD COLUMN claimed_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE processed_updates ADD COLUMN processed_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE processed_updates
+SET
+    claimed_at = received_at,
+    processed_at = received_at;
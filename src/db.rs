@@ -28,7 +28,14 @@ pub use repo::Repo;
 pub mod repo;
 
 pub mod model {
+    pub mod api_key;
+    pub mod api_subscription;
     pub mod chat;
+    pub mod chat_channel;
     pub mod client_state;
+    pub mod notification;
+    pub mod partner_webhook;
+    pub mod shared_tracking;
+    pub mod subscription;
     pub mod vehicle;
 }
@@ -0,0 +1,42 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+/// Partner allowed to use the REST API
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// New random key, it's only shown once since the database keeps its hash
+    pub fn generate() -> String {
+        let key: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(40)
+            .map(char::from)
+            .collect();
+
+        format!("tcd_{key}")
+    }
+
+    pub fn hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+}
+
+impl From<Row> for ApiKey {
+    fn from(row: Row) -> ApiKey {
+        ApiKey::builder()
+            .id(row.get("id"))
+            .name(row.get("name"))
+            .created_at(row.get("created_at"))
+            .maybe_revoked_at(row.get("revoked_at"))
+            .build()
+    }
+}
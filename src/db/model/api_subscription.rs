@@ -0,0 +1,24 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+
+/// Plate registered by a partner through the REST API
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct ApiSubscription {
+    pub api_key_id: i64,
+    pub plate: String,
+    /// Partner's own identifier for the owner of the vehicle
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for ApiSubscription {
+    fn from(row: Row) -> ApiSubscription {
+        ApiSubscription::builder()
+            .api_key_id(row.get("api_key_id"))
+            .plate(row.get("plate"))
+            .maybe_reference(row.get("reference"))
+            .created_at(row.get("created_at"))
+            .build()
+    }
+}
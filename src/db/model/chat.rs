@@ -1,20 +1,73 @@
 use bb8_postgres::tokio_postgres::Row;
 use bon::Builder;
+use chrono_tz::Tz;
+use frankenstein::ChatType;
+use postgres_types::{FromSql, ToSql};
 
 use crate::db::Repo;
+use crate::i18n::{Lang, Locale};
+use crate::DEFAULT_TIMEZONE;
 
 use super::client_state::ClientState;
 
+/// Groups and supergroups share the plates and settings of the chat
+#[derive(Debug, Eq, PartialEq, Clone, Copy, ToSql, FromSql)]
+#[postgres(name = "chat_kind")]
+pub enum ChatKind {
+    #[postgres(name = "private")]
+    Private,
+    #[postgres(name = "group")]
+    Group,
+}
+
+impl From<ChatType> for ChatKind {
+    fn from(chat_type: ChatType) -> Self {
+        match chat_type {
+            ChatType::Private => ChatKind::Private,
+            ChatType::Group | ChatType::Supergroup | ChatType::Channel => ChatKind::Group,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Builder)]
 pub struct Chat {
     pub id: i64,
     pub user_id: u64,
     pub username: String,
     pub state: ClientState,
+    /// Member that opened the prompt of `state`
+    pub state_user_id: Option<i64>,
     pub selected_text: Option<String>,
-    pub subscribed_vehicles: Option<String>,
     pub active: bool,
     pub language_code: Option<String>,
+    /// IANA name, `DEFAULT_TIMEZONE` when `None`
+    pub timezone: Option<String>,
+    #[builder(default = ChatKind::Private)]
+    pub kind: ChatKind,
+}
+
+impl Chat {
+    /// Chosen with `/language`, or taken from Telegram when the chat was created
+    pub fn lang(&self) -> Lang {
+        Lang::from_code(self.language_code.as_deref())
+    }
+
+    /// Unknown timezones fall back to `DEFAULT_TIMEZONE`
+    pub fn tz(&self) -> Tz {
+        self.timezone
+            .as_deref()
+            .and_then(|timezone| timezone.parse().ok())
+            .unwrap_or(*DEFAULT_TIMEZONE)
+    }
+
+    /// How texts are rendered for the chat
+    pub fn locale(&self) -> Locale {
+        Locale::new(self.lang(), self.tz())
+    }
+
+    pub fn is_group(&self) -> bool {
+        self.kind == ChatKind::Group
+    }
 }
 
 impl From<Row> for Chat {
@@ -29,10 +82,12 @@ impl From<Row> for Chat {
             .user_id(user_id)
             .username(row.get("username"))
             .state(row.get("state"))
+            .maybe_state_user_id(row.try_get("state_user_id").ok())
             .maybe_selected_text(row.try_get("selected_text").ok())
-            .maybe_subscribed_vehicles(row.try_get("subscribed_vehicles").ok())
             .active(row.get("active"))
             .maybe_language_code(row.try_get("language_code").ok())
+            .maybe_timezone(row.try_get("timezone").ok())
+            .kind(row.get("kind"))
             .build()
     }
 }
@@ -0,0 +1,46 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+use postgres_types::{FromSql, ToSql};
+
+/// Ways of telling a chat that its vehicle has been found
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, ToSql, FromSql)]
+#[postgres(name = "notification_channel")]
+pub enum NotificationChannel {
+    #[postgres(name = "telegram")]
+    Telegram,
+    #[postgres(name = "email")]
+    Email,
+}
+
+impl NotificationChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationChannel::Telegram => "telegram",
+            NotificationChannel::Email => "email",
+        }
+    }
+}
+
+/// Preference of a chat for one channel
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct ChatChannel {
+    pub chat_id: i64,
+    pub channel: NotificationChannel,
+    /// Email address, `None` for Telegram
+    pub address: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Row> for ChatChannel {
+    fn from(row: Row) -> ChatChannel {
+        ChatChannel::builder()
+            .chat_id(row.get("chat_id"))
+            .channel(row.get("channel"))
+            .maybe_address(row.get("address"))
+            .enabled(row.get("enabled"))
+            .created_at(row.get("created_at"))
+            .build()
+    }
+}
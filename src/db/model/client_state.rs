@@ -7,4 +7,7 @@ pub enum ClientState {
     Initial,
     #[postgres(name = "add_vehicle")]
     AddVehicle,
+    /// Waiting for the email address to notify
+    #[postgres(name = "set_email")]
+    SetEmail,
 }
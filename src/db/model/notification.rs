@@ -0,0 +1,57 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+use postgres_types::{FromSql, ToSql};
+use serde::Serialize;
+
+use super::chat_channel::NotificationChannel;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, ToSql, FromSql, Serialize)]
+#[postgres(name = "notification_status")]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationStatus {
+    #[postgres(name = "pending")]
+    Pending,
+    /// Claimed by a worker, it can be claimed again once the claim is stale
+    #[postgres(name = "sending")]
+    Sending,
+    #[postgres(name = "delivered")]
+    Delivered,
+    /// Every attempt failed
+    #[postgres(name = "failed")]
+    Failed,
+}
+
+/// Event of a vehicle that has to be told to one of its subscribers
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct Notification {
+    pub id: i64,
+    pub chat_id: i64,
+    pub plate: String,
+    pub event: String,
+    pub event_at: DateTime<Utc>,
+    pub channel: NotificationChannel,
+    pub status: NotificationStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<Row> for Notification {
+    fn from(row: Row) -> Notification {
+        Notification::builder()
+            .id(row.get("id"))
+            .chat_id(row.get("chat_id"))
+            .plate(row.get("plate"))
+            .event(row.get("event"))
+            .event_at(row.get("event_at"))
+            .channel(row.get("channel"))
+            .status(row.get("status"))
+            .attempts(row.get("attempts"))
+            .maybe_last_error(row.get("last_error"))
+            .created_at(row.get("created_at"))
+            .maybe_delivered_at(row.get("delivered_at"))
+            .build()
+    }
+}
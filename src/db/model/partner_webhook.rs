@@ -0,0 +1,90 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+
+use super::notification::NotificationStatus;
+
+/// Callback URL of a partner
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct PartnerWebhook {
+    pub id: i64,
+    pub api_key_id: i64,
+    pub url: String,
+    pub secret: String,
+    /// Every plate registered with the key when `None`
+    pub plates: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PartnerWebhook {
+    pub fn generate_secret() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(64)
+            .map(char::from)
+            .collect()
+    }
+}
+
+impl From<Row> for PartnerWebhook {
+    fn from(row: Row) -> PartnerWebhook {
+        PartnerWebhook::builder()
+            .id(row.get("id"))
+            .api_key_id(row.get("api_key_id"))
+            .url(row.get("url"))
+            .secret(row.get("secret"))
+            .maybe_plates(row.get("plates"))
+            .created_at(row.get("created_at"))
+            .build()
+    }
+}
+
+/// Event sent to a webhook
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub plate: String,
+    pub payload: String,
+    pub status: NotificationStatus,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<Row> for WebhookDelivery {
+    fn from(row: Row) -> WebhookDelivery {
+        WebhookDelivery::builder()
+            .id(row.get("id"))
+            .webhook_id(row.get("webhook_id"))
+            .event(row.get("event"))
+            .plate(row.get("plate"))
+            .payload(row.get("payload"))
+            .status(row.get("status"))
+            .attempts(row.get("attempts"))
+            .created_at(row.get("created_at"))
+            .maybe_delivered_at(row.get("delivered_at"))
+            .build()
+    }
+}
+
+/// Request made for a delivery
+#[derive(Debug, Clone, Builder, PartialEq, Eq, Serialize)]
+pub struct WebhookAttempt {
+    pub attempted_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+}
+
+impl From<Row> for WebhookAttempt {
+    fn from(row: Row) -> WebhookAttempt {
+        WebhookAttempt::builder()
+            .attempted_at(row.get("attempted_at"))
+            .maybe_response_status(row.get("response_status"))
+            .maybe_error(row.get("error"))
+            .build()
+    }
+}
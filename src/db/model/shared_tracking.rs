@@ -0,0 +1,35 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+
+/// Subscription shared with a `share_<token>` link
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct SharedTracking {
+    pub token: String,
+    pub chat_id: i64,
+    pub plate: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SharedTracking {
+    /// Short enough for the 64 characters of a `start` link
+    pub fn generate_token() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(20)
+            .map(char::from)
+            .collect()
+    }
+}
+
+impl From<Row> for SharedTracking {
+    fn from(row: Row) -> SharedTracking {
+        SharedTracking::builder()
+            .token(row.get("token"))
+            .chat_id(row.get("chat_id"))
+            .plate(row.get("plate"))
+            .created_at(row.get("created_at"))
+            .build()
+    }
+}
@@ -0,0 +1,29 @@
+use bb8_postgres::tokio_postgres::Row;
+use bon::Builder;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Builder, PartialEq, Eq)]
+pub struct Subscription {
+    pub chat_id: i64,
+    pub plate: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What following a plate of a list ended up doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscribeOutcome {
+    Added,
+    AlreadyFollowed,
+    /// Found vehicles aren't followed, there is nothing left to alert about
+    AlreadyFound(DateTime<Utc>),
+}
+
+impl From<Row> for Subscription {
+    fn from(row: Row) -> Subscription {
+        Subscription::builder()
+            .chat_id(row.get("chat_id"))
+            .plate(row.get("plate"))
+            .created_at(row.get("created_at"))
+            .build()
+    }
+}
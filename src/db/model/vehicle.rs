@@ -1,16 +1,40 @@
 use bb8_postgres::tokio_postgres::Row;
 use bon::Builder;
 use bytes::BytesMut;
-use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use postgres_types::{IsNull, ToSql, Type};
-use std::{error::Error, fmt::Debug};
+use serde::Serialize;
+use std::{error::Error, fmt::Debug, fmt::Write};
+
+use crate::i18n::{Locale, Msg};
+use crate::telegram::client::escape_html;
 
 #[derive(Debug, Clone, Builder)]
 pub struct Vehicle {
     pub plate: String,
-    pub subscribers_ids: Option<String>,
-    //Active == subscribers.is_some_and_not_empty && found_at.is_none
+    //Active == has subscriptions && found_at.is_none
     pub found_at: Option<DateTime<Utc>>,
+    #[builder(default)]
+    pub details: FoundDetails,
+    /// Last lookup made by the sweep
+    pub checked_at: Option<DateTime<Utc>>,
+    pub check_status: Option<String>,
+}
+
+/// Information published by tucochedana.es about a found vehicle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Builder, Serialize)]
+pub struct FoundDetails {
+    /// Depot or address where the vehicle is
+    pub location: Option<String>,
+    pub registered_at: Option<NaiveDate>,
+    pub reference: Option<String>,
+    pub contact: Option<String>,
+}
+
+impl FoundDetails {
+    pub fn is_empty(&self) -> bool {
+        self == &FoundDetails::default()
+    }
 }
 
 impl ToSql for Vehicle {
@@ -26,15 +50,6 @@ impl ToSql for Vehicle {
         serialized_data.extend_from_slice(self.plate.as_bytes());
         serialized_data.push(b'\0'); // Field delimiter
 
-        // Serialize `subscribers_ids` if it exists
-        match &self.subscribers_ids {
-            Some(subscribers) => {
-                serialized_data.extend_from_slice(subscribers.as_bytes());
-                serialized_data.push(b'\0');
-            }
-            None => serialized_data.push(b'\0'),
-        }
-
         // Serialize `found_at` if it exists
         if let Some(found) = &self.found_at {
             let timestamp = found.timestamp().to_string();
@@ -67,10 +82,19 @@ impl ToSql for Vehicle {
 
 impl From<Row> for Vehicle {
     fn from(row: Row) -> Vehicle {
+        let details = FoundDetails::builder()
+            .maybe_location(row.try_get("location").ok())
+            .maybe_registered_at(row.try_get("registered_at").ok())
+            .maybe_reference(row.try_get("reference").ok())
+            .maybe_contact(row.try_get("contact").ok())
+            .build();
+
         Vehicle::builder()
             .plate(row.get("plate"))
-            .maybe_subscribers_ids(row.try_get("subscribers_ids").ok())
             .maybe_found_at(row.try_get("found_at").ok())
+            .details(details)
+            .maybe_checked_at(row.try_get("checked_at").ok())
+            .maybe_check_status(row.try_get("check_status").ok())
             .build()
     }
 }
@@ -78,55 +102,60 @@ impl From<Row> for Vehicle {
 impl PartialEq<Self> for Vehicle {
     fn eq(&self, other: &Self) -> bool {
         self.plate == other.plate
-            && self.subscribers_ids == other.subscribers_ids
             && self.found_at == other.found_at
+            && self.details == other.details
     }
 }
 
 impl Vehicle {
-    pub fn found_at_to_text(&self) -> String {
-        // Spanish names for days of the week
-        let days = [
-            "domingo",
-            "lunes",
-            "martes",
-            "miércoles",
-            "jueves",
-            "viernes",
-            "sábado",
-        ];
-        // Spanish names for months
-        let months = [
-            "enero",
-            "febrero",
-            "marzo",
-            "abril",
-            "mayo",
-            "junio",
-            "julio",
-            "agosto",
-            "septiembre",
-            "octubre",
-            "noviembre",
-            "diciembre",
-        ];
+    /// Found time in the timezone of the chat, the date of registration is already local
+    pub fn found_at_to_text(&self, locale: &Locale) -> String {
+        let lang = locale.lang;
+        let plate = self.plate.as_str();
 
         let Some(time) = &self.found_at else {
-            return format!("El vehículo {} no ha sido encontrado todavía", self.plate);
+            return Msg::NotFoundYet.format(lang, &[("plate", plate)]);
         };
 
-        // Get day of the week, day of the month, month, and year
-        let weekday = days[time.weekday().num_days_from_sunday() as usize];
-        let day = time.day();
-        let month = months[(time.month() - 1) as usize];
-        let year = time.year();
-        let hour = time.hour();
-        let minute = time.minute();
-
-        // Format the date as a Spanish-readable string
-        format!(
-            "El vehículo {} fue encontrado el {}, {} de {} de {}, {:02}:{:02} 🙌🏼",
-            self.plate, weekday, day, month, year, hour, minute
-        )
+        let mut text = Msg::FoundAt.format(
+            lang,
+            &[("plate", plate), ("date", &locale.format_datetime(time))],
+        );
+
+        let details = &self.details;
+        if let Some(location) = &details.location {
+            let location = escape_html(location);
+            let _ = write!(
+                text,
+                "\n{}",
+                Msg::Location.format(lang, &[("location", &location)])
+            );
+        }
+        if let Some(date) = &details.registered_at {
+            let date = lang.format_date(date);
+            let _ = write!(
+                text,
+                "\n{}",
+                Msg::RegisteredAt.format(lang, &[("date", &date)])
+            );
+        }
+        if let Some(reference) = &details.reference {
+            let reference = escape_html(reference);
+            let _ = write!(
+                text,
+                "\n{}",
+                Msg::Reference.format(lang, &[("reference", &reference)])
+            );
+        }
+        if let Some(contact) = &details.contact {
+            let contact = escape_html(contact);
+            let _ = write!(
+                text,
+                "\n{}",
+                Msg::Contact.format(lang, &[("contact", &contact)])
+            );
+        }
+
+        text
     }
 }
@@ -0,0 +1 @@
+SELECT 1 FROM processed_updates WHERE update_id = $1 AND processed_at IS NOT NULL
@@ -0,0 +1,13 @@
+-- Only one worker can process an update, a claim older than 15 minutes
+-- that wasn't processed was left behind by a worker that died
+INSERT INTO
+    processed_updates (update_id, claimed_at)
+VALUES ($1, NOW())
+ON CONFLICT (update_id) DO
+UPDATE
+SET
+    claimed_at = NOW()
+WHERE
+    processed_updates.processed_at IS NULL
+    AND processed_updates.claimed_at < NOW() - INTERVAL '15 minutes'
+RETURNING update_id;
@@ -0,0 +1,17 @@
+-- Only one worker can send it at a time,
+-- a claim older than 15 minutes was left behind by a worker that died
+UPDATE webhook_deliveries
+SET
+    status = 'sending',
+    attempts = attempts + 1,
+    claimed_at = NOW()
+WHERE
+    id = $1
+    AND (
+        status = 'pending'
+        OR (
+            status = 'sending'
+            AND claimed_at < NOW() - INTERVAL '15 minutes'
+        )
+    )
+RETURNING *;
@@ -0,0 +1 @@
+SELECT COUNT(*) FROM chats WHERE active = true;
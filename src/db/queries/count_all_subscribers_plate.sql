@@ -0,0 +1 @@
+SELECT COUNT(*) FROM subscriptions WHERE plate = $1
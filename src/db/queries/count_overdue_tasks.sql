@@ -0,0 +1,6 @@
+-- Tasks the workers should have picked up already
+SELECT COUNT(*)
+FROM fang_tasks
+WHERE
+    state IN ('new', 'retried')
+    AND scheduled_at < $1;
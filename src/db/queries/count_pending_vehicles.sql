@@ -0,0 +1,21 @@
+-- Same vehicles as get_pending_vehicles
+SELECT COUNT(*)
+FROM vehicles v
+WHERE
+    v.found_at IS NULL
+    AND (
+        EXISTS (
+            SELECT 1
+            FROM subscriptions s
+                JOIN chats c ON c.id = s.chat_id
+            WHERE
+                s.plate = v.plate
+                AND c.active = true
+        )
+        OR EXISTS (
+            SELECT 1
+            FROM api_subscriptions a
+            WHERE
+                a.plate = v.plate
+        )
+    );
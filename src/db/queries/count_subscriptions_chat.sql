@@ -0,0 +1 @@
+SELECT COUNT(*) FROM subscriptions WHERE chat_id = $1
@@ -0,0 +1 @@
+DELETE FROM api_subscriptions WHERE api_key_id = $1 AND plate = $2;
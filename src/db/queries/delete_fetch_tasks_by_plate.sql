@@ -1,4 +0,0 @@
-DELETE FROM fang_tasks
-WHERE
-    metadata ->> 'plate' = $1
-    AND (metadata ->> 'type') = 'FetchTask'
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Row created by an update of the new group before the migration was processed
+DELETE FROM chats WHERE id = $2 AND EXISTS (SELECT 1 FROM chats WHERE id = $1)
@@ -0,0 +1 @@
+DELETE FROM partner_webhooks WHERE id = $1 AND api_key_id = $2;
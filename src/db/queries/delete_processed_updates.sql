@@ -0,0 +1 @@
+DELETE FROM processed_updates WHERE received_at < $1;
@@ -0,0 +1 @@
+DELETE FROM subscriptions WHERE chat_id = $1 AND plate = $2
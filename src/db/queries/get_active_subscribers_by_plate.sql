@@ -0,0 +1,7 @@
+SELECT c.*
+FROM chats c
+    JOIN subscriptions s ON s.chat_id = c.id
+WHERE
+    s.plate = $1
+    AND c.active = true
+ORDER BY c.id;
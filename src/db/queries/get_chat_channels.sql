@@ -0,0 +1 @@
+SELECT * FROM chat_channels WHERE chat_id = $1 ORDER BY channel;
@@ -0,0 +1,12 @@
+-- Found vehicles among the ones registered with the key, newest first
+SELECT v.*
+FROM vehicles v
+    JOIN api_subscriptions a ON a.plate = v.plate
+WHERE
+    a.api_key_id = $1
+    AND v.found_at IS NOT NULL
+    AND (
+        $2::TIMESTAMPTZ IS NULL
+        OR v.found_at >= $2
+    )
+ORDER BY v.found_at DESC;
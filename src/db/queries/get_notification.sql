@@ -0,0 +1 @@
+SELECT * FROM notifications WHERE id = $1;
@@ -0,0 +1 @@
+SELECT * FROM partner_webhooks WHERE id = $1;
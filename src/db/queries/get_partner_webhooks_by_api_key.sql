@@ -0,0 +1 @@
+SELECT * FROM partner_webhooks WHERE api_key_id = $1 ORDER BY id ASC;
@@ -0,0 +1,9 @@
+SELECT *
+FROM notifications
+WHERE
+    status = 'pending'
+    OR (
+        status = 'sending'
+        AND claimed_at < NOW() - INTERVAL '15 minutes'
+    )
+ORDER BY created_at ASC;
@@ -0,0 +1,23 @@
+-- Vehicles not found yet that at least one active chat or API key is waiting for,
+-- the ones checked longest ago go first
+SELECT v.*
+FROM vehicles v
+WHERE
+    v.found_at IS NULL
+    AND (
+        EXISTS (
+            SELECT 1
+            FROM subscriptions s
+                JOIN chats c ON c.id = s.chat_id
+            WHERE
+                s.plate = v.plate
+                AND c.active = true
+        )
+        OR EXISTS (
+            SELECT 1
+            FROM api_subscriptions a
+            WHERE
+                a.plate = v.plate
+        )
+    )
+ORDER BY v.checked_at ASC NULLS FIRST;
@@ -0,0 +1,9 @@
+SELECT *
+FROM webhook_deliveries
+WHERE
+    status = 'pending'
+    OR (
+        status = 'sending'
+        AND claimed_at < NOW() - INTERVAL '15 minutes'
+    )
+ORDER BY created_at ASC;
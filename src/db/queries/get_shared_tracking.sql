@@ -0,0 +1 @@
+SELECT * FROM shared_trackings WHERE token = $1
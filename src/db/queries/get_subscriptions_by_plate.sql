@@ -0,0 +1 @@
+SELECT * FROM subscriptions WHERE plate = $1 ORDER BY created_at
@@ -0,0 +1,6 @@
+SELECT v.*
+FROM vehicles v
+    JOIN subscriptions s ON s.plate = v.plate
+WHERE
+    s.chat_id = $1
+ORDER BY s.created_at;
@@ -0,0 +1 @@
+SELECT * FROM webhook_attempts WHERE delivery_id = $1 ORDER BY attempted_at ASC;
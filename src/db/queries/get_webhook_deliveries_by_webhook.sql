@@ -0,0 +1,9 @@
+-- History of the webhook, newest first
+SELECT d.*
+FROM webhook_deliveries d
+    JOIN partner_webhooks w ON w.id = d.webhook_id
+WHERE
+    d.webhook_id = $1
+    AND w.api_key_id = $2
+ORDER BY d.created_at DESC
+LIMIT $3;
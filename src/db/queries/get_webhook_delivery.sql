@@ -0,0 +1 @@
+SELECT * FROM webhook_deliveries WHERE id = $1;
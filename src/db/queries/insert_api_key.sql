@@ -0,0 +1 @@
+INSERT INTO api_keys (name, key_hash) VALUES ($1, $2) RETURNING *;
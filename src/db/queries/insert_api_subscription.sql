@@ -0,0 +1,5 @@
+INSERT INTO
+    api_subscriptions (api_key_id, plate, reference)
+VALUES ($1, $2, $3)
+ON CONFLICT DO NOTHING
+RETURNING *;
@@ -1 +1 @@
-INSERT INTO chats (id , state , user_id, username, language_code) VALUES ($1 , $2 , $3, $4, $5) RETURNING *;
+INSERT INTO chats (id , state , user_id, username, language_code, kind) VALUES ($1 , $2 , $3, $4, $5, $6) RETURNING *;
@@ -0,0 +1,39 @@
+-- One notification for every channel of the active subscribers of the found vehicle
+INSERT INTO
+    notifications (
+        chat_id,
+        plate,
+        event,
+        event_at,
+        channel
+    )
+SELECT s.chat_id, s.plate, 'found', $2, ch.channel
+FROM
+    subscriptions s
+    JOIN chats c ON c.id = s.chat_id
+    CROSS JOIN LATERAL (
+        -- Telegram unless the chat disabled it
+        SELECT 'telegram'::notification_channel AS channel
+        WHERE
+            NOT EXISTS (
+                SELECT 1
+                FROM chat_channels cc
+                WHERE
+                    cc.chat_id = s.chat_id
+                    AND cc.channel = 'telegram'
+                    AND cc.enabled = false
+            )
+        UNION ALL
+        SELECT cc.channel
+        FROM chat_channels cc
+        WHERE
+            cc.chat_id = s.chat_id
+            AND cc.channel <> 'telegram'
+            AND cc.enabled = true
+            AND cc.address IS NOT NULL
+    ) ch
+WHERE
+    s.plate = $1
+    AND c.active = true
+ON CONFLICT DO NOTHING
+RETURNING id;
@@ -0,0 +1 @@
+INSERT INTO vehicles (plate) VALUES ($1) ON CONFLICT (plate) DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO
+    partner_webhooks (api_key_id, url, secret, plates)
+VALUES ($1, $2, $3, $4)
+RETURNING *;
@@ -0,0 +1,4 @@
+INSERT INTO
+    subscriptions (chat_id, plate)
+VALUES ($1, $2)
+ON CONFLICT (chat_id, plate) DO NOTHING;
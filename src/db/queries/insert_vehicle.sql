@@ -1,9 +1,12 @@
 INSERT INTO
     vehicles (
         plate,
-        subscribers_ids,
-        found_at
+        found_at,
+        location,
+        registered_at,
+        reference,
+        contact
     )
-VALUES ($1, $2, $3)
+VALUES ($1, $2, $3, $4, $5, $6)
 RETURNING
-    *;
\ No newline at end of file
+    *;
@@ -0,0 +1,3 @@
+INSERT INTO
+    webhook_attempts (delivery_id, response_status, error)
+VALUES ($1, $2, $3);
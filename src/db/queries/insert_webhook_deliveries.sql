@@ -0,0 +1,18 @@
+-- A delivery for every webhook of the partners that registered the plate
+INSERT INTO
+    webhook_deliveries (webhook_id, event, plate, payload)
+SELECT w.id, $2, $1::VARCHAR, $3
+FROM partner_webhooks w
+WHERE
+    EXISTS (
+        SELECT 1
+        FROM api_subscriptions a
+        WHERE
+            a.api_key_id = w.api_key_id
+            AND a.plate = $1::VARCHAR
+    )
+    AND (
+        w.plates IS NULL
+        OR $1::VARCHAR = ANY (w.plates)
+    )
+RETURNING id;
@@ -0,0 +1,7 @@
+-- Transient outcomes keep the last status the API gave about the vehicle
+UPDATE vehicles
+SET
+    checked_at = $1,
+    check_status = COALESCE($2, check_status)
+WHERE
+    plate = $3
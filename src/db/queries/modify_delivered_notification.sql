@@ -0,0 +1,7 @@
+UPDATE notifications
+SET
+    status = 'delivered',
+    delivered_at = $1,
+    last_error = NULL
+WHERE
+    id = $2;
@@ -0,0 +1 @@
+UPDATE notifications SET status = $1, last_error = $2, claimed_at = NULL WHERE id = $3;
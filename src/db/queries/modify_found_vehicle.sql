@@ -0,0 +1,9 @@
+UPDATE vehicles
+SET
+    found_at = $1,
+    location = $2,
+    registered_at = $3,
+    reference = $4,
+    contact = $5
+WHERE
+    plate = $6
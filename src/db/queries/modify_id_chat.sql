@@ -0,0 +1 @@
+UPDATE chats SET id = $2 WHERE id = $1
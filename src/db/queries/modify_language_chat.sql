@@ -0,0 +1 @@
+UPDATE chats SET language_code = $1 WHERE id = $2
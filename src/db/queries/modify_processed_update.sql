@@ -0,0 +1 @@
+UPDATE processed_updates SET processed_at = NOW() WHERE update_id = $1;
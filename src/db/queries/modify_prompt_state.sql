@@ -0,0 +1 @@
+UPDATE chats SET state = $1, state_user_id = $2 WHERE id = $3
@@ -1 +1 @@
-UPDATE chats SET state = $1 WHERE id = $2
+UPDATE chats SET state = $1, state_user_id = NULL WHERE id = $2
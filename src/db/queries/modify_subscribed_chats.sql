@@ -1 +0,0 @@
-UPDATE chats SET subscribed_vehicles = $1 WHERE id = $2
\ No newline at end of file
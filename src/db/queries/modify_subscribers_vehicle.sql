@@ -1 +0,0 @@
-UPDATE vehicles SET subscribers_ids = $1 WHERE plate = $2
\ No newline at end of file
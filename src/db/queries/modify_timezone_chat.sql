@@ -0,0 +1 @@
+UPDATE chats SET timezone = $1 WHERE id = $2
@@ -0,0 +1 @@
+UPDATE webhook_deliveries SET status = $1, delivered_at = $2, claimed_at = NULL WHERE id = $3;
@@ -0,0 +1,8 @@
+-- Gives the claim back when the attempt failed before storing its result
+UPDATE notifications
+SET
+    status = 'pending',
+    claimed_at = NULL
+WHERE
+    id = $1
+    AND status = 'sending';
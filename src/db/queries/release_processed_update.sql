@@ -0,0 +1,5 @@
+-- Gives the claim back when the update failed, it's processed when Telegram sends it again
+DELETE FROM processed_updates
+WHERE
+    update_id = $1
+    AND processed_at IS NULL;
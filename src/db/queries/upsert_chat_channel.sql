@@ -0,0 +1,18 @@
+-- The address is kept when it isn't given, e.g. while disabling the channel
+INSERT INTO
+    chat_channels (
+        chat_id,
+        channel,
+        address,
+        enabled
+    )
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (chat_id, channel) DO
+UPDATE
+SET
+    address = COALESCE(
+        EXCLUDED.address,
+        chat_channels.address
+    ),
+    enabled = EXCLUDED.enabled
+RETURNING *;
@@ -0,0 +1,6 @@
+-- Each subscription keeps its first token
+INSERT INTO
+    shared_trackings (token, chat_id, plate)
+VALUES ($1, $2, $3)
+ON CONFLICT (chat_id, plate) DO UPDATE SET token = shared_trackings.token
+RETURNING *;
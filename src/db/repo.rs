@@ -6,13 +6,26 @@ use bb8_postgres::{
     PostgresConnectionManager,
 };
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 use tokio::sync::OnceCell;
 
+use crate::i18n::Lang;
 use crate::DATABASE_URL;
 
 use super::{
-    model::{chat::Chat, client_state::ClientState, vehicle::Vehicle},
+    model::{
+        api_key::ApiKey,
+        api_subscription::ApiSubscription,
+        chat::{Chat, ChatKind},
+        chat_channel::{ChatChannel, NotificationChannel},
+        client_state::ClientState,
+        notification::{Notification, NotificationStatus},
+        partner_webhook::{PartnerWebhook, WebhookAttempt, WebhookDelivery},
+        shared_tracking::SharedTracking,
+        subscription::{SubscribeOutcome, Subscription},
+        vehicle::{FoundDetails, Vehicle},
+    },
     BotDbError,
 };
 
@@ -21,25 +34,77 @@ static REPO: OnceCell<Repo> = OnceCell::const_new();
 const INSERT_CHAT: &str = include_str!("queries/insert_chat.sql");
 const INSERT_VEHICLE: &str = include_str!("queries/insert_vehicle.sql");
 const INSERT_VEHICLE_PLATE: &str = include_str!("queries/insert_vehicle_plate.sql");
+const INSERT_SUBSCRIPTION: &str = include_str!("queries/insert_subscription.sql");
+const INSERT_MISSING_VEHICLE: &str = include_str!("queries/insert_missing_vehicle.sql");
 const DELETE_CHAT: &str = include_str!("queries/delete_chat.sql");
+const DELETE_SUBSCRIPTION: &str = include_str!("queries/delete_subscription.sql");
 const CHECK_CHAT_EXISTS: &str = include_str!("queries/check_chat_exists.sql");
 const GET_CHAT: &str = include_str!("queries/get_chat.sql");
 const GET_VEHICLE: &str = include_str!("queries/get_vehicle.sql");
-const GET_VEHICLES: &str = include_str!("queries/get_vehicles.sql");
+const GET_PENDING_VEHICLES: &str = include_str!("queries/get_pending_vehicles.sql");
+const GET_VEHICLES_BY_CHAT_ID: &str = include_str!("queries/get_vehicles_by_chat_id.sql");
+const GET_SUBSCRIPTIONS_BY_PLATE: &str = include_str!("queries/get_subscriptions_by_plate.sql");
+const GET_ACTIVE_SUBSCRIBERS_BY_PLATE: &str =
+    include_str!("queries/get_active_subscribers_by_plate.sql");
 const MODIFY_STATE: &str = include_str!("queries/modify_state.sql");
+const MODIFY_PROMPT_STATE: &str = include_str!("queries/modify_prompt_state.sql");
 const MODIFY_ACTIVE_CHAT: &str = include_str!("queries/modify_active_chat.sql");
+const MODIFY_LANGUAGE_CHAT: &str = include_str!("queries/modify_language_chat.sql");
+const MODIFY_TIMEZONE_CHAT: &str = include_str!("queries/modify_timezone_chat.sql");
+const UPSERT_SHARED_TRACKING: &str = include_str!("queries/upsert_shared_tracking.sql");
+const GET_SHARED_TRACKING: &str = include_str!("queries/get_shared_tracking.sql");
+const MODIFY_ID_CHAT: &str = include_str!("queries/modify_id_chat.sql");
+const DELETE_MIGRATED_CHAT: &str = include_str!("queries/delete_migrated_chat.sql");
 const MODIFY_FOUND_AT_VEHICLE: &str = include_str!("queries/modify_found_at vehicle.sql");
-const CONCANT_CHAT_TO_SUBSCRIBERS: &str = include_str!("queries/concat_to_subscribers.sql");
-const CONCAT_VEHICLE_TO_SUBSCRIPTIONS: &str =
-    include_str!("queries/concat_to_subscribed_vehicles.sql");
+const MODIFY_CHECKED_VEHICLE: &str = include_str!("queries/modify_checked_vehicle.sql");
+const MODIFY_FOUND_VEHICLE: &str = include_str!("queries/modify_found_vehicle.sql");
 const _DELETE_VEHICLE: &str = include_str!("queries/delete_vehicle.sql");
 const _DELETE_ALL_FANG_TASKS_BY_PROFILE_ID: &str =
     include_str!("queries/delete_all_tasks_by_profile_id.sql");
-const DELETE_FETCH_TASK_BY_PLATE: &str = include_str!("queries/delete_fetch_tasks_by_plate.sql");
-const MODIFY_SUBSCRIBERS_VEHICLE: &str = include_str!("queries/modify_subscribers_vehicle.sql");
-const MODIFY_SUBSCRIBED_CHAT: &str = include_str!("queries/modify_subscribed_chats.sql");
-const FILTER_ACTIVE_CHATS: &str = include_str!("queries/filter_active_chats.sql");
 const COUNT_SUBSCRIBERS_PLATE: &str = include_str!("queries/count_subscribers_plate.sql");
+const COUNT_ALL_SUBSCRIBERS_PLATE: &str = include_str!("queries/count_all_subscribers_plate.sql");
+const COUNT_SUBSCRIPTIONS_CHAT: &str = include_str!("queries/count_subscriptions_chat.sql");
+const INSERT_FOUND_NOTIFICATIONS: &str = include_str!("queries/insert_found_notifications.sql");
+const GET_NOTIFICATION: &str = include_str!("queries/get_notification.sql");
+const GET_PENDING_NOTIFICATIONS: &str = include_str!("queries/get_pending_notifications.sql");
+const CLAIM_NOTIFICATION: &str = include_str!("queries/claim_notification.sql");
+const RELEASE_NOTIFICATION: &str = include_str!("queries/release_notification.sql");
+const MODIFY_DELIVERED_NOTIFICATION: &str =
+    include_str!("queries/modify_delivered_notification.sql");
+const MODIFY_FAILED_NOTIFICATION: &str = include_str!("queries/modify_failed_notification.sql");
+const INSERT_PARTNER_WEBHOOK: &str = include_str!("queries/insert_partner_webhook.sql");
+const GET_PARTNER_WEBHOOKS_BY_API_KEY: &str =
+    include_str!("queries/get_partner_webhooks_by_api_key.sql");
+const GET_PARTNER_WEBHOOK: &str = include_str!("queries/get_partner_webhook.sql");
+const DELETE_PARTNER_WEBHOOK: &str = include_str!("queries/delete_partner_webhook.sql");
+const INSERT_WEBHOOK_DELIVERIES: &str = include_str!("queries/insert_webhook_deliveries.sql");
+const GET_WEBHOOK_DELIVERY: &str = include_str!("queries/get_webhook_delivery.sql");
+const GET_PENDING_WEBHOOK_DELIVERIES: &str =
+    include_str!("queries/get_pending_webhook_deliveries.sql");
+const GET_WEBHOOK_DELIVERIES_BY_WEBHOOK: &str =
+    include_str!("queries/get_webhook_deliveries_by_webhook.sql");
+const CLAIM_WEBHOOK_DELIVERY: &str = include_str!("queries/claim_webhook_delivery.sql");
+const RELEASE_WEBHOOK_DELIVERY: &str = include_str!("queries/release_webhook_delivery.sql");
+const MODIFY_WEBHOOK_DELIVERY: &str = include_str!("queries/modify_webhook_delivery.sql");
+const INSERT_WEBHOOK_ATTEMPT: &str = include_str!("queries/insert_webhook_attempt.sql");
+const GET_WEBHOOK_ATTEMPTS: &str = include_str!("queries/get_webhook_attempts.sql");
+const CLAIM_PROCESSED_UPDATE: &str = include_str!("queries/claim_processed_update.sql");
+const MODIFY_PROCESSED_UPDATE: &str = include_str!("queries/modify_processed_update.sql");
+const RELEASE_PROCESSED_UPDATE: &str = include_str!("queries/release_processed_update.sql");
+const CHECK_PROCESSED_UPDATE: &str = include_str!("queries/check_processed_update.sql");
+const DELETE_PROCESSED_UPDATES: &str = include_str!("queries/delete_processed_updates.sql");
+const GET_CHAT_CHANNELS: &str = include_str!("queries/get_chat_channels.sql");
+const UPSERT_CHAT_CHANNEL: &str = include_str!("queries/upsert_chat_channel.sql");
+const PING: &str = include_str!("queries/ping.sql");
+const COUNT_OVERDUE_TASKS: &str = include_str!("queries/count_overdue_tasks.sql");
+const COUNT_ACTIVE_CHATS: &str = include_str!("queries/count_active_chats.sql");
+const COUNT_PENDING_VEHICLES: &str = include_str!("queries/count_pending_vehicles.sql");
+const INSERT_API_KEY: &str = include_str!("queries/insert_api_key.sql");
+const GET_API_KEY_BY_HASH: &str = include_str!("queries/get_api_key_by_hash.sql");
+const INSERT_API_SUBSCRIPTION: &str = include_str!("queries/insert_api_subscription.sql");
+const DELETE_API_SUBSCRIPTION: &str = include_str!("queries/delete_api_subscription.sql");
+const GET_FOUND_VEHICLES_BY_API_KEY: &str =
+    include_str!("queries/get_found_vehicles_by_api_key.sql");
 
 #[derive(Debug)]
 pub struct Repo {
@@ -84,6 +149,26 @@ impl Repo {
         })
     }
 
+    /// Checks a connection of the pool can reach the database
+    pub async fn ping(&self) -> Result<(), BotDbError> {
+        let connection = self.pool.get().await?;
+        connection.query_one(PING, &[]).await?;
+        Ok(())
+    }
+
+    /// Number of fang tasks that should have started before `scheduled_before`
+    pub async fn count_overdue_tasks(
+        &self,
+        scheduled_before: DateTime<Utc>,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+        let n: i64 = connection
+            .query_one(COUNT_OVERDUE_TASKS, &[&scheduled_before])
+            .await?
+            .get(0);
+        Ok(n as u64)
+    }
+
     pub fn as_u64_le(array: &[u8; 8]) -> u64 {
         (array[0] as u64)
             + ((array[1] as u64) << 8)
@@ -119,10 +204,25 @@ impl Repo {
         Ok(row.into())
     }
 
+    /// Chat of a user that may have never talked to the bot, like the ones using inline mode
+    pub async fn find_chat(&self, chat_id: &i64) -> Result<Option<Chat>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_opt(GET_CHAT, &[chat_id]).await?;
+
+        Ok(row.map(|row| row.into()))
+    }
+
     #[cfg(test)]
     pub async fn get_testing_chat(&self) -> Result<Chat, BotDbError> {
         match self
-            .find_or_create_chat(&i64::MAX, 1361356382_u64, "Quevedo", &None)
+            .find_or_create_chat(
+                &i64::MAX,
+                1361356382_u64,
+                "Quevedo",
+                &None,
+                ChatKind::Private,
+            )
             .await
         {
             Ok(result) => Ok(result.0),
@@ -138,30 +238,39 @@ impl Repo {
 
     // Getters
     pub async fn get_vehicles_by_chat_id(&self, chat_id: &i64) -> Result<Vec<Vehicle>, BotDbError> {
-        let chat = self.get_chat(chat_id).await?;
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(GET_VEHICLES_BY_CHAT_ID, &[chat_id])
+            .await?;
 
-        match chat.subscribed_vehicles {
-            Some(mut subs) => self.get_vehicles_from_subs_string(&mut subs).await,
+        let vehicles: Vec<Vehicle> = rows.into_iter().map(|row| row.into()).collect();
 
-            None => Ok(vec![]),
-        }
+        Ok(vehicles)
     }
 
-    async fn get_vehicles_from_subs_string(
-        &self,
-        subscribed_vehicles: &mut String,
-    ) -> Result<Vec<Vehicle>, BotDbError> {
+    /// Vehicles the sweep has to look up
+    pub async fn get_pending_vehicles(&self) -> Result<Vec<Vehicle>, BotDbError> {
         let connection = self.pool.get().await?;
 
-        subscribed_vehicles.retain(|c| !c.is_whitespace());
+        let rows = connection.query(GET_PENDING_VEHICLES, &[]).await?;
 
-        let rows = connection
-            .query(GET_VEHICLES, &[subscribed_vehicles])
-            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
 
-        let vehicles: Vec<Vehicle> = rows.into_iter().map(|row| row.into()).collect();
+    pub async fn count_active_chats(&self) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+        let n: i64 = connection.query_one(COUNT_ACTIVE_CHATS, &[]).await?.get(0);
+        Ok(n as u64)
+    }
 
-        Ok(vehicles)
+    pub async fn count_pending_vehicles(&self) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+        let n: i64 = connection
+            .query_one(COUNT_PENDING_VEHICLES, &[])
+            .await?
+            .get(0);
+        Ok(n as u64)
     }
 
     pub async fn get_vehicle(&self, plate: &str) -> Result<Vehicle, BotDbError> {
@@ -172,6 +281,14 @@ impl Repo {
         Ok(row.into())
     }
 
+    pub async fn find_vehicle(&self, plate: &str) -> Result<Option<Vehicle>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_opt(GET_VEHICLE, &[&plate]).await?;
+
+        Ok(row.map(|row| row.into()))
+    }
+
     async fn check_user_exists(&self, chat_id: &i64) -> Result<bool, BotDbError> {
         let connection = self.pool.get().await?;
 
@@ -188,6 +305,7 @@ impl Repo {
         user_id: u64,
         username: &str,
         language_code: &Option<String>,
+        kind: ChatKind,
     ) -> Result<(Chat, bool), BotDbError> {
         if self.check_user_exists(chat_id).await? {
             let chat = self.get_chat(chat_id).await?;
@@ -195,7 +313,7 @@ impl Repo {
             Ok((chat, false))
         } else {
             let chat = self
-                .insert_chat(chat_id, user_id, username, language_code)
+                .insert_chat(chat_id, user_id, username, language_code, kind)
                 .await?;
 
             Ok((chat, true))
@@ -215,7 +333,14 @@ impl Repo {
         let row = match connection
             .query_one(
                 INSERT_VEHICLE,
-                &[&vehicle.plate, &vehicle.subscribers_ids, &vehicle.found_at],
+                &[
+                    &vehicle.plate,
+                    &vehicle.found_at,
+                    &vehicle.details.location,
+                    &vehicle.details.registered_at,
+                    &vehicle.details.reference,
+                    &vehicle.details.contact,
+                ],
             )
             .await
         {
@@ -249,6 +374,7 @@ impl Repo {
         user_id: u64,
         username: &'_ str,
         language_code: &Option<String>,
+        kind: ChatKind,
     ) -> Result<Chat, BotDbError> {
         let connection = self.pool.get().await?;
 
@@ -263,6 +389,7 @@ impl Repo {
                     &bytes,
                     &username,
                     language_code,
+                    &kind,
                 ],
             )
             .await
@@ -298,6 +425,53 @@ impl Repo {
         Ok(n)
     }
 
+    /// Records the lookup, without a status the previous one is kept
+    pub async fn modify_checked_vehicle(
+        &self,
+        plate: &str,
+        checked_at: DateTime<Utc>,
+        status: Option<&str>,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(MODIFY_CHECKED_VEHICLE, &[&checked_at, &status, &plate])
+            .await?;
+        Ok(n)
+    }
+
+    /// Marks the vehicle as found, stores the details published with it and queues the
+    /// notifications of its active subscribers in the same transaction, returns their ids
+    pub async fn modify_found_vehicle(
+        &self,
+        plate: &str,
+        found_at: DateTime<Utc>,
+        details: &FoundDetails,
+    ) -> Result<Vec<i64>, BotDbError> {
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+
+        transaction
+            .execute(
+                MODIFY_FOUND_VEHICLE,
+                &[
+                    &found_at,
+                    &details.location,
+                    &details.registered_at,
+                    &details.reference,
+                    &details.contact,
+                    &plate,
+                ],
+            )
+            .await?;
+        let rows = transaction
+            .query(INSERT_FOUND_NOTIFICATIONS, &[&plate, &found_at])
+            .await?;
+
+        transaction.commit().await?;
+        Ok(rows.into_iter().map(|row| row.get("id")).collect())
+    }
+
     pub async fn modify_active_chat(
         &self,
         chat_id: &i64,
@@ -311,6 +485,41 @@ impl Repo {
         Ok(n)
     }
 
+    /// Replaces the language given by Telegram with the one chosen by the chat
+    pub async fn modify_language_chat(&self, chat_id: &i64, lang: Lang) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(MODIFY_LANGUAGE_CHAT, &[&lang.code(), chat_id])
+            .await?;
+        Ok(n)
+    }
+
+    pub async fn modify_timezone_chat(&self, chat_id: &i64, tz: Tz) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(MODIFY_TIMEZONE_CHAT, &[&tz.name(), chat_id])
+            .await?;
+        Ok(n)
+    }
+
+    /// A group became a supergroup, its plates and settings move to the new id.
+    /// Returns false when the old group was unknown
+    pub async fn migrate_chat(&self, from: &i64, to: &i64) -> Result<bool, BotDbError> {
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
+
+        transaction
+            .execute(DELETE_MIGRATED_CHAT, &[from, to])
+            .await?;
+        let n = transaction.execute(MODIFY_ID_CHAT, &[from, to]).await?;
+
+        transaction.commit().await?;
+
+        Ok(n == 1)
+    }
+
     pub async fn modify_state(
         &self,
         chat_id: &i64,
@@ -324,109 +533,152 @@ impl Repo {
         Ok(n)
     }
 
+    /// State waiting for the answer of `user_id`
+    pub async fn modify_prompt_state(
+        &self,
+        chat_id: &i64,
+        new_state: ClientState,
+        user_id: u64,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(
+                MODIFY_PROMPT_STATE,
+                &[&new_state, &(user_id as i64), chat_id],
+            )
+            .await?;
+        Ok(n)
+    }
+
+    // Notification channels
+
+    /// Only the channels the chat has changed, Telegram is enabled when missing
+    pub async fn get_chat_channels(&self, chat_id: &i64) -> Result<Vec<ChatChannel>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection.query(GET_CHAT_CHANNELS, &[chat_id]).await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    pub async fn get_chat_channel(
+        &self,
+        chat_id: &i64,
+        channel: NotificationChannel,
+    ) -> Result<Option<ChatChannel>, BotDbError> {
+        Ok(self
+            .get_chat_channels(chat_id)
+            .await?
+            .into_iter()
+            .find(|chat_channel| chat_channel.channel == channel))
+    }
+
+    /// Enables or disables the channel, the previous address is kept when `address` is `None`
+    pub async fn modify_chat_channel(
+        &self,
+        chat_id: &i64,
+        channel: NotificationChannel,
+        address: Option<&str>,
+        enabled: bool,
+    ) -> Result<ChatChannel, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection
+            .query_one(
+                UPSERT_CHAT_CHANNEL,
+                &[chat_id, &channel, &address, &enabled],
+            )
+            .await?;
+        Ok(row.into())
+    }
+
     //Subscriptions
 
     pub async fn get_active_subscriptions_from_vehicle(
         &self,
         plate: &str,
     ) -> Result<Vec<Chat>, BotDbError> {
-        let chat_ids = self.get_vehicle(plate).await?.subscribers_ids;
-
-        if chat_ids.is_none() {
-            return Ok(vec![]);
-        }
-
         let connection = self.pool.get().await?;
 
-        let mut chat_ids_str = chat_ids.unwrap();
-        chat_ids_str.pop(); // Removes the last ","
-        chat_ids_str.retain(|c| !c.is_whitespace());
-
         let active_chats: Vec<Row> = connection
-            .query(FILTER_ACTIVE_CHATS, &[&chat_ids_str])
+            .query(GET_ACTIVE_SUBSCRIBERS_BY_PLATE, &[&plate])
             .await?;
 
         Ok(active_chats.into_iter().map(|row| row.into()).collect())
     }
 
-    pub async fn get_subscriptions_from_vehicle_as_string(
+    pub async fn get_subscriptions_by_plate(
         &self,
         plate: &str,
-    ) -> Result<Option<String>, BotDbError> {
-        Ok(self.get_vehicle(plate).await?.subscribers_ids)
+    ) -> Result<Vec<Subscription>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(GET_SUBSCRIPTIONS_BY_PLATE, &[&plate])
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
     }
 
+    /// Number of active chats subscribed to the vehicle
     pub async fn get_n_subscribers_by_plate(&self, plate: &str) -> Result<u64, BotDbError> {
         let connection = self.pool.get().await?;
-        let n = connection
-            .execute(COUNT_SUBSCRIBERS_PLATE, &[&plate])
-            .await?;
-        Ok(n)
+        let n: i64 = connection
+            .query_one(COUNT_SUBSCRIBERS_PLATE, &[&plate])
+            .await?
+            .get(0);
+        Ok(n as u64)
     }
 
-    pub async fn append_subscription_to_chat(
-        &self,
-        plate: &str,
-        chat_id: &i64,
-    ) -> Result<(), BotDbError> {
+    pub async fn create_subscription(&self, plate: &str, chat_id: i64) -> Result<(), BotDbError> {
         let connection = self.pool.get().await?;
 
-        connection
-            .execute(CONCAT_VEHICLE_TO_SUBSCRIPTIONS, &[&plate, &chat_id])
+        let n = connection
+            .execute(INSERT_SUBSCRIPTION, &[&chat_id, &plate])
             .await?;
 
-        Ok(())
+        if n == 0 {
+            return Err(BotDbError::AlreadySubscribedError(
+                chat_id,
+                plate.to_string(),
+            ));
+        }
 
-        // match connection
-        //     .execute(CONCAT_VEHICLE_TO_SUBSCRIPTIONS, &[&plate, &chat_id])
-        //     .await
-        // {
-        //     Ok(n) if n > 0 => Ok(()),
-        //     Ok(_) => Err(BotDbError::AlreadySubscribedError(
-        //         *chat_id,
-        //         plate.to_string(),
-        //     )),
-        //     Err(err) => Err(BotDbError::PgError(err)),
-        // }
+        Ok(())
     }
 
-    pub async fn create_subscription(&self, plate: &str, chat_id: i64) -> Result<(), BotDbError> {
-        let current_subscriptions = self.get_subscriptions_from_vehicle_as_string(plate).await?;
-
-        if current_subscriptions.is_some_and(|list| {
-            list.split(',')
-                .map(str::trim)
-                .any(|subscribed_id| subscribed_id == chat_id.to_string())
-        }) {
-            // return Err(BotDbError::AlreadySubscribedError(
-            //     chat_id,
-            //     plate.to_string(),
-            // ));
-            return Ok(());
-        }
-
+    /// Follows every plate in one transaction, the outcomes are in the order of the plates
+    pub async fn create_subscriptions(
+        &self,
+        plates: &[String],
+        chat_id: i64,
+    ) -> Result<Vec<SubscribeOutcome>, BotDbError> {
         let mut connection = self.pool.get().await?;
-
         let transaction = connection.transaction().await?;
 
-        let n1 = transaction
-            .execute(CONCANT_CHAT_TO_SUBSCRIBERS, &[&chat_id.to_string(), &plate])
-            .await?;
-        let n2 = transaction
-            .execute(CONCAT_VEHICLE_TO_SUBSCRIPTIONS, &[&plate, &chat_id])
-            .await?;
-
-        if n1 == n2 {
-            transaction.commit().await?;
-            Ok(())
-        } else {
-            transaction.rollback().await?;
-            Err(BotDbError::SubscriptionError(
-                chat_id,
-                plate.to_string(),
-                format!(" Create subscription ->{n1} != {n2}"),
-            ))
+        let mut outcomes = Vec::with_capacity(plates.len());
+        for plate in plates {
+            transaction
+                .execute(INSERT_MISSING_VEHICLE, &[plate])
+                .await?;
+            let vehicle: Vehicle = transaction.query_one(GET_VEHICLE, &[plate]).await?.into();
+
+            let outcome = match vehicle.found_at {
+                Some(found_at) => SubscribeOutcome::AlreadyFound(found_at),
+                None => match transaction
+                    .execute(INSERT_SUBSCRIPTION, &[&chat_id, plate])
+                    .await?
+                {
+                    0 => SubscribeOutcome::AlreadyFollowed,
+                    _ => SubscribeOutcome::Added,
+                },
+            };
+            outcomes.push(outcome);
         }
+
+        transaction.commit().await?;
+
+        Ok(outcomes)
     }
 
     /// Returns the new size of subscriptions and subscribers lists
@@ -435,76 +687,422 @@ impl Repo {
         plate: &str,
         chat_id: i64,
     ) -> Result<(u64, u64), BotDbError> {
-        let current_subscribers = self.get_subscriptions_from_vehicle_as_string(plate).await?;
-        let current_subscriptions = self.get_chat(&chat_id).await?.subscribed_vehicles;
-
-        let (n_subscribers, updated_subscribers) =
-            Self::pop_member_from_subs_string(current_subscribers.clone(), chat_id.to_string());
+        let mut connection = self.pool.get().await?;
+        let transaction = connection.transaction().await?;
 
-        let (n_subscriptions, updated_subscriptions) =
-            Self::pop_member_from_subs_string(current_subscriptions.clone(), plate.to_string());
+        let n = transaction
+            .execute(DELETE_SUBSCRIPTION, &[&chat_id, &plate])
+            .await?;
 
-        if n_subscribers == 0 || n_subscriptions == 0 {
-            let reason = if n_subscribers == 0 {
-                format!("End Subscription -> The vehicle {plate} doesn't have any subscribers")
-            } else {
-                format!("End Subscription -> User {chat_id} doesn't have any subscription at the moment")
-            };
+        if n == 0 {
+            transaction.rollback().await?;
             return Err(BotDbError::SubscriptionError(
                 chat_id,
                 plate.to_string(),
-                reason,
+                format!(
+                    "End Subscription -> User {chat_id} is not subscribed to the vehicle {plate}"
+                ),
             ));
         }
 
+        let n_subscribers: i64 = transaction
+            .query_one(COUNT_ALL_SUBSCRIBERS_PLATE, &[&plate])
+            .await?
+            .get(0);
+
+        let n_subscriptions: i64 = transaction
+            .query_one(COUNT_SUBSCRIPTIONS_CHAT, &[&chat_id])
+            .await?
+            .get(0);
+
+        transaction.commit().await?;
+
+        Ok((n_subscribers as u64, n_subscriptions as u64))
+    }
+
+    /// Link to the subscription of the chat, the same one every time
+    pub async fn share_subscription(
+        &self,
+        chat_id: i64,
+        plate: &str,
+    ) -> Result<SharedTracking, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection
+            .query_one(
+                UPSERT_SHARED_TRACKING,
+                &[&SharedTracking::generate_token(), &chat_id, &plate],
+            )
+            .await?;
+
+        Ok(row.into())
+    }
+
+    pub async fn get_shared_tracking(
+        &self,
+        token: &str,
+    ) -> Result<Option<SharedTracking>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_opt(GET_SHARED_TRACKING, &[&token]).await?;
+
+        Ok(row.map(|row| row.into()))
+    }
+
+    // API keys
+
+    /// Stores a new key for the partner, returns it along with the key in clear
+    pub async fn create_api_key(&self, name: &str) -> Result<(ApiKey, String), BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let key = ApiKey::generate();
+        let row = connection
+            .query_one(INSERT_API_KEY, &[&name, &ApiKey::hash(&key)])
+            .await?;
+
+        Ok((row.into(), key))
+    }
+
+    /// The partner the key belongs to, `None` if it's unknown or revoked
+    pub async fn get_api_key(&self, key: &str) -> Result<Option<ApiKey>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection
+            .query_opt(GET_API_KEY_BY_HASH, &[&ApiKey::hash(key)])
+            .await?;
+        Ok(row.map(|row| row.into()))
+    }
+
+    /// `None` if the plate was already registered with the key
+    pub async fn create_api_subscription(
+        &self,
+        api_key_id: i64,
+        plate: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<ApiSubscription>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection
+            .query_opt(INSERT_API_SUBSCRIPTION, &[&api_key_id, &plate, &reference])
+            .await?;
+        Ok(row.map(|row| row.into()))
+    }
+
+    pub async fn end_api_subscription(
+        &self,
+        api_key_id: i64,
+        plate: &str,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(DELETE_API_SUBSCRIPTION, &[&api_key_id, &plate])
+            .await?;
+        Ok(n)
+    }
+
+    /// Vehicles registered with the key that were found after `since`
+    pub async fn get_found_vehicles_by_api_key(
+        &self,
+        api_key_id: i64,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Vehicle>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(GET_FOUND_VEHICLES_BY_API_KEY, &[&api_key_id, &since])
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    // Partner webhooks
+
+    pub async fn create_partner_webhook(
+        &self,
+        api_key_id: i64,
+        url: &str,
+        plates: Option<&[String]>,
+    ) -> Result<PartnerWebhook, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let secret = PartnerWebhook::generate_secret();
+        let row = connection
+            .query_one(
+                INSERT_PARTNER_WEBHOOK,
+                &[&api_key_id, &url, &secret, &plates],
+            )
+            .await?;
+        Ok(row.into())
+    }
+
+    pub async fn get_partner_webhooks(
+        &self,
+        api_key_id: i64,
+    ) -> Result<Vec<PartnerWebhook>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(GET_PARTNER_WEBHOOKS_BY_API_KEY, &[&api_key_id])
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    pub async fn get_partner_webhook(&self, id: i64) -> Result<PartnerWebhook, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_one(GET_PARTNER_WEBHOOK, &[&id]).await?;
+        Ok(row.into())
+    }
+
+    /// Only the owner of the webhook can delete it
+    pub async fn delete_partner_webhook(
+        &self,
+        id: i64,
+        api_key_id: i64,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(DELETE_PARTNER_WEBHOOK, &[&id, &api_key_id])
+            .await?;
+        Ok(n)
+    }
+
+    /// Queues the event for every webhook interested in the plate, returns the new deliveries
+    pub async fn create_webhook_deliveries(
+        &self,
+        plate: &str,
+        event: &str,
+        payload: &str,
+    ) -> Result<Vec<i64>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(INSERT_WEBHOOK_DELIVERIES, &[&plate, &event, &payload])
+            .await?;
+        Ok(rows.into_iter().map(|row| row.get("id")).collect())
+    }
+
+    pub async fn get_webhook_delivery(&self, id: i64) -> Result<WebhookDelivery, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_one(GET_WEBHOOK_DELIVERY, &[&id]).await?;
+        Ok(row.into())
+    }
+
+    pub async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(GET_PENDING_WEBHOOK_DELIVERIES, &[])
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// Latest deliveries of a webhook of the partner
+    pub async fn get_webhook_deliveries(
+        &self,
+        webhook_id: i64,
+        api_key_id: i64,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(
+                GET_WEBHOOK_DELIVERIES_BY_WEBHOOK,
+                &[&webhook_id, &api_key_id, &limit],
+            )
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// Marks the delivery as being sent, `None` if it isn't pending anymore
+    /// or another worker claimed it recently
+    pub async fn claim_webhook_delivery(
+        &self,
+        id: i64,
+    ) -> Result<Option<WebhookDelivery>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_opt(CLAIM_WEBHOOK_DELIVERY, &[&id]).await?;
+        Ok(row.map(|row| row.into()))
+    }
+
+    /// Puts a claimed delivery back to pending
+    pub async fn release_webhook_delivery(&self, id: i64) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection.execute(RELEASE_WEBHOOK_DELIVERY, &[&id]).await?;
+        Ok(n)
+    }
+
+    /// Records the attempt and the new status of the delivery
+    pub async fn modify_webhook_delivery(
+        &self,
+        id: i64,
+        status: NotificationStatus,
+        response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> Result<(), BotDbError> {
         let mut connection = self.pool.get().await?;
         let transaction = connection.transaction().await?;
 
-        let n = transaction
-            .execute(MODIFY_SUBSCRIBERS_VEHICLE, &[&updated_subscribers, &plate])
+        let delivered_at = (status == NotificationStatus::Delivered).then(Utc::now);
+
+        transaction
+            .execute(MODIFY_WEBHOOK_DELIVERY, &[&status, &delivered_at, &id])
+            .await?;
+        transaction
+            .execute(INSERT_WEBHOOK_ATTEMPT, &[&id, &response_status, &error])
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_webhook_attempts(
+        &self,
+        delivery_id: i64,
+    ) -> Result<Vec<WebhookAttempt>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(GET_WEBHOOK_ATTEMPTS, &[&delivery_id])
+            .await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    // Processed updates
+
+    /// Whether the update has already been processed
+    pub async fn check_processed_update(&self, update_id: u32) -> Result<bool, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(CHECK_PROCESSED_UPDATE, &[&i64::from(update_id)])
             .await?;
+        Ok(n == 1)
+    }
+
+    /// Claims the update before processing it, `false` if it's already been processed
+    /// or another worker claimed it recently
+    pub async fn claim_update(&self, update_id: u32) -> Result<bool, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection
+            .query_opt(CLAIM_PROCESSED_UPDATE, &[&i64::from(update_id)])
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Remembers the claimed update has been processed
+    pub async fn modify_processed_update(&self, update_id: u32) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(MODIFY_PROCESSED_UPDATE, &[&i64::from(update_id)])
+            .await?;
+        Ok(n)
+    }
+
+    /// Forgets the claim of an update that failed
+    pub async fn release_update(&self, update_id: u32) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(RELEASE_PROCESSED_UPDATE, &[&i64::from(update_id)])
+            .await?;
+        Ok(n)
+    }
+
+    /// Forgets the updates received before `older_than`
+    pub async fn delete_processed_updates(
+        &self,
+        older_than: DateTime<Utc>,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let n = connection
+            .execute(DELETE_PROCESSED_UPDATES, &[&older_than])
+            .await?;
+        Ok(n)
+    }
+
+    // Notifications
+
+    /// Queues a notification for every active subscriber of the found vehicle,
+    /// returns the ids of the new ones
+    pub async fn create_found_notifications(
+        &self,
+        plate: &str,
+        found_at: DateTime<Utc>,
+    ) -> Result<Vec<i64>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection
+            .query(INSERT_FOUND_NOTIFICATIONS, &[&plate, &found_at])
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("id")).collect())
+    }
+
+    pub async fn get_notification(&self, id: i64) -> Result<Notification, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let row = connection.query_one(GET_NOTIFICATION, &[&id]).await?;
+        Ok(row.into())
+    }
+
+    pub async fn get_pending_notifications(&self) -> Result<Vec<Notification>, BotDbError> {
+        let connection = self.pool.get().await?;
+
+        let rows = connection.query(GET_PENDING_NOTIFICATIONS, &[]).await?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
 
-        let n1 = transaction
-            .execute(MODIFY_SUBSCRIBED_CHAT, &[&updated_subscriptions, &chat_id])
-            .await?;
+    /// Marks the notification as being sent, `None` if it isn't pending anymore
+    /// or another worker claimed it recently
+    pub async fn claim_notification(&self, id: i64) -> Result<Option<Notification>, BotDbError> {
+        let connection = self.pool.get().await?;
 
-        if n != n1 {
-            transaction.rollback().await?;
-            return Err(BotDbError::SubscriptionError(
-                chat_id,
-                plate.to_string(),
-                format!("End Subscription -> Update query failed: {} != {}", n, n1),
-            ));
-        }
+        let row = connection.query_opt(CLAIM_NOTIFICATION, &[&id]).await?;
+        Ok(row.map(|row| row.into()))
+    }
 
-        transaction.commit().await?;
+    /// Puts a claimed notification back to pending
+    pub async fn release_notification(&self, id: i64) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
 
-        Ok((n_subscribers, n_subscriptions))
+        let n = connection.execute(RELEASE_NOTIFICATION, &[&id]).await?;
+        Ok(n)
     }
 
-    /// Works for both chat.subscribed_vehicles and vehicle.subscribed_ids
-    fn pop_member_from_subs_string(
-        subscription_string: Option<String>,
-        member: String,
-    ) -> (u64, Option<String>) {
-        match subscription_string {
-            Some(subscribers) => {
-                let valid_items: Vec<_> = subscribers
-                    .split(',')
-                    .map(str::trim)
-                    .filter(|&x| x != member)
-                    .collect();
+    pub async fn modify_delivered_notification(
+        &self,
+        id: i64,
+        delivered_at: DateTime<Utc>,
+    ) -> Result<u64, BotDbError> {
+        let connection = self.pool.get().await?;
 
-                (valid_items.len() as u64, Some(valid_items.join(",")))
-            }
-            None => (0, None),
-        }
+        let n = connection
+            .execute(MODIFY_DELIVERED_NOTIFICATION, &[&delivered_at, &id])
+            .await?;
+        Ok(n)
     }
 
-    pub async fn delete_tasks_by_plate(&self, plate: &str) -> Result<u64, BotDbError> {
+    /// Stores the error of the last attempt, the notification goes back to pending
+    /// unless `status` is `NotificationStatus::Failed`
+    pub async fn modify_failed_notification(
+        &self,
+        id: i64,
+        status: NotificationStatus,
+        error: &str,
+    ) -> Result<u64, BotDbError> {
         let connection = self.pool.get().await?;
+
         let n = connection
-            .execute(DELETE_FETCH_TASK_BY_PLATE, &[&plate])
+            .execute(MODIFY_FAILED_NOTIFICATION, &[&status, &error, &id])
             .await?;
         Ok(n)
     }
@@ -521,6 +1119,7 @@ pub mod db_tests {
     use std::ops::Not;
 
     use super::*;
+    use crate::i18n::Locale;
 
     pub fn random_datetime() -> DateTime<Utc> {
         use chrono::Utc;
@@ -670,7 +1269,13 @@ pub mod db_tests {
         let connection = db_controller.get_connection().get().await.unwrap();
 
         let chat = db_controller
-            .insert_chat(&999, 1111111, "hello", &Some("en".to_string()))
+            .insert_chat(
+                &999,
+                1111111,
+                "hello",
+                &Some("en".to_string()),
+                ChatKind::Private,
+            )
             .await
             .unwrap();
 
@@ -707,6 +1312,22 @@ pub mod db_tests {
 
         assert_eq!(chat.state, ClientState::Initial);
 
+        // Prompts remember who opened them until the state changes again
+        db_controller
+            .modify_prompt_state(&chat_id, ClientState::SetEmail, 42)
+            .await
+            .unwrap();
+        let chat = db_controller.get_chat(&chat_id).await.unwrap();
+        assert_eq!(chat.state, ClientState::SetEmail);
+        assert_eq!(chat.state_user_id, Some(42));
+
+        db_controller
+            .modify_state(&chat_id, ClientState::Initial)
+            .await
+            .unwrap();
+        let chat = db_controller.get_chat(&chat_id).await.unwrap();
+        assert_eq!(chat.state_user_id, None);
+
         let n = db_controller.delete_chat(&999).await.unwrap();
         assert_eq!(n, 1_u64);
         db_controller.cleanup_test_db().await.unwrap();
@@ -721,31 +1342,11 @@ pub mod db_tests {
             .await
             .unwrap();
 
-        let (chat, vehicle) =
+        let (subscriptions, subscribers) =
             test_subscribe_to_vehicle(testing_plate, testing_chat, &db_controller)
                 .await
                 .unwrap();
 
-        let subscribers: Vec<i64> = vehicle
-            .subscribers_ids
-            .unwrap()
-            .split(',')
-            .filter_map(|x| {
-                match x.parse::<i64>() {
-                    Ok(value) => Some(value), // Keep successfully parsed values
-                    Err(_) => {
-                        None // Skip the value if parsing fails
-                    }
-                }
-            })
-            .collect();
-
-        let mut subscriptions = chat.subscribed_vehicles.unwrap();
-
-        subscriptions.pop();
-
-        let subscriptions: Vec<String> = subscriptions.split(',').map(|x| x.to_string()).collect();
-
         assert!(
             subscribers.len() == 1,
             "subscribers -> {} vs 1",
@@ -769,29 +1370,11 @@ pub mod db_tests {
             .await
             .unwrap();
 
-        let (chat, vehicle) =
+        let (subscriptions, subscribers) =
             test_subscribe_to_vehicle(testing_plate, testing_chat, &db_controller)
                 .await
                 .unwrap();
 
-        let subscribers: Vec<i64> = vehicle
-            .subscribers_ids
-            .unwrap()
-            .split(',')
-            .filter_map(|x| {
-                match x.parse::<i64>() {
-                    Ok(value) => Some(value), // Keep successfully parsed values
-                    Err(_) => None,           // Skip the value if parsing fails
-                }
-            })
-            .collect();
-
-        let mut subscriptions = chat.subscribed_vehicles.unwrap();
-
-        subscriptions.pop();
-
-        let subscriptions: Vec<String> = subscriptions.split(',').map(|x| x.to_string()).collect();
-
         assert!(
             subscribers.len() == 3,
             "subscribers -> {} vs 3",
@@ -806,43 +1389,64 @@ pub mod db_tests {
         db_controller.cleanup_test_db().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_subscribe_twice() {
+        let testing_plate = "ABC123";
+        let testing_chat = 1;
+
+        let db_controller = Repo::new_for_test("test_subscribe_twice").await.unwrap();
+
+        let result = db_controller
+            .create_subscription(testing_plate, testing_chat)
+            .await;
+
+        assert!(
+            matches!(result, Err(BotDbError::AlreadySubscribedError(1, _))),
+            "{:?}",
+            result
+        );
+
+        let subscribers = db_controller
+            .get_subscriptions_by_plate(testing_plate)
+            .await
+            .unwrap();
+        assert_eq!(subscribers.len(), 1);
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    /// Returns the vehicles of the chat and the subscriptions of the vehicle
     async fn test_subscribe_to_vehicle(
         testing_plate: &str,
         testing_chat: i64,
         db_controller: &Repo,
-    ) -> Result<(Chat, Vehicle), BotDbError> {
+    ) -> Result<(Vec<Vehicle>, Vec<Subscription>), BotDbError> {
         // Añadir a uno vacío
         db_controller
             .create_subscription(testing_plate, testing_chat)
             .await
             .unwrap();
 
-        let chat = db_controller.get_chat(&testing_chat).await.unwrap();
-
-        assert!(chat
-            .subscribed_vehicles
-            .clone()
-            .is_some_and(|t| t.is_empty().not()));
+        let vehicles = db_controller
+            .get_vehicles_by_chat_id(&testing_chat)
+            .await
+            .unwrap();
 
-        let vehicle = db_controller.get_vehicle(testing_plate).await.unwrap();
+        assert!(vehicles.is_empty().not());
+        assert!(vehicles.iter().any(|subbed| subbed.plate == testing_plate));
 
-        assert!(&chat
-            .subscribed_vehicles
-            .clone()
-            .unwrap()
-            .split(',')
-            .any(|subbed| subbed == testing_plate));
+        let subscribers = db_controller
+            .get_subscriptions_by_plate(testing_plate)
+            .await
+            .unwrap();
 
-        assert!(&vehicle
-            .subscribers_ids
-            .clone()
-            .unwrap()
-            .split(',')
-            .any(|subscriber| subscriber == testing_chat.to_string()));
+        assert!(subscribers
+            .iter()
+            .any(|subscriber| subscriber.chat_id == testing_chat));
 
-        log::info!("CHAT -> {:?}", chat);
-        log::info!("VEHICLE -> {:?}", vehicle);
-        Ok((chat, vehicle))
+        log::info!("VEHICLES -> {:?}", vehicles);
+        log::info!("SUBSCRIBERS -> {:?}", subscribers);
+        Ok((vehicles, subscribers))
     }
 
     #[tokio::test]
@@ -860,19 +1464,13 @@ pub mod db_tests {
             .expect("Failed to create initial subscription");
 
         // Verify that the chat is subscribed to the vehicle
-        let chat = db_controller.get_chat(&testing_chat).await.unwrap();
-        assert!(chat
-            .subscribed_vehicles
-            .clone()
-            .is_some_and(|t| t.split(',').any(|plate| plate == testing_plate)));
-
-        let vehicle = db_controller.get_vehicle(testing_plate).await.unwrap();
-        assert!(vehicle
-            .subscribers_ids
-            .clone()
-            .unwrap_or_default()
-            .split(',')
-            .any(|id| id == testing_chat.to_string()));
+        let vehicles = db_controller
+            .get_vehicles_by_chat_id(&testing_chat)
+            .await
+            .unwrap();
+        assert!(vehicles
+            .iter()
+            .any(|vehicle| vehicle.plate == testing_plate));
 
         // Step 2: Call `end_subscription` to remove the subscription
         let (n_subscribers, n_subscriptions) = db_controller
@@ -880,40 +1478,51 @@ pub mod db_tests {
             .await
             .expect("Failed to end subscription");
 
-        // Verify the expected number of modifications
+        // Verify the size of the remaining lists
         assert_eq!(
-            n_subscribers, 1,
-            "Expected one subscriber to be removed from vehicle"
+            n_subscribers, 0,
+            "Expected the vehicle to be left without subscribers"
         );
         assert_eq!(
-            n_subscriptions, 1,
-            "Expected one subscription to be removed from chat"
+            n_subscriptions, 0,
+            "Expected the chat to be left without subscriptions"
         );
 
         // Step 3: Verify that the chat is no longer subscribed to the vehicle
-        let updated_chat = db_controller.get_chat(&testing_chat).await.unwrap();
+        let updated_vehicles = db_controller
+            .get_vehicles_by_chat_id(&testing_chat)
+            .await
+            .unwrap();
         assert!(
-            updated_chat
-                .subscribed_vehicles
-                .clone()
-                .is_some_and(|t| !t.contains(testing_plate)),
+            !updated_vehicles
+                .iter()
+                .any(|vehicle| vehicle.plate == testing_plate),
             "{:?}",
-            updated_chat.subscribed_vehicles
+            updated_vehicles
         );
 
         // Verify that the vehicle no longer includes the chat in its subscribers
-        let updated_vehicle = db_controller.get_vehicle(testing_plate).await.unwrap();
+        let updated_subscribers = db_controller
+            .get_subscriptions_by_plate(testing_plate)
+            .await
+            .unwrap();
         assert!(
-            !updated_vehicle
-                .subscribers_ids
-                .clone()
-                .unwrap_or_default()
-                .split(',')
-                .any(|id| id == testing_chat.to_string()),
+            !updated_subscribers
+                .iter()
+                .any(|subscriber| subscriber.chat_id == testing_chat),
             "{:?}",
-            updated_vehicle.subscribers_ids
+            updated_subscribers
         );
 
+        // Step 4: Ending it again should fail
+        let result = db_controller
+            .end_subscription(testing_plate, testing_chat)
+            .await;
+        assert!(matches!(
+            result,
+            Err(BotDbError::SubscriptionError(3, _, _))
+        ));
+
         // Clean up the test database
         db_controller.cleanup_test_db().await.unwrap();
     }
@@ -927,12 +1536,10 @@ pub mod db_tests {
         let expected_subscriptions = vec![
             Vehicle::builder()
                 .plate("ABC123".to_string())
-                .maybe_subscribers_ids(Some(String::from("1,")))
                 .maybe_found_at(None)
                 .build(),
             Vehicle::builder()
                 .plate("DEF456".to_string())
-                .maybe_subscribers_ids(Some(String::from("1,2,")))
                 .maybe_found_at(None)
                 .build(),
         ];
@@ -981,6 +1588,44 @@ pub mod db_tests {
         db_controller.cleanup_test_db().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_modify_found_vehicle() {
+        let db_controller = Repo::new_for_test("test_modify_found_vehicle")
+            .await
+            .unwrap();
+
+        let test_datetime = random_datetime();
+        let details = FoundDetails::builder()
+            .location("Campa de Ribarroja".to_string())
+            .registered_at(chrono::NaiveDate::from_ymd_opt(2024, 11, 4).unwrap())
+            .reference("VLC-0042".to_string())
+            .build();
+
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+        let ids = db_controller
+            .modify_found_vehicle("ABC123", test_datetime, &details)
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+        assert_eq!(
+            db_controller
+                .get_notification(ids[0])
+                .await
+                .unwrap()
+                .chat_id,
+            1
+        );
+
+        let vehicle = db_controller.get_vehicle("ABC123").await.unwrap();
+        assert_eq!(vehicle.found_at, Some(test_datetime));
+        assert_eq!(vehicle.details, details);
+        assert!(vehicle
+            .found_at_to_text(&Locale::default())
+            .contains("Campa de Ribarroja"));
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
     /// Test for modifying the active state of a chat
     #[tokio::test]
     async fn test_modify_active_chat() {
@@ -1022,6 +1667,236 @@ pub mod db_tests {
         db_controller.cleanup_test_db().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_create_subscriptions() {
+        let db_controller = Repo::new_for_test("test_create_subscriptions")
+            .await
+            .unwrap();
+
+        let (chat, _) = db_controller
+            .find_or_create_chat(&994, 7777777, "flota", &None, ChatKind::Private)
+            .await
+            .unwrap();
+        db_controller
+            .find_or_create_vehicle("9994ZZZ")
+            .await
+            .unwrap();
+        db_controller
+            .create_subscription("9994ZZZ", chat.id)
+            .await
+            .unwrap();
+        let found_at = Utc::now();
+        db_controller
+            .find_or_create_vehicle("9995ZZZ")
+            .await
+            .unwrap();
+        db_controller
+            .modify_found_at_vehicle("9995ZZZ", found_at)
+            .await
+            .unwrap();
+
+        let plates = ["9993ZZZ", "9994ZZZ", "9995ZZZ"].map(String::from);
+        let outcomes = db_controller
+            .create_subscriptions(&plates, chat.id)
+            .await
+            .unwrap();
+
+        assert_eq!(outcomes[0], SubscribeOutcome::Added);
+        assert_eq!(outcomes[1], SubscribeOutcome::AlreadyFollowed);
+        assert!(matches!(outcomes[2], SubscribeOutcome::AlreadyFound(_)));
+
+        let vehicles = db_controller
+            .get_vehicles_by_chat_id(&chat.id)
+            .await
+            .unwrap();
+        let mut followed: Vec<_> = vehicles
+            .iter()
+            .map(|vehicle| vehicle.plate.as_str())
+            .collect();
+        followed.sort();
+        assert_eq!(followed, vec!["9993ZZZ", "9994ZZZ"]);
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_chat_and_vehicle() {
+        let db_controller = Repo::new_for_test("test_find_chat_and_vehicle")
+            .await
+            .unwrap();
+
+        assert!(db_controller.find_chat(&997).await.unwrap().is_none());
+
+        db_controller
+            .find_or_create_chat(&997, 3333333, "hola", &None, ChatKind::Private)
+            .await
+            .unwrap();
+        let chat = db_controller.find_chat(&997).await.unwrap().unwrap();
+        assert_eq!(chat.user_id, 3333333);
+
+        assert!(db_controller
+            .find_vehicle("9999ZZZ")
+            .await
+            .unwrap()
+            .is_none());
+        db_controller
+            .find_or_create_vehicle("9999ZZZ")
+            .await
+            .unwrap();
+        let vehicle = db_controller
+            .find_vehicle("9999ZZZ")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vehicle.found_at, None);
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_share_subscription() {
+        let db_controller = Repo::new_for_test("test_share_subscription").await.unwrap();
+
+        let (chat, _) = db_controller
+            .find_or_create_chat(&995, 6666666, "hola", &None, ChatKind::Private)
+            .await
+            .unwrap();
+        db_controller
+            .find_or_create_vehicle("9997ZZZ")
+            .await
+            .unwrap();
+
+        // Only followed plates can be shared
+        assert!(db_controller
+            .share_subscription(chat.id, "9997ZZZ")
+            .await
+            .is_err());
+
+        db_controller
+            .create_subscription("9997ZZZ", chat.id)
+            .await
+            .unwrap();
+        let shared = db_controller
+            .share_subscription(chat.id, "9997ZZZ")
+            .await
+            .unwrap();
+        assert_eq!(shared.token.len(), 20);
+        assert_eq!(
+            db_controller
+                .share_subscription(chat.id, "9997ZZZ")
+                .await
+                .unwrap(),
+            shared
+        );
+        assert_eq!(
+            db_controller
+                .get_shared_tracking(&shared.token)
+                .await
+                .unwrap(),
+            Some(shared.clone())
+        );
+
+        // Gone along with the subscription
+        db_controller
+            .end_subscription("9997ZZZ", chat.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            db_controller
+                .get_shared_tracking(&shared.token)
+                .await
+                .unwrap(),
+            None
+        );
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_migrate_chat() {
+        let db_controller = Repo::new_for_test("test_migrate_chat").await.unwrap();
+
+        let (group, _) = db_controller
+            .find_or_create_chat(&-996, 4444444, "Familia", &None, ChatKind::Group)
+            .await
+            .unwrap();
+        assert!(group.is_group());
+        db_controller
+            .find_or_create_vehicle("9998ZZZ")
+            .await
+            .unwrap();
+        db_controller
+            .create_subscription("9998ZZZ", group.id)
+            .await
+            .unwrap();
+
+        // The supergroup may have been seen before the migration
+        db_controller
+            .find_or_create_chat(&-100996, 5555555, "Familia", &None, ChatKind::Group)
+            .await
+            .unwrap();
+
+        assert!(db_controller.migrate_chat(&-996, &-100996).await.unwrap());
+        assert!(db_controller.find_chat(&-996).await.unwrap().is_none());
+
+        let supergroup = db_controller.get_chat(&-100996).await.unwrap();
+        assert_eq!(supergroup.user_id, 4444444);
+        let vehicles = db_controller
+            .get_vehicles_by_chat_id(&-100996)
+            .await
+            .unwrap();
+        assert_eq!(vehicles.len(), 1);
+
+        // Both groups send the migration, the second time there is nothing to move
+        assert!(!db_controller.migrate_chat(&-996, &-100996).await.unwrap());
+        assert!(db_controller.find_chat(&-100996).await.unwrap().is_some());
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_modify_locale_chat() {
+        let db_controller = Repo::new_for_test("test_modify_locale_chat").await.unwrap();
+
+        // Telegram's language is the default
+        let (chat, _) = db_controller
+            .find_or_create_chat(
+                &998,
+                2222222,
+                "hola",
+                &Some("ca-ES".to_string()),
+                ChatKind::Private,
+            )
+            .await
+            .unwrap();
+        assert_eq!(chat.lang(), Lang::Ca);
+
+        let n = db_controller
+            .modify_language_chat(&998, Lang::En)
+            .await
+            .unwrap();
+        assert_eq!(n, 1);
+
+        let chat = db_controller.get_chat(&998).await.unwrap();
+        assert_eq!(chat.lang(), Lang::En);
+
+        // Europe/Madrid unless DEFAULT_TIMEZONE says otherwise
+        assert_eq!(chat.timezone, None);
+        assert_eq!(chat.tz(), *crate::DEFAULT_TIMEZONE);
+
+        db_controller
+            .modify_timezone_chat(&998, chrono_tz::Atlantic::Canary)
+            .await
+            .unwrap();
+        let chat = db_controller.get_chat(&998).await.unwrap();
+        assert_eq!(
+            chat.locale(),
+            Locale::new(Lang::En, chrono_tz::Atlantic::Canary)
+        );
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_get_active_subscriptions_from_vehicle() {
         let db_controller = Repo::new_for_test("test_get_active_subscriptions_from_vehicle")
@@ -1032,17 +1907,24 @@ pub mod db_tests {
 
         // Setup a test vehicle with specific subscribers, some active and some inactive
         let vehicle_plate = "XYZ987";
-        let subscribers_ids = "1,2,3,4,"; // Assuming IDs 1, 2, 3, 4 are in the chats table
+        let subscribers_ids = [1_i64, 2, 3]; // IDs 1, 2, 3 are in the chats table
 
         // Insert the vehicle with subscribers
         connection
             .execute(
-                "INSERT INTO vehicles (plate, subscribers_ids) VALUES ($1, $2)",
-                &[&vehicle_plate, &subscribers_ids],
+                "INSERT INTO vehicles (plate) VALUES ($1)",
+                &[&vehicle_plate],
             )
             .await
             .unwrap();
 
+        for chat_id in subscribers_ids {
+            db_controller
+                .create_subscription(vehicle_plate, chat_id)
+                .await
+                .unwrap();
+        }
+
         // Set chat active states
         db_controller
             .modify_active_chat(&1_i64, true)
@@ -1072,6 +1954,12 @@ pub mod db_tests {
 
         // Assert the result matches the expected active chat IDs
         assert_eq!(active_chat_ids, vec![1, 3]);
+
+        let n = db_controller
+            .get_n_subscribers_by_plate(vehicle_plate)
+            .await
+            .unwrap();
+        assert_eq!(n, 2);
         db_controller.cleanup_test_db().await.unwrap();
     }
 
@@ -1079,16 +1967,11 @@ pub mod db_tests {
     async fn test_insert_vehicle() {
         let db_controller = Repo::new_for_test("test_insert_vehicle").await.unwrap();
 
-        let test_vehicle = Vehicle {
-            plate: "TEST123".to_string(),
-            subscribers_ids: Some("123,".to_string()),
-            found_at: None,
-        };
+        let test_vehicle = Vehicle::builder().plate("TEST123".to_string()).build();
 
         match db_controller.insert_vehicle(test_vehicle.clone()).await {
             Ok(vehicle) => {
                 assert_eq!(vehicle.plate, test_vehicle.plate);
-                assert_eq!(vehicle.subscribers_ids, test_vehicle.subscribers_ids);
                 assert!(vehicle.found_at.is_none());
             }
             Err(e) => panic!("Failed to insert vehicle: {:?}", e),
@@ -1112,4 +1995,49 @@ pub mod db_tests {
         }
         db_controller.cleanup_test_db().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_claim_update() {
+        let db_controller = Repo::new_for_test("test_claim_update").await.unwrap();
+
+        assert!(db_controller.claim_update(10).await.unwrap());
+        assert!(!db_controller.check_processed_update(10).await.unwrap());
+        assert!(!db_controller.claim_update(10).await.unwrap());
+        assert_eq!(db_controller.modify_processed_update(10).await.unwrap(), 1);
+        assert!(db_controller.check_processed_update(10).await.unwrap());
+        assert!(!db_controller.claim_update(10).await.unwrap());
+
+        // A failed update can be claimed again, a processed one is never released
+        assert!(db_controller.claim_update(11).await.unwrap());
+        assert_eq!(db_controller.release_update(11).await.unwrap(), 1);
+        assert_eq!(db_controller.release_update(10).await.unwrap(), 0);
+        assert!(db_controller.claim_update(11).await.unwrap());
+
+        // The claim of a worker that died expires
+        let connection = db_controller.get_connection().get().await.unwrap();
+        connection
+            .execute(
+                "UPDATE processed_updates SET claimed_at = NOW() - INTERVAL '1 hour' WHERE update_id = 11",
+                &[],
+            )
+            .await
+            .unwrap();
+        assert!(db_controller.claim_update(11).await.unwrap());
+
+        // Only the expired ones are forgotten
+        let n = db_controller
+            .delete_processed_updates(Utc::now() - Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(n, 0);
+
+        let n = db_controller
+            .delete_processed_updates(Utc::now() + Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(n, 2);
+        assert!(db_controller.claim_update(10).await.unwrap());
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
 }
@@ -0,0 +1,262 @@
+use std::{collections::BTreeMap, future::Future, time::Duration};
+
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    db::Repo,
+    runtime::{webhook_url, RuntimeMode},
+    telegram::client::ApiClient,
+    FETCH_IN_MINUTES, RUNTIME_MODE,
+};
+
+/// Max time a dependency has to answer
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Error,
+}
+
+/// Result of checking one dependency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentReport {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl ComponentReport {
+    fn ok(details: Value) -> Self {
+        ComponentReport {
+            status: Status::Ok,
+            error: None,
+            details,
+        }
+    }
+
+    fn error(error: impl ToString, details: Value) -> Self {
+        ComponentReport {
+            status: Status::Error,
+            error: Some(error.to_string()),
+            details,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Readiness {
+    pub status: Status,
+    pub components: BTreeMap<&'static str, ComponentReport>,
+}
+
+impl Readiness {
+    pub fn new(components: BTreeMap<&'static str, ComponentReport>) -> Self {
+        let status = if components
+            .values()
+            .all(|component| component.status == Status::Ok)
+        {
+            Status::Ok
+        } else {
+            Status::Error
+        };
+
+        Readiness { status, components }
+    }
+}
+
+impl IntoResponse for Readiness {
+    fn into_response(self) -> axum::response::Response {
+        let code = match self.status {
+            Status::Ok => StatusCode::OK,
+            Status::Error => StatusCode::SERVICE_UNAVAILABLE,
+        };
+
+        (code, Json(self)).into_response()
+    }
+}
+
+/// Liveness, answers as long as the server is running
+pub async fn healthz() -> Json<Value> {
+    Json(json!({ "status": Status::Ok }))
+}
+
+/// Readiness, checks every dependency the bot needs
+pub async fn readyz() -> Readiness {
+    let mut components = BTreeMap::new();
+
+    match Repo::repo().await {
+        Ok(repo) => {
+            components.insert("database", check_database(repo).await);
+            components.insert("queue", check_queue(repo).await);
+        }
+        Err(err) => {
+            components.insert("database", ComponentReport::error(&err, Value::Null));
+            components.insert("queue", ComponentReport::error(&err, Value::Null));
+        }
+    }
+
+    let telegram = ApiClient::api_client().await;
+    let expected_url = expected_webhook_url(*RUNTIME_MODE);
+    components.insert("telegram", check_webhook(telegram, &expected_url).await);
+
+    Readiness::new(components)
+}
+
+/// Webhook Telegram should have for the mode, polling mode removes it on start
+pub fn expected_webhook_url(mode: RuntimeMode) -> String {
+    match mode {
+        RuntimeMode::Webhook => webhook_url(),
+        RuntimeMode::Polling => String::new(),
+    }
+}
+
+async fn with_timeout<T, E: ToString>(
+    check: impl Future<Output = Result<T, E>>,
+) -> Result<T, String> {
+    match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result.map_err(|err| err.to_string()),
+        Err(_) => Err(format!("No answer after {CHECK_TIMEOUT:?}")),
+    }
+}
+
+pub async fn check_database(repo: &Repo) -> ComponentReport {
+    let start = std::time::Instant::now();
+
+    match with_timeout(repo.ping()).await {
+        Ok(()) => ComponentReport::ok(json!({ "latency_ms": start.elapsed().as_millis() })),
+        Err(err) => ComponentReport::error(err, Value::Null),
+    }
+}
+
+/// The workers are stuck or dead when tasks stay waiting long after their time
+pub async fn check_queue(repo: &Repo) -> ComponentReport {
+    let stalled_after = chrono::Duration::minutes(i64::from(*FETCH_IN_MINUTES).max(1) * 2);
+
+    match with_timeout(repo.count_overdue_tasks(Utc::now() - stalled_after)).await {
+        Ok(0) => ComponentReport::ok(json!({ "overdue_tasks": 0 })),
+        Ok(n) => ComponentReport::error(
+            "Workers are not picking up tasks",
+            json!({ "overdue_tasks": n }),
+        ),
+        Err(err) => ComponentReport::error(err, Value::Null),
+    }
+}
+
+/// Checks the webhook registered in Telegram is `expected_url`, empty when there shouldn't be one
+pub async fn check_webhook(telegram: &ApiClient, expected_url: &str) -> ComponentReport {
+    let info = match with_timeout(telegram.get_webhook_info()).await {
+        Ok(response) => response.result,
+        Err(err) => return ComponentReport::error(err, Value::Null),
+    };
+
+    let details = json!({
+        "url": info.url,
+        "pending_update_count": info.pending_update_count,
+        "last_error_date": info.last_error_date,
+        "last_error_message": info.last_error_message,
+    });
+
+    if info.url == expected_url {
+        ComponentReport::ok(details)
+    } else {
+        ComponentReport::error(format!("Webhook should be '{expected_url}'"), details)
+    }
+}
+
+#[cfg(test)]
+mod health_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_database_and_queue() {
+        let db_controller = Repo::new_for_test("test_check_database_and_queue")
+            .await
+            .unwrap();
+
+        let report = check_database(&db_controller).await;
+        assert_eq!(report.status, Status::Ok);
+        assert!(report.details["latency_ms"].is_number());
+
+        let report = check_queue(&db_controller).await;
+        assert_eq!(report.status, Status::Ok, "{report:?}");
+
+        let connection = db_controller.get_connection().get().await.unwrap();
+        connection
+            .execute(
+                "INSERT INTO fang_tasks (metadata, task_type, scheduled_at) VALUES ('{}', 'scheduled_fetch', NOW() - INTERVAL '1 day')",
+                &[],
+            )
+            .await
+            .unwrap();
+
+        let report = check_queue(&db_controller).await;
+        assert_eq!(report.status, Status::Error);
+        assert_eq!(report.details["overdue_tasks"], 1);
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_webhook() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _info = server
+            .mock("POST", "/getWebhookInfo")
+            .with_status(200)
+            .with_body(
+                r#"{"ok": true, "result": {"url": "https://bot.example/webhook", "has_custom_certificate": false, "pending_update_count": 3}}"#,
+            )
+            .create_async()
+            .await;
+
+        let telegram = ApiClient::new_url(server.url()).await;
+
+        let report = check_webhook(&telegram, "https://bot.example/webhook").await;
+        assert_eq!(report.status, Status::Ok);
+        assert_eq!(report.details["pending_update_count"], 3);
+
+        let report = check_webhook(&telegram, &expected_webhook_url(RuntimeMode::Polling)).await;
+        assert_eq!(report.status, Status::Error);
+
+        let components = BTreeMap::from([
+            ("telegram", report),
+            ("database", ComponentReport::ok(Value::Null)),
+        ]);
+        let readiness = Readiness::new(components);
+        assert_eq!(readiness.status, Status::Error);
+
+        let body = serde_json::to_value(&readiness).unwrap();
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["components"]["database"], json!({ "status": "ok" }));
+        assert_eq!(
+            body["components"]["telegram"]["details"]["url"],
+            "https://bot.example/webhook"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_webhook_polling() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _info = server
+            .mock("POST", "/getWebhookInfo")
+            .with_status(200)
+            .with_body(
+                r#"{"ok": true, "result": {"url": "", "has_custom_certificate": false, "pending_update_count": 0}}"#,
+            )
+            .create_async()
+            .await;
+
+        let telegram = ApiClient::new_url(server.url()).await;
+
+        let report = check_webhook(&telegram, &expected_webhook_url(RuntimeMode::Polling)).await;
+        assert_eq!(report.status, Status::Ok, "{report:?}");
+    }
+}
@@ -0,0 +1,993 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+use crate::DEFAULT_TIMEZONE;
+
+/// Languages the bot speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Lang {
+    #[default]
+    Es,
+    /// Valencian/Catalan
+    Ca,
+    En,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 3] = [Lang::Es, Lang::Ca, Lang::En];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lang::Es => "es",
+            Lang::Ca => "ca",
+            Lang::En => "en",
+        }
+    }
+
+    /// Name of the language in the language itself
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lang::Es => "Español",
+            Lang::Ca => "Valencià/Català",
+            Lang::En => "English",
+        }
+    }
+
+    /// Language for an IETF tag such as Telegram's `language_code`.
+    ///
+    /// Spanish when unknown, English for any other language
+    pub fn from_code(code: Option<&str>) -> Lang {
+        let Some(code) = code.map(str::trim).filter(|code| !code.is_empty()) else {
+            return Lang::default();
+        };
+
+        let primary = code
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match primary.as_str() {
+            "es" => Lang::Es,
+            "ca" | "va" => Lang::Ca,
+            _ => Lang::En,
+        }
+    }
+
+    fn weekday(&self, num_days_from_sunday: u32) -> &'static str {
+        let days = match self {
+            Lang::Es => [
+                "domingo",
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+            ],
+            Lang::Ca => [
+                "diumenge",
+                "dilluns",
+                "dimarts",
+                "dimecres",
+                "dijous",
+                "divendres",
+                "dissabte",
+            ],
+            Lang::En => [
+                "Sunday",
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+            ],
+        };
+        days[num_days_from_sunday as usize]
+    }
+
+    fn month(&self, month: u32) -> &'static str {
+        let months = match self {
+            Lang::Es => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+            Lang::Ca => [
+                "gener", "febrer", "març", "abril", "maig", "juny", "juliol", "agost", "setembre",
+                "octubre", "novembre", "desembre",
+            ],
+            Lang::En => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+        };
+        months[(month - 1) as usize]
+    }
+
+    /// `4 de noviembre de 2024`, `4 d'octubre de 2024` or `4 November 2024`
+    pub fn format_date(&self, date: &NaiveDate) -> String {
+        let month = self.month(date.month());
+        match self {
+            Lang::Es => format!("{} de {} de {}", date.day(), month, date.year()),
+            // "de" is elided before a vowel
+            Lang::Ca if month.starts_with(['a', 'o']) => {
+                format!("{} d'{} de {}", date.day(), month, date.year())
+            }
+            Lang::Ca => format!("{} de {} de {}", date.day(), month, date.year()),
+            Lang::En => format!("{} {} {}", date.day(), month, date.year()),
+        }
+    }
+
+    /// Weekday, date and time, e.g. `lunes, 4 de noviembre de 2024, 10:05`
+    pub fn format_datetime<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> String {
+        format!(
+            "{}, {}, {:02}:{:02}",
+            self.weekday(time.weekday().num_days_from_sunday()),
+            self.format_date(&time.date_naive()),
+            time.hour(),
+            time.minute()
+        )
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lang::ALL
+            .into_iter()
+            .find(|lang| lang.code() == s.trim())
+            .ok_or_else(|| format!("Unknown language '{s}'"))
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Language and timezone the texts of a chat are rendered with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub lang: Lang,
+    pub tz: Tz,
+}
+
+impl Locale {
+    pub fn new(lang: Lang, tz: Tz) -> Self {
+        Locale { lang, tz }
+    }
+
+    /// Local time of the chat, every time shown to the users goes through here
+    pub fn format_datetime(&self, time: &DateTime<Utc>) -> String {
+        self.lang.format_datetime(&time.with_timezone(&self.tz))
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::new(Lang::default(), *DEFAULT_TIMEZONE)
+    }
+}
+
+/// Id of every text shown to the users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Msg {
+    Welcome,
+    Greeting,
+    SelectCommand,
+    Back,
+    UnknownCommand,
+    Cancelled,
+    StartAddVehicle,
+    StartMyVehicles,
+    StartEnableAlerts,
+    StartDisableAlerts,
+    StartHelp,
+    StartChannels,
+    StartLanguage,
+    AddVehiclePrompt,
+    PlateEmpty,
+    PlateInvalidCharacters,
+    PlateUnknownProvince,
+    PlateUnknownFormat,
+    LookupUnavailable,
+    VehicleAddedWithAlerts,
+    VehicleAdded,
+    VehicleAlreadyAdded,
+    VehicleAddedByOther,
+    VehicleRemoved,
+    VehicleRemovedLast,
+    VehicleNotAdded,
+    VehiclesMenu,
+    VehicleInfo,
+    AlertsAlreadyEnabled,
+    AlertsNeedVehicles,
+    AlertsEnabled,
+    AlertsAlreadyDisabled,
+    AlertsDisabled,
+    ChannelsMenu,
+    SetEmailPrompt,
+    EmailUnavailable,
+    InvalidEmail,
+    EmailAdded,
+    ChannelNeeded,
+    ChannelEnabled,
+    ChannelDisabled,
+    EnableTelegram,
+    DisableTelegram,
+    EnableEmail,
+    ChangeEmail,
+    DisableEmail,
+    LanguageMenu,
+    LanguageChanged,
+    StartTimezone,
+    TimezoneMenu,
+    TimezoneChanged,
+    InlineHint,
+    InlineFound,
+    InlineNotFound,
+    NotTracked,
+    InlineLastChecked,
+    FollowPlate,
+    AdminOnly,
+    InvalidLink,
+    ShareVehicle,
+    ShareLink,
+    UpdateFailed,
+    ButtonOutdated,
+    ButtonInvalid,
+    MissingArgument,
+    InvalidArgument,
+    BulkSummary,
+    BulkAdded,
+    BulkAlreadyFollowed,
+    BulkAlreadyFound,
+    BulkInvalid,
+    BulkWithAlerts,
+    BulkTooMany,
+    InvalidPlatesDocument,
+    HelpIntro,
+    HelpCommands,
+    HelpCredits,
+    CmdStart,
+    CmdAddVehicleMessage,
+    CmdAddVehicle,
+    CmdGetMyVehicles,
+    CmdCheck,
+    CmdDeleteVehicle,
+    CmdStartFetch,
+    CmdStopFetch,
+    CmdChannels,
+    CmdLanguage,
+    CmdTimezone,
+    CmdCancel,
+    CmdHelp,
+    NotFoundYet,
+    FoundAt,
+    Location,
+    RegisteredAt,
+    Reference,
+    Contact,
+    EmailSubject,
+    EmailFooter,
+}
+
+impl Msg {
+    pub const ALL: [Msg; 98] = [
+        Msg::Welcome,
+        Msg::Greeting,
+        Msg::SelectCommand,
+        Msg::Back,
+        Msg::UnknownCommand,
+        Msg::Cancelled,
+        Msg::StartAddVehicle,
+        Msg::StartMyVehicles,
+        Msg::StartEnableAlerts,
+        Msg::StartDisableAlerts,
+        Msg::StartHelp,
+        Msg::StartChannels,
+        Msg::StartLanguage,
+        Msg::AddVehiclePrompt,
+        Msg::PlateEmpty,
+        Msg::PlateInvalidCharacters,
+        Msg::PlateUnknownProvince,
+        Msg::PlateUnknownFormat,
+        Msg::LookupUnavailable,
+        Msg::VehicleAddedWithAlerts,
+        Msg::VehicleAdded,
+        Msg::VehicleAlreadyAdded,
+        Msg::VehicleAddedByOther,
+        Msg::VehicleRemoved,
+        Msg::VehicleRemovedLast,
+        Msg::VehicleNotAdded,
+        Msg::VehiclesMenu,
+        Msg::VehicleInfo,
+        Msg::AlertsAlreadyEnabled,
+        Msg::AlertsNeedVehicles,
+        Msg::AlertsEnabled,
+        Msg::AlertsAlreadyDisabled,
+        Msg::AlertsDisabled,
+        Msg::ChannelsMenu,
+        Msg::SetEmailPrompt,
+        Msg::EmailUnavailable,
+        Msg::InvalidEmail,
+        Msg::EmailAdded,
+        Msg::ChannelNeeded,
+        Msg::ChannelEnabled,
+        Msg::ChannelDisabled,
+        Msg::EnableTelegram,
+        Msg::DisableTelegram,
+        Msg::EnableEmail,
+        Msg::ChangeEmail,
+        Msg::DisableEmail,
+        Msg::LanguageMenu,
+        Msg::LanguageChanged,
+        Msg::StartTimezone,
+        Msg::TimezoneMenu,
+        Msg::TimezoneChanged,
+        Msg::InlineHint,
+        Msg::InlineFound,
+        Msg::InlineNotFound,
+        Msg::NotTracked,
+        Msg::InlineLastChecked,
+        Msg::FollowPlate,
+        Msg::AdminOnly,
+        Msg::InvalidLink,
+        Msg::ShareVehicle,
+        Msg::ShareLink,
+        Msg::UpdateFailed,
+        Msg::ButtonOutdated,
+        Msg::ButtonInvalid,
+        Msg::MissingArgument,
+        Msg::InvalidArgument,
+        Msg::BulkSummary,
+        Msg::BulkAdded,
+        Msg::BulkAlreadyFollowed,
+        Msg::BulkAlreadyFound,
+        Msg::BulkInvalid,
+        Msg::BulkWithAlerts,
+        Msg::BulkTooMany,
+        Msg::InvalidPlatesDocument,
+        Msg::HelpIntro,
+        Msg::HelpCommands,
+        Msg::HelpCredits,
+        Msg::CmdStart,
+        Msg::CmdAddVehicleMessage,
+        Msg::CmdAddVehicle,
+        Msg::CmdGetMyVehicles,
+        Msg::CmdCheck,
+        Msg::CmdDeleteVehicle,
+        Msg::CmdStartFetch,
+        Msg::CmdStopFetch,
+        Msg::CmdChannels,
+        Msg::CmdLanguage,
+        Msg::CmdTimezone,
+        Msg::CmdCancel,
+        Msg::CmdHelp,
+        Msg::NotFoundYet,
+        Msg::FoundAt,
+        Msg::Location,
+        Msg::RegisteredAt,
+        Msg::Reference,
+        Msg::Contact,
+        Msg::EmailSubject,
+        Msg::EmailFooter,
+    ];
+
+    /// Spanish, Valencian/Catalan and English texts, `{name}` marks a placeholder
+    fn catalog(&self) -> [&'static str; 3] {
+        match self {
+            Msg::Welcome => [
+                "¡Bienvenido! Este bot se encuentra en desarrollo.\nEste proyecto no está afiliado con **tucochedana.es**",
+                "Benvingut! Aquest bot està en desenvolupament.\nAquest projecte no està afiliat amb **tucochedana.es**",
+                "Welcome! This bot is under development.\nThis project is not affiliated with **tucochedana.es**",
+            ],
+            Msg::Greeting => [
+                "Hola, {username}!\n{text}",
+                "Hola, {username}!\n{text}",
+                "Hi, {username}!\n{text}",
+            ],
+            Msg::SelectCommand => [
+                "Seleccione un comando",
+                "Seleccione una ordre",
+                "Select a command",
+            ],
+            Msg::Back => ["⬅️ Volver", "⬅️ Tornar", "⬅️ Back"],
+            Msg::UnknownCommand => [
+                "Comando '{command}' desconocido. Ejecuta /start para ver los comandos disponibles",
+                "Ordre '{command}' desconeguda. Executa /start per a veure les ordres disponibles",
+                "Unknown command '{command}'. Run /start to see the available commands",
+            ],
+            Msg::Cancelled => [
+                "Operación cancelada",
+                "Operació cancel·lada",
+                "Operation cancelled",
+            ],
+            Msg::StartAddVehicle => ["Añadir un vehículo", "Afegir un vehicle", "Add a vehicle"],
+            Msg::StartMyVehicles => ["Mis vehículos", "Els meus vehicles", "My vehicles"],
+            Msg::StartEnableAlerts => [
+                "🚨 Activar alertas",
+                "🚨 Activar alertes",
+                "🚨 Enable alerts",
+            ],
+            Msg::StartDisableAlerts => [
+                "🔕 Desactivar alertas",
+                "🔕 Desactivar alertes",
+                "🔕 Disable alerts",
+            ],
+            Msg::StartHelp => ["Ayuda", "Ajuda", "Help"],
+            Msg::StartChannels => [
+                "📬 Canales de aviso",
+                "📬 Canals d'avís",
+                "📬 Notification channels",
+            ],
+            Msg::StartLanguage => ["🌐 Idioma", "🌐 Idioma", "🌐 Language"],
+            Msg::AddVehiclePrompt => [
+                "Escribe la matrícula del vehículo del que deseas recibir alertas o /cancel para cancelar. Puedes añadir varias separadas por comas o enviarlas en un fichero .txt o .csv",
+                "Escriu la matrícula del vehicle del qual vols rebre alertes o /cancel per a cancel·lar. Pots afegir-ne diverses separades per comes o enviar-les en un fitxer .txt o .csv",
+                "Write the plate of the vehicle you want to be alerted about or /cancel to cancel. You can add several separated by commas or send them in a .txt or .csv file",
+            ],
+            Msg::PlateEmpty => [
+                "No ha escrito ninguna matrícula, pruebe de nuevo",
+                "No ha escrit cap matrícula, torne-ho a provar",
+                "You didn't write any plate, try again",
+            ],
+            Msg::PlateInvalidCharacters => [
+                "La matrícula '{plate}' contiene caracteres no válidos, utilice solo letras y números",
+                "La matrícula '{plate}' conté caràcters no vàlids, utilitze només lletres i números",
+                "The plate '{plate}' contains invalid characters, use only letters and numbers",
+            ],
+            Msg::PlateUnknownProvince => [
+                "La matrícula {plate} no es válida: '{province}' no es un código de provincia",
+                "La matrícula {plate} no és vàlida: '{province}' no és un codi de província",
+                "The plate {plate} is not valid: '{province}' is not a province code",
+            ],
+            Msg::PlateUnknownFormat => [
+                "La matrícula {plate} no sigue ningún formato conocido (p. ej. 1234BCD o V-1234-AB), pruebe de nuevo",
+                "La matrícula {plate} no segueix cap format conegut (p. ex. 1234BCD o V-1234-AB), torne-ho a provar",
+                "The plate {plate} doesn't follow any known format (e.g. 1234BCD or V-1234-AB), try again",
+            ],
+            Msg::LookupUnavailable => [
+                "\n⚠️ No hemos podido consultar tucochedana.es en este momento, lo comprobaremos más tarde",
+                "\n⚠️ No hem pogut consultar tucochedana.es en aquest moment, ho comprovarem més tard",
+                "\n⚠️ We couldn't check tucochedana.es right now, we'll try again later",
+            ],
+            Msg::VehicleAddedWithAlerts => [
+                "Vehículo {plate} añadido ✅\ncomo tiene las alertas activas, le avisaremos si se registra{note}",
+                "Vehicle {plate} afegit ✅\ncom té les alertes activades, l'avisarem si es registra{note}",
+                "Vehicle {plate} added ✅\nsince your alerts are enabled, we'll let you know when it's registered{note}",
+            ],
+            Msg::VehicleAdded => [
+                "Vehículo {plate} añadido ✅{note}",
+                "Vehicle {plate} afegit ✅{note}",
+                "Vehicle {plate} added ✅{note}",
+            ],
+            Msg::VehicleAlreadyAdded => [
+                "El vehículo {plate} ya ha sido añadido previamente 👀",
+                "El vehicle {plate} ja s'havia afegit 👀",
+                "Vehicle {plate} had already been added 👀",
+            ],
+            Msg::VehicleAddedByOther => [
+                "El vehículo {plate} ya ha sido registrado por otro usuario, le añadiremos como interesado{note}",
+                "El vehicle {plate} ja l'ha registrat un altre usuari, l'afegirem com a interessat{note}",
+                "Vehicle {plate} was already registered by another user, we'll add you as interested{note}",
+            ],
+            Msg::VehicleRemoved => [
+                "El vehículo {plate} ha sido eliminado correctamente ✅",
+                "El vehicle {plate} s'ha eliminat correctament ✅",
+                "Vehicle {plate} removed ✅",
+            ],
+            Msg::VehicleRemovedLast => [
+                "El vehículo {plate} ha sido eliminado correctamente ✅\nHemos desactivado las alertas pues no tiene ningún otro coche añadido",
+                "El vehicle {plate} s'ha eliminat correctament ✅\nHem desactivat les alertes perquè no té cap altre cotxe afegit",
+                "Vehicle {plate} removed ✅\nWe have disabled the alerts since you don't have any other vehicle",
+            ],
+            Msg::VehicleNotAdded => [
+                "No tiene añadido el vehículo {plate}",
+                "No té afegit el vehicle {plate}",
+                "You haven't added the vehicle {plate}",
+            ],
+            Msg::VehiclesMenu => ["Vehículos añadidos", "Vehicles afegits", "Added vehicles"],
+            Msg::VehicleInfo => [
+                "Información más reciente sobre el vehículo",
+                "Informació més recent sobre el vehicle",
+                "Latest information about the vehicle",
+            ],
+            Msg::AlertsAlreadyEnabled => [
+                "Las alertas ya han sido activadas",
+                "Les alertes ja estan activades",
+                "Alerts are already enabled",
+            ],
+            Msg::AlertsNeedVehicles => [
+                "Debe añadir vehículos para activar las alertas",
+                "Ha d'afegir vehicles per a activar les alertes",
+                "You have to add vehicles to enable the alerts",
+            ],
+            Msg::AlertsEnabled => [
+                "Alerta activada correctamente ✅\nle avisaremos si se registra alguno de sus vehículos",
+                "Alerta activada correctament ✅\nl'avisarem si es registra algun dels seus vehicles",
+                "Alerts enabled ✅\nwe'll let you know when any of your vehicles is registered",
+            ],
+            Msg::AlertsAlreadyDisabled => [
+                "Las alertas ya han sido desactivadas",
+                "Les alertes ja estan desactivades",
+                "Alerts are already disabled",
+            ],
+            Msg::AlertsDisabled => [
+                "Alertas desactivadas correctamente ✅",
+                "Alertes desactivades correctament ✅",
+                "Alerts disabled ✅",
+            ],
+            Msg::ChannelsMenu => [
+                "📬 Canales por los que le avisaremos cuando encontremos sus vehículos",
+                "📬 Canals pels quals l'avisarem quan trobem els seus vehicles",
+                "📬 Channels we'll use to let you know when your vehicles are found",
+            ],
+            Msg::SetEmailPrompt => [
+                "Escribe la dirección de email en la que deseas recibir los avisos o /cancel para cancelar",
+                "Escriu l'adreça de correu en què vols rebre els avisos o /cancel per a cancel·lar",
+                "Write the email address where you want to receive the alerts or /cancel to cancel",
+            ],
+            Msg::EmailUnavailable => [
+                "Los avisos por email no están disponibles",
+                "Els avisos per correu no estan disponibles",
+                "Email alerts are not available",
+            ],
+            Msg::InvalidEmail => [
+                "La dirección '{address}' no es válida, pruebe de nuevo o /cancel para cancelar",
+                "L'adreça '{address}' no és vàlida, torne-ho a provar o /cancel per a cancel·lar",
+                "The address '{address}' is not valid, try again or /cancel to cancel",
+            ],
+            Msg::EmailAdded => [
+                "Le avisaremos también en {address} ✅",
+                "L'avisarem també a {address} ✅",
+                "We'll also let you know at {address} ✅",
+            ],
+            Msg::ChannelNeeded => [
+                "Necesita al menos un canal activo para recibir los avisos",
+                "Necessita almenys un canal actiu per a rebre els avisos",
+                "You need at least one enabled channel to receive the alerts",
+            ],
+            Msg::ChannelEnabled => ["Canal activado ✅", "Canal activat ✅", "Channel enabled ✅"],
+            Msg::ChannelDisabled => [
+                "Canal desactivado 🔕",
+                "Canal desactivat 🔕",
+                "Channel disabled 🔕",
+            ],
+            Msg::EnableTelegram => [
+                "🔔 Activar Telegram",
+                "🔔 Activar Telegram",
+                "🔔 Enable Telegram",
+            ],
+            Msg::DisableTelegram => [
+                "🔕 Desactivar Telegram",
+                "🔕 Desactivar Telegram",
+                "🔕 Disable Telegram",
+            ],
+            Msg::EnableEmail => [
+                "📧 Avisarme por email",
+                "📧 Avisar-me per correu",
+                "📧 Alert me by email",
+            ],
+            Msg::ChangeEmail => ["📧 Cambiar email", "📧 Canviar correu", "📧 Change email"],
+            Msg::DisableEmail => [
+                "🔕 Desactivar email",
+                "🔕 Desactivar correu",
+                "🔕 Disable email",
+            ],
+            Msg::LanguageMenu => [
+                "Elija el idioma del bot",
+                "Trie l'idioma del bot",
+                "Choose the language of the bot",
+            ],
+            Msg::LanguageChanged => [
+                "Idioma cambiado a {language} ✅",
+                "Idioma canviat a {language} ✅",
+                "Language changed to {language} ✅",
+            ],
+            Msg::StartTimezone => ["🕒 Zona horaria", "🕒 Zona horària", "🕒 Time zone"],
+            Msg::TimezoneMenu => [
+                "Elija la zona horaria en la que se muestran las horas\nHora actual: {time} ({timezone})",
+                "Trie la zona horària en què es mostren les hores\nHora actual: {time} ({timezone})",
+                "Choose the time zone used to show the times\nCurrent time: {time} ({timezone})",
+            ],
+            Msg::TimezoneChanged => [
+                "Zona horaria cambiada a {timezone} ✅",
+                "Zona horària canviada a {timezone} ✅",
+                "Time zone changed to {timezone} ✅",
+            ],
+            Msg::InlineHint => [
+                "Escribe una matrícula, por ejemplo 1234BCD",
+                "Escriu una matrícula, per exemple 1234BCD",
+                "Type a plate, for example 1234BCD",
+            ],
+            Msg::InlineFound => ["Encontrado 🙌🏼", "Trobat 🙌🏼", "Found 🙌🏼"],
+            Msg::InlineNotFound => [
+                "Sin encontrar todavía",
+                "Encara sense trobar",
+                "Not found yet",
+            ],
+            Msg::NotTracked => [
+                "Nadie sigue el vehículo {plate} todavía",
+                "Ningú segueix el vehicle {plate} encara",
+                "Nobody is following vehicle {plate} yet",
+            ],
+            Msg::InlineLastChecked => [
+                "Última comprobación: {date}",
+                "Última comprovació: {date}",
+                "Last checked: {date}",
+            ],
+            Msg::FollowPlate => ["🔔 Seguir {plate}", "🔔 Seguir {plate}", "🔔 Follow {plate}"],
+            Msg::AdminOnly => [
+                "Solo los administradores del grupo pueden cambiar sus vehículos y ajustes 🔒",
+                "Només els administradors del grup poden canviar els seus vehicles i ajustos 🔒",
+                "Only the group admins can change its vehicles and settings 🔒",
+            ],
+            Msg::InvalidLink => [
+                "El enlace no es válido o ya no existe 🤔",
+                "L'enllaç no és vàlid o ja no existeix 🤔",
+                "The link is not valid or doesn't exist anymore 🤔",
+            ],
+            Msg::ShareVehicle => ["🔗 Compartir", "🔗 Compartir", "🔗 Share"],
+            Msg::ShareLink => [
+                "Comparte este enlace para que otros sigan el vehículo {plate} contigo:\n{url}",
+                "Comparteix aquest enllaç perquè altres seguisquen el vehicle {plate} amb tu:\n{url}",
+                "Share this link so others can follow vehicle {plate} with you:\n{url}",
+            ],
+            Msg::UpdateFailed => [
+                "Algo ha fallado, inténtalo de nuevo más tarde",
+                "Alguna cosa ha fallat, torna-ho a provar més tard",
+                "Something went wrong, try again later",
+            ],
+            Msg::ButtonOutdated => [
+                "Este botón es de una versión anterior del bot, usa el menú actualizado",
+                "Aquest botó és d'una versió anterior del bot, fes servir el menú actualitzat",
+                "This button is from an older version of the bot, use the updated menu",
+            ],
+            Msg::ButtonInvalid => [
+                "No reconozco este botón, usa el menú actualizado",
+                "No reconec aquest botó, fes servir el menú actualitzat",
+                "I don't recognise this button, use the updated menu",
+            ],
+            Msg::MissingArgument => [
+                "Falta un dato, por ejemplo: {usage}",
+                "Falta una dada, per exemple: {usage}",
+                "Something is missing, for example: {usage}",
+            ],
+            Msg::InvalidArgument => [
+                "'{argument}' no es válido, por ejemplo: {usage}",
+                "'{argument}' no és vàlid, per exemple: {usage}",
+                "'{argument}' isn't valid, for example: {usage}",
+            ],
+            Msg::BulkSummary => [
+                "Matrículas añadidas: {added} de {total}",
+                "Matrícules afegides: {added} de {total}",
+                "Plates added: {added} of {total}",
+            ],
+            Msg::BulkAdded => [
+                "✅ {plate} añadida",
+                "✅ {plate} afegida",
+                "✅ {plate} added",
+            ],
+            Msg::BulkAlreadyFollowed => [
+                "👀 {plate} ya había sido añadida",
+                "👀 {plate} ja s'havia afegit",
+                "👀 {plate} had already been added",
+            ],
+            Msg::BulkAlreadyFound => [
+                "🙌🏼 {plate} ya fue encontrada el {date}",
+                "🙌🏼 {plate} ja es va trobar el {date}",
+                "🙌🏼 {plate} was already found on {date}",
+            ],
+            Msg::BulkInvalid => ["❌ {reason}", "❌ {reason}", "❌ {reason}"],
+            Msg::BulkWithAlerts => [
+                "Como tiene las alertas activas, le avisaremos cuando se registren",
+                "Com té les alertes activades, l'avisarem quan es registren",
+                "Since your alerts are enabled, we'll let you know when they're registered",
+            ],
+            Msg::BulkTooMany => [
+                "Como máximo se pueden añadir {max} matrículas a la vez, envíe el resto en otro mensaje",
+                "Com a màxim es poden afegir {max} matrícules alhora, envie la resta en un altre missatge",
+                "At most {max} plates can be added at once, send the rest in another message",
+            ],
+            Msg::InvalidPlatesDocument => [
+                "Solo se pueden leer listas de matrículas en ficheros .txt o .csv de hasta {size} KB",
+                "Només es poden llegir llistes de matrícules en fitxers .txt o .csv de fins a {size} KB",
+                "Only lists of plates in .txt or .csv files of up to {size} KB can be read",
+            ],
+            Msg::HelpIntro => [
+                "Proyecto no oficial integrado con [Tu Coche Dana](https://tucochedana.es/) para notificar a los dueños de los vehículos perdidos durante las inundaciones de 2024",
+                "Projecte no oficial integrat amb [Tu Coche Dana](https://tucochedana.es/) per a avisar els propietaris dels vehicles perduts durant les inundacions de 2024",
+                "Unofficial project integrated with [Tu Coche Dana](https://tucochedana.es/) to alert the owners of the vehicles lost during the 2024 floods",
+            ],
+            Msg::HelpCommands => [
+                "Comandos",
+                "Ordres",
+                "Commands",
+            ],
+            Msg::HelpCredits => [
+                "Bot original de [@Betisman](https://t.me/tucochedanachecker_bot)",
+                "Bot original de [@Betisman](https://t.me/tucochedanachecker_bot)",
+                "Original bot by [@Betisman](https://t.me/tucochedanachecker_bot)",
+            ],
+            Msg::CmdStart => [
+                "Despliega el menú de opciones y el mensaje de bienvenida",
+                "Mostra el menú d'opcions i el missatge de benvinguda",
+                "Shows the options menu and the welcome message",
+            ],
+            Msg::CmdAddVehicleMessage => [
+                "Registra la matrícula del vehículo que buscas",
+                "Registra la matrícula del vehicle que busques",
+                "Registers the plate of the vehicle you are looking for",
+            ],
+            Msg::CmdAddVehicle => [
+                "Registra la matrícula escrita después del comando, o varias separadas por comas",
+                "Registra la matrícula escrita després de l'ordre, o diverses separades per comes",
+                "Registers the plate written after the command, or several separated by commas",
+            ],
+            Msg::CmdGetMyVehicles => [
+                "Devuelve el listado de vehículos que has registrado",
+                "Torna el llistat de vehicles que has registrat",
+                "Lists the vehicles you have registered",
+            ],
+            Msg::CmdCheck => [
+                "Muestra el estado de una matrícula registrada",
+                "Mostra l'estat d'una matrícula registrada",
+                "Shows the status of a registered plate",
+            ],
+            Msg::CmdDeleteVehicle => [
+                "Elimina una matrícula registrada",
+                "Elimina una matrícula registrada",
+                "Removes a registered plate",
+            ],
+            Msg::CmdStartFetch => [
+                "Activa la búsqueda de los vehículos guardados",
+                "Activa la cerca dels vehicles guardats",
+                "Enables the search of your vehicles",
+            ],
+            Msg::CmdStopFetch => [
+                "Desactiva la búsqueda de los vehículos guardados",
+                "Desactiva la cerca dels vehicles guardats",
+                "Disables the search of your vehicles",
+            ],
+            Msg::CmdChannels => [
+                "Elige si quieres recibir los avisos por Telegram, por email o por ambos",
+                "Tria si vols rebre els avisos per Telegram, per correu o per tots dos",
+                "Choose whether you want the alerts by Telegram, by email or both",
+            ],
+            Msg::CmdLanguage => [
+                "Cambia el idioma del bot",
+                "Canvia l'idioma del bot",
+                "Changes the language of the bot",
+            ],
+            Msg::CmdTimezone => [
+                "Cambia la zona horaria en la que se muestran las horas (Europe/Madrid por defecto)",
+                "Canvia la zona horària en què es mostren les hores (Europe/Madrid per defecte)",
+                "Changes the time zone used to show the times (Europe/Madrid by default)",
+            ],
+            Msg::CmdCancel => [
+                "Cancela la acción en curso y vuelve al menú",
+                "Cancel·la l'acció en curs i torna al menú",
+                "Cancels the current action and goes back to the menu",
+            ],
+            Msg::CmdHelp => [
+                "Muestra un mensaje de ayuda sobre cómo usar el bot",
+                "Mostra un missatge d'ajuda sobre com utilitzar el bot",
+                "Shows a help message about how to use the bot",
+            ],
+            Msg::NotFoundYet => [
+                "El vehículo {plate} no ha sido encontrado todavía",
+                "El vehicle {plate} encara no ha sigut trobat",
+                "Vehicle {plate} hasn't been found yet",
+            ],
+            Msg::FoundAt => [
+                "El vehículo {plate} fue encontrado el {date} 🙌🏼",
+                "El vehicle {plate} va ser trobat el {date} 🙌🏼",
+                "Vehicle {plate} was found on {date} 🙌🏼",
+            ],
+            Msg::Location => [
+                "📍 Ubicación: {location}",
+                "📍 Ubicació: {location}",
+                "📍 Location: {location}",
+            ],
+            Msg::RegisteredAt => [
+                "🗓️ Registrado el {date}",
+                "🗓️ Registrat el {date}",
+                "🗓️ Registered on {date}",
+            ],
+            Msg::Reference => [
+                "🔖 Referencia: {reference}",
+                "🔖 Referència: {reference}",
+                "🔖 Reference: {reference}",
+            ],
+            Msg::Contact => [
+                "📞 Contacto: {contact}",
+                "📞 Contacte: {contact}",
+                "📞 Contact: {contact}",
+            ],
+            Msg::EmailSubject => [
+                "Tu vehículo {plate} ha sido encontrado",
+                "El teu vehicle {plate} ha sigut trobat",
+                "Your vehicle {plate} has been found",
+            ],
+            Msg::EmailFooter => [
+                "Este aviso lo envía Tu Coche Dana Bot, proyecto no afiliado con tucochedana.es",
+                "Aquest avís l'envia Tu Coche Dana Bot, projecte no afiliat amb tucochedana.es",
+                "This alert is sent by Tu Coche Dana Bot, a project not affiliated with tucochedana.es",
+            ],
+        }
+    }
+
+    pub fn text(&self, lang: Lang) -> &'static str {
+        let [es, ca, en] = self.catalog();
+        match lang {
+            Lang::Es => es,
+            Lang::Ca => ca,
+            Lang::En => en,
+        }
+    }
+
+    /// Text with its `{name}` placeholders replaced in one pass, so braces in the values
+    /// are never taken as placeholders
+    pub fn format(&self, lang: Lang, args: &[(&str, &str)]) -> String {
+        let mut text = String::new();
+        let mut rest = self.text(lang);
+
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let value = rest.find('}').and_then(|end| {
+                let name = &rest[1..end];
+                let (_, value) = args.iter().find(|(arg, _)| *arg == name)?;
+                Some((value, end))
+            });
+            match value {
+                Some((value, end)) => {
+                    text.push_str(value);
+                    rest = &rest[end + 1..];
+                }
+                // Unknown placeholders are left as they are
+                None => {
+                    text.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        text.push_str(rest);
+        text
+    }
+}
+
+#[cfg(test)]
+mod i18n_tests {
+    use chrono::{TimeZone, Utc};
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(Lang::from_code(None), Lang::Es);
+        assert_eq!(Lang::from_code(Some("")), Lang::Es);
+        assert_eq!(Lang::from_code(Some("es-ES")), Lang::Es);
+        assert_eq!(Lang::from_code(Some("ca")), Lang::Ca);
+        assert_eq!(Lang::from_code(Some("ca-ES-valencia")), Lang::Ca);
+        assert_eq!(Lang::from_code(Some("en-GB")), Lang::En);
+        assert_eq!(Lang::from_code(Some("de")), Lang::En);
+        assert_eq!("ca".parse::<Lang>(), Ok(Lang::Ca));
+        assert!("de".parse::<Lang>().is_err());
+    }
+
+    #[test]
+    fn test_same_placeholders() {
+        let placeholder = Regex::new(r"\{[a-z_]+\}").unwrap();
+        let placeholders = |text: &str| {
+            let mut found: Vec<String> = placeholder
+                .find_iter(text)
+                .map(|m| m.as_str().to_string())
+                .collect();
+            found.sort();
+            found
+        };
+
+        for msg in Msg::ALL {
+            let expected = placeholders(msg.text(Lang::Es));
+            for lang in Lang::ALL {
+                assert!(!msg.text(lang).is_empty(), "{msg:?} {lang}");
+                assert_eq!(placeholders(msg.text(lang)), expected, "{msg:?} {lang}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_datetime() {
+        let time = Utc.with_ymd_and_hms(2024, 10, 29, 9, 5, 0).unwrap();
+
+        assert_eq!(
+            Lang::Es.format_datetime(&time),
+            "martes, 29 de octubre de 2024, 09:05"
+        );
+        assert_eq!(
+            Lang::Ca.format_datetime(&time),
+            "dimarts, 29 d'octubre de 2024, 09:05"
+        );
+        assert_eq!(
+            Lang::En.format_datetime(&time),
+            "Tuesday, 29 October 2024, 09:05"
+        );
+        assert_eq!(
+            Lang::Ca.format_date(&NaiveDate::from_ymd_opt(2024, 11, 4).unwrap()),
+            "4 de novembre de 2024"
+        );
+    }
+
+    #[test]
+    fn test_locale_timezones() {
+        let madrid = Locale::new(Lang::Es, chrono_tz::Europe::Madrid);
+        let canary = Locale::new(Lang::Es, chrono_tz::Atlantic::Canary);
+
+        // CEST (UTC+2) and CET (UTC+1)
+        let summer = Utc.with_ymd_and_hms(2024, 7, 1, 12, 0, 0).unwrap();
+        let winter = Utc.with_ymd_and_hms(2024, 12, 1, 12, 0, 0).unwrap();
+        assert!(madrid.format_datetime(&summer).ends_with("14:00"));
+        assert!(madrid.format_datetime(&winter).ends_with("13:00"));
+        assert!(canary.format_datetime(&winter).ends_with("12:00"));
+
+        // The local date changes before the UTC one
+        let late = Utc.with_ymd_and_hms(2024, 10, 29, 23, 30, 0).unwrap();
+        assert_eq!(
+            madrid.format_datetime(&late),
+            "miércoles, 30 de octubre de 2024, 00:30"
+        );
+
+        // The clocks go back at 03:00 CEST on the last Sunday of October
+        let before = Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 10, 27, 1, 30, 0).unwrap();
+        assert!(madrid.format_datetime(&before).ends_with("02:30"));
+        assert!(madrid.format_datetime(&after).ends_with("02:30"));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            Msg::VehicleAdded.format(Lang::En, &[("plate", "1234BCD"), ("note", "")]),
+            "Vehicle 1234BCD added ✅"
+        );
+        // Values aren't searched for placeholders
+        assert_eq!(
+            Msg::VehicleAdded.format(Lang::En, &[("plate", "{note}"), ("note", "!")]),
+            "Vehicle {note} added ✅!"
+        );
+        assert_eq!(
+            Msg::VehicleAdded.format(Lang::En, &[("note", "{plate}")]),
+            "Vehicle {plate} added ✅{plate}"
+        );
+    }
+}
@@ -1,7 +1,11 @@
+use chrono_tz::Tz;
+use db::model::chat_channel::NotificationChannel;
 use db::BotDbError;
 use fang::{AsyncQueueError, FangError, ToFangError};
-use frankenstein::reqwest::StatusCode;
+use ipnet::IpNet;
 use lazy_static::lazy_static;
+use plate::PlateError;
+use runtime::RuntimeMode;
 use std::fmt::{self, Debug};
 use telegram::client::ApiError;
 use thiserror::Error;
@@ -15,6 +19,17 @@ lazy_static! {
     pub static ref API_URL: String = std::env::var("API_URL").expect("API_URL not set");
     pub static ref WEBHOOK_URL: String = std::env::var("WEBHOOK_URL").expect("WEBHOOK_URL not set");
     pub static ref WEBHOOK_CERT: Option<String> = std::env::var("WEBHOOK_CERT").ok();
+    /// Random on each start when not set, it's registered again with the webhook anyway
+    pub static ref WEBHOOK_SECRET_TOKEN: String = std::env::var("WEBHOOK_SECRET_TOKEN")
+        .unwrap_or_else(|_| server::random_secret_token());
+    pub static ref WEBHOOK_ALLOWED_IPS: Vec<IpNet> = std::env::var("WEBHOOK_ALLOWED_IPS")
+        .map(|ranges| server::parse_ip_ranges(&ranges))
+        .unwrap_or(Ok(vec![]))
+        .expect("WEBHOOK_ALLOWED_IPS should be a comma separated list of IP ranges");
+    pub static ref WEBHOOK_MAX_BODY_BYTES: usize = std::env::var("WEBHOOK_MAX_BODY_BYTES")
+        .unwrap_or(String::from("262144"))
+        .parse()
+        .expect("WEBHOOK_MAX_BODY_BYTES should be a number of bytes");
     pub static ref WEBHOOK_PORT: u32 = std::env::var("WEBHOOK_PORT")
         .expect("WEBHOOK_PORT not set")
         .parse()
@@ -28,6 +43,40 @@ lazy_static! {
         .unwrap_or(String::from("5"))
         .parse()
         .expect("The number of minutes should be 0<=N<=255");
+    pub static ref SWEEP_CONCURRENCY: usize = std::env::var("SWEEP_CONCURRENCY")
+        .unwrap_or(String::from("5"))
+        .parse()
+        .expect("The number of concurrent lookups should be a positive number");
+    pub static ref SWEEP_JITTER_MS: u64 = std::env::var("SWEEP_JITTER_MS")
+        .unwrap_or(String::from("750"))
+        .parse()
+        .expect("The jitter should be a number of milliseconds");
+    pub static ref RUNTIME_MODE: RuntimeMode = std::env::var("RUNTIME_MODE")
+        .unwrap_or(String::from("webhook"))
+        .parse()
+        .expect("RUNTIME_MODE should be webhook or polling");
+    /// Telegram keeps undelivered updates for 24 hours
+    pub static ref UPDATE_DEDUP_HOURS: i64 = std::env::var("UPDATE_DEDUP_HOURS")
+        .unwrap_or(String::from("24"))
+        .parse()
+        .expect("UPDATE_DEDUP_HOURS should be a number of hours");
+    pub static ref PARTNER_WEBHOOK_MAX_RETRIES: i32 = std::env::var("PARTNER_WEBHOOK_MAX_RETRIES")
+        .unwrap_or(String::from("5"))
+        .parse()
+        .expect("PARTNER_WEBHOOK_MAX_RETRIES should be a number of retries");
+    /// Email notifications are disabled when not set
+    pub static ref SMTP_URL: Option<String> = std::env::var("SMTP_URL").ok();
+    pub static ref SMTP_FROM: String = std::env::var("SMTP_FROM")
+        .unwrap_or(String::from("Tu Coche Dana Bot <noreply@tucochedana.bot>"));
+    /// Used to show the times to the chats that haven't chosen another one
+    pub static ref DEFAULT_TIMEZONE: Tz = std::env::var("DEFAULT_TIMEZONE")
+        .unwrap_or(String::from("Europe/Madrid"))
+        .parse()
+        .expect("DEFAULT_TIMEZONE should be an IANA timezone such as Europe/Madrid");
+    /// Buttons are signed when set, the ones sent before changing it stop working
+    pub static ref CALLBACK_SECRET: Option<String> = std::env::var("CALLBACK_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty());
     pub static ref MAX_RETRIES: i32 = std::env::var("MAX_RETRIES")
         .unwrap_or(String::from("1"))
         .parse()
@@ -35,6 +84,7 @@ lazy_static! {
 }
 
 const TASK_NAME: &str = "scheduled_fetch";
+const NOTIFICATION_TASK_NAME: &str = "notification";
 
 /// HTTP Server module
 pub mod server;
@@ -42,32 +92,61 @@ pub mod server;
 /// Fang task
 pub mod workers;
 
+/// Webhook and polling modes
+pub mod runtime;
+
+/// Health and readiness checks
+pub mod health;
+
+/// Prometheus metrics
+pub mod metrics;
+
+/// REST API for partners
+pub mod rest_api;
+
+/// Notification channels
+pub mod notifier;
+
+/// Translations of the texts shown to the users
+pub mod i18n;
+
 /// API Module
 pub mod tucochedana {
     pub mod client;
+    pub mod lookup;
 }
 
 /// Database module
 pub mod db;
 
+/// Spanish licence plates
+pub mod plate;
+
 pub mod telegram {
     pub mod client;
 }
 
 pub mod update_handler {
+    pub mod args;
+    pub mod callback;
     pub mod command;
+    pub mod deep_link;
+    pub mod dispatcher;
     pub mod process_update;
 }
 
 pub mod tasks {
-    pub mod fetch;
+    pub mod cleanup;
+    pub mod notification;
+    pub mod partner_webhook;
+    pub mod sweep;
 }
 
 #[derive(Debug, Error, ToFangError)]
 pub enum BotError {
     #[error(transparent)]
     MessageError(#[from] std::fmt::Error),
-    #[error("Update can not be processed {}", self)]
+    #[error("Update can not be processed: {0}")]
     UpdateNotMessage(String),
     #[error(transparent)]
     TelegramError(#[from] ApiError),
@@ -77,14 +156,26 @@ pub enum BotError {
     ReqwestError(#[from] frankenstein::reqwest::Error),
     #[error(transparent)]
     SerdeJsonError(#[from] SerdeJSONError),
-    #[error("Api returned code {0}: {1}")]
-    TuCocheDanaError(StatusCode, String),
     #[error(transparent)]
     HttpError(#[from] std::io::Error),
+    #[error("Failed to start, {0}")]
+    StartupError(String),
     #[error(transparent)]
     AsyncQueueError(#[from] AsyncQueueError),
-    #[error("Bad Fetch Task: {}", self)]
-    FetchTaskError(String),
+    #[error(transparent)]
+    PlateError(#[from] PlateError),
+    #[error("Webhook delivery {0} failed: {1}")]
+    WebhookDeliveryError(i64, String),
+    #[error("Chat {0} has no address for {1:?}")]
+    MissingAddress(i64, NotificationChannel),
+    #[error("Notification channel {0:?} is not configured")]
+    ChannelUnavailable(NotificationChannel),
+    #[error(transparent)]
+    SmtpError(#[from] lettre::transport::smtp::Error),
+    #[error(transparent)]
+    EmailError(#[from] lettre::error::Error),
+    #[error(transparent)]
+    EmailAddressError(#[from] lettre::address::AddressError),
 }
 
 #[derive(Debug, Error)]
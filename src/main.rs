@@ -1,6 +1,8 @@
 use tu_coche_dana_bot::{
-    server::app, telegram::client::ApiClient, workers, SERVER_PORT, WEBHOOK_CERT, WEBHOOK_PORT,
-    WEBHOOK_URL,
+    db::Repo,
+    i18n::Lang,
+    update_handler::command::frontend::help::{help_markdown, help_path},
+    RUNTIME_MODE,
 };
 
 #[tokio::main]
@@ -10,28 +12,32 @@ async fn main() {
     // Logger
     pretty_env_logger::init_timed();
 
-    // Start fang workers
-    let queue = workers::start_workers().await.unwrap();
+    let mut args = std::env::args().skip(1);
 
-    // Webhook setup
-    let telegram = ApiClient::api_client().await;
-    let webhook = match *WEBHOOK_PORT {
-        443 | 80 => format!("{}/webhook", *WEBHOOK_URL), //Debe estar bien formateado (http o https)
-        _ => format!("{}:{}/webhook", *WEBHOOK_URL, *WEBHOOK_PORT),
-    };
-    let response = telegram
-        .set_webhook(&webhook, None, WEBHOOK_CERT.clone())
-        .await
-        .unwrap();
-    if response.ok && response.result {
-        log::info!("Setted Telegram webhook at URL {}", webhook);
-    } else {
-        log::error!("{:?}", response.description);
+    match args.next().as_deref() {
+        // Keys for the partners using the REST API
+        Some("create-api-key") => {
+            let name = args
+                .next()
+                .expect("Usage: tu-coche-dana-bot create-api-key <partner name>");
+
+            let repo = Repo::repo().await.unwrap();
+            let (api_key, key) = repo.create_api_key(&name).await.unwrap();
+
+            println!("API key {} for '{}': {key}", api_key.id, api_key.name);
+        }
+        // Help files from the commands metadata, run it from the root of the repository
+        Some("generate-help") => {
+            for lang in Lang::ALL {
+                std::fs::write(help_path(lang), help_markdown(lang)).unwrap();
+                println!("Generated {}", help_path(lang));
+            }
+        }
+        _ => {
+            if let Err(err) = RUNTIME_MODE.run().await {
+                log::error!("{err}");
+                std::process::exit(1);
+            }
+        }
     }
-    // Http Server
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", *SERVER_PORT))
-        .await
-        .unwrap();
-    log::info!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app(queue)).await.unwrap();
 }
@@ -0,0 +1,107 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec_with_registry, register_histogram_with_registry,
+    register_int_counter_vec_with_registry, register_int_counter_with_registry,
+    register_int_gauge_with_registry, Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, Registry, TextEncoder,
+};
+
+lazy_static! {
+    pub static ref REGISTRY: Registry =
+        Registry::new_custom(Some("tu_coche_dana_bot".to_string()), None).unwrap();
+    pub static ref UPDATES_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "updates_total",
+        "Updates processed by command and outcome",
+        &["command", "outcome"],
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref UPDATE_DURATION: HistogramVec = register_histogram_vec_with_registry!(
+        "update_duration_seconds",
+        "Time spent processing an update by command",
+        &["command"],
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref LOOKUPS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lookups_total",
+        "Lookups made to tucochedana.es by status",
+        &["status"],
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref LOOKUP_DURATION: Histogram = register_histogram_with_registry!(
+        "lookup_duration_seconds",
+        "Latency of the lookups made to tucochedana.es",
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0],
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref VEHICLES_FOUND_TOTAL: IntCounter = register_int_counter_with_registry!(
+        "vehicles_found_total",
+        "Vehicles found by the sweep",
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref TASKS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "tasks_total",
+        "Fang task runs by task and outcome",
+        &["task", "outcome"],
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref NOTIFICATIONS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "notifications_total",
+        "Notification delivery attempts by outcome",
+        &["outcome"],
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref ACTIVE_CHATS: IntGauge = register_int_gauge_with_registry!(
+        "active_chats",
+        "Chats with the alerts enabled",
+        REGISTRY
+    )
+    .unwrap();
+    pub static ref PENDING_VEHICLES: IntGauge = register_int_gauge_with_registry!(
+        "pending_vehicles",
+        "Vehicles not found yet that active chats are waiting for",
+        REGISTRY
+    )
+    .unwrap();
+}
+
+/// Counts the run of a fang task
+pub fn record_task<T, E>(task: &str, result: &Result<T, E>) {
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    TASKS_TOTAL.with_label_values(&[task, outcome]).inc();
+}
+
+/// Every metric in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = vec![];
+
+    if let Err(err) = TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer) {
+        log::error!("Failed to encode the metrics: {err}");
+    }
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        UPDATES_TOTAL.with_label_values(&["start", "ok"]).inc();
+        record_task("sweep", &Ok::<(), ()>(()));
+        ACTIVE_CHATS.set(3);
+
+        let text = render();
+
+        assert!(text.contains(r#"tu_coche_dana_bot_updates_total{command="start",outcome="ok"}"#));
+        assert!(text.contains(r#"tu_coche_dana_bot_tasks_total{outcome="ok",task="sweep"}"#));
+        assert!(text.contains("tu_coche_dana_bot_active_chats 3"));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{fmt, str::FromStr};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+
+/// Provinces codes used by the provincial system (1900-2000)
+const PROVINCES: &[&str] = &[
+    "A", "AB", "AL", "AV", "B", "BA", "BI", "BU", "C", "CA", "CC", "CE", "CO", "CR", "CS", "CU",
+    "GC", "GE", "GI", "GR", "GU", "H", "HU", "IB", "J", "L", "LE", "LO", "LU", "M", "MA", "ML",
+    "MU", "NA", "O", "OR", "OU", "P", "PM", "PO", "S", "SA", "SE", "SG", "SO", "SS", "T", "TE",
+    "TF", "TO", "V", "VA", "VI", "Z", "ZA",
+];
+
+/// Prefixes of official bodies (army, police...)
+const OFFICIAL_PREFIXES: &[&str] = &[
+    "ET", "EA", "FN", "PGC", "CNP", "DGP", "MF", "MMA", "PMM", "CME",
+];
+
+lazy_static! {
+    // Letters never include vowels, Ñ or Q
+    static ref NATIONAL: Regex = Regex::new(r"^[0-9]{4}[BCDFGHJKLMNPRSTVWXYZ]{3}$").unwrap();
+    static ref PREFIXED: Regex =
+        Regex::new(r"^([CREHPS])([0-9]{4}[BCDFGHJKLMNPRSTVWXYZ]{3})$").unwrap();
+    static ref PROVINCIAL: Regex = Regex::new(r"^([A-Z]{1,2})([0-9]{4})([A-Z]{1,2})$").unwrap();
+    static ref OLD_PROVINCIAL: Regex = Regex::new(r"^([A-Z]{1,2})([0-9]{1,6})$").unwrap();
+    static ref OFFICIAL: Regex = Regex::new(r"^([A-Z]{2,3})([0-9]{4,6})([A-Z]{0,2})$").unwrap();
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum PlateError {
+    #[error("Empty plate")]
+    Empty,
+    #[error("Plate '{0}' contains invalid characters")]
+    InvalidCharacters(String),
+    #[error("Unknown province code '{1}' in plate '{0}'")]
+    UnknownProvince(String, String),
+    #[error("Plate '{0}' doesn't follow any known format")]
+    UnknownFormat(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlateKind {
+    /// `1234BCD`, since 2000
+    National,
+    /// `V1234AB` (1971-2000) or `V123456` (until 1971)
+    Provincial,
+    /// `C1234BCD`
+    Moped,
+    /// `R1234BCD`
+    Trailer,
+    /// Special, historic and temporary vehicles (`E1234BCD`, `H1234BCD`, `P1234BCD`...)
+    /// and official bodies (`ET12345`, `PGC1234A`...)
+    Special,
+    /// Stored before plates were validated, only accepted for the vehicles a chat already follows
+    Unverified,
+}
+
+/// Spanish licence plate in its canonical form: uppercase and without separators
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Plate {
+    canonical: String,
+    kind: PlateKind,
+}
+
+impl Plate {
+    pub fn parse(input: &str) -> Result<Self, PlateError> {
+        input.parse()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.canonical
+    }
+
+    pub fn kind(&self) -> PlateKind {
+        self.kind
+    }
+
+    /// Uppercases and removes the usual separators (spaces, hyphens and dots)
+    pub fn normalize(input: &str) -> Result<String, PlateError> {
+        let normalized = Self::strip(input);
+
+        if normalized.is_empty() {
+            return Err(PlateError::Empty);
+        }
+
+        if !normalized.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(PlateError::InvalidCharacters(input.trim().to_string()));
+        }
+
+        Ok(normalized)
+    }
+
+    /// Plate that doesn't follow any format, written the way the `normalize_plates` migration
+    /// left the stored ones
+    pub fn unverified(input: &str) -> Result<Self, PlateError> {
+        let plate = Self::strip(input);
+        if plate.is_empty() {
+            return Err(PlateError::Empty);
+        }
+        Ok(Self::new(plate, PlateKind::Unverified))
+    }
+
+    fn strip(input: &str) -> String {
+        input
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '.')
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    }
+
+    fn new(canonical: String, kind: PlateKind) -> Self {
+        Plate { canonical, kind }
+    }
+}
+
+impl FromStr for Plate {
+    type Err = PlateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let plate = Self::normalize(s)?;
+
+        if NATIONAL.is_match(&plate) {
+            return Ok(Self::new(plate, PlateKind::National));
+        }
+
+        if let Some(captures) = PREFIXED.captures(&plate) {
+            let kind = match &captures[1] {
+                "C" => PlateKind::Moped,
+                "R" => PlateKind::Trailer,
+                _ => PlateKind::Special,
+            };
+            return Ok(Self::new(plate, kind));
+        }
+
+        if let Some(captures) = OFFICIAL.captures(&plate) {
+            if OFFICIAL_PREFIXES.contains(&&captures[1]) {
+                return Ok(Self::new(plate, PlateKind::Special));
+            }
+        }
+
+        if let Some(captures) = PROVINCIAL
+            .captures(&plate)
+            .or_else(|| OLD_PROVINCIAL.captures(&plate))
+        {
+            let province = &captures[1];
+            if !PROVINCES.contains(&province) {
+                return Err(PlateError::UnknownProvince(
+                    plate.clone(),
+                    province.to_string(),
+                ));
+            }
+            return Ok(Self::new(plate, PlateKind::Provincial));
+        }
+
+        Err(PlateError::UnknownFormat(plate))
+    }
+}
+
+/// Most tokens a plate is written with, `V 1234 AB`
+const MAX_PLATE_TOKENS: usize = 3;
+
+/// Plates of a list separated by newlines, commas, semicolons or spaces, in order and
+/// without repetitions. Spaces may also be part of a plate, `1234 BCD, 5678 CDF` are two
+pub fn parse_plates(input: &str) -> Vec<Result<Plate, PlateError>> {
+    let mut plates: Vec<Result<Plate, PlateError>> = vec![];
+
+    for line in input.split(['\n', '\r', ',', ';', '\t']) {
+        // Quoted CSV fields
+        let tokens: Vec<&str> = line
+            .split_whitespace()
+            .map(|token| token.trim_matches(['"', '\'']))
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            // The longest run of tokens that makes a plate
+            let longest = (1..=MAX_PLATE_TOKENS.min(tokens.len() - i))
+                .rev()
+                .find_map(|n| {
+                    Plate::parse(&tokens[i..i + n].concat())
+                        .ok()
+                        .map(|plate| (n, plate))
+                });
+
+            let plate = match longest {
+                Some((n, plate)) => {
+                    i += n;
+                    Ok(plate)
+                }
+                None => {
+                    i += 1;
+                    Plate::parse(tokens[i - 1])
+                }
+            };
+
+            if !plates.contains(&plate) {
+                plates.push(plate);
+            }
+        }
+    }
+
+    plates
+}
+
+impl fmt::Display for Plate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.canonical)
+    }
+}
+
+impl AsRef<str> for Plate {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<Plate> for String {
+    fn from(plate: Plate) -> String {
+        plate.canonical
+    }
+}
+
+#[cfg(test)]
+mod plate_tests {
+    use super::*;
+
+    #[test]
+    fn test_national_plate_variants() {
+        for input in ["1234 bcd", "1234-BCD", "1234BCD", " 1234.bcd "] {
+            let plate = Plate::parse(input).unwrap();
+            assert_eq!(plate.as_str(), "1234BCD", "{input}");
+            assert_eq!(plate.kind(), PlateKind::National);
+        }
+    }
+
+    #[test]
+    fn test_national_plate_with_vowels() {
+        assert_eq!(
+            Plate::parse("1234 ABC"),
+            Err(PlateError::UnknownFormat("1234ABC".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_provincial_plates() {
+        let plate = Plate::parse("V-1234-AB").unwrap();
+        assert_eq!(plate.to_string(), "V1234AB");
+        assert_eq!(plate.kind(), PlateKind::Provincial);
+
+        let plate = Plate::parse("M 123456").unwrap();
+        assert_eq!(plate.to_string(), "M123456");
+        assert_eq!(plate.kind(), PlateKind::Provincial);
+
+        assert_eq!(
+            Plate::parse("XX-1234-AB"),
+            Err(PlateError::UnknownProvince(
+                "XX1234AB".to_string(),
+                "XX".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_prefixed_plates() {
+        assert_eq!(Plate::parse("C 1234 BCD").unwrap().kind(), PlateKind::Moped);
+        assert_eq!(
+            Plate::parse("R-1234-BCD").unwrap().kind(),
+            PlateKind::Trailer
+        );
+        assert_eq!(Plate::parse("E1234BCD").unwrap().kind(), PlateKind::Special);
+        assert_eq!(
+            Plate::parse("H 1234 BCD").unwrap().kind(),
+            PlateKind::Special
+        );
+        assert_eq!(Plate::parse("ET 12345").unwrap().kind(), PlateKind::Special);
+        assert_eq!(
+            Plate::parse("PGC 1234 A").unwrap().kind(),
+            PlateKind::Special
+        );
+    }
+
+    #[test]
+    fn test_parse_plates() {
+        let plates =
+            parse_plates("1234 bcd, V-1234-AB\n\"5678CDF\";1234BCD\r\nhola 9999 BBB M 123456");
+        let plates: Vec<_> = plates
+            .into_iter()
+            .map(|plate| plate.map(String::from))
+            .collect();
+
+        assert_eq!(
+            plates,
+            vec![
+                Ok("1234BCD".to_string()),
+                Ok("V1234AB".to_string()),
+                Ok("5678CDF".to_string()),
+                Err(PlateError::UnknownFormat("HOLA".to_string())),
+                Ok("9999BBB".to_string()),
+                Ok("M123456".to_string()),
+            ]
+        );
+        assert!(parse_plates(" ,\n ").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_plates() {
+        assert_eq!(Plate::parse("   "), Err(PlateError::Empty));
+        assert_eq!(
+            Plate::parse("1234,BCD"),
+            Err(PlateError::InvalidCharacters("1234,BCD".to_string()))
+        );
+        assert!(matches!(
+            Plate::parse("hola que tal"),
+            Err(PlateError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_unverified_plates() {
+        let plate = Plate::unverified("hola que-tal").unwrap();
+        assert_eq!(plate.as_str(), "HOLAQUETAL");
+        assert_eq!(plate.kind(), PlateKind::Unverified);
+        assert_eq!(Plate::unverified(" - "), Err(PlateError::Empty));
+    }
+}
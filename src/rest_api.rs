@@ -0,0 +1,692 @@
+use std::net::IpAddr;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    db::{
+        model::{
+            api_key::ApiKey,
+            api_subscription::ApiSubscription,
+            notification::NotificationStatus,
+            partner_webhook::{PartnerWebhook, WebhookAttempt},
+            vehicle::{FoundDetails, Vehicle},
+        },
+        BotDbError,
+    },
+    plate::{Plate, PlateError},
+    server::AppState,
+};
+
+/// Header with the key given to the partner
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Deliveries returned by `/webhooks/:id/deliveries`
+const DELIVERIES_LIMIT: i64 = 50;
+
+/// Loopback, private, shared and link-local ranges the webhooks can't point to
+const INTERNAL_IP_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "::/128",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+const OPENAPI: &str = include_str!("../resources/openapi.yaml");
+
+/// Routes of `/api/v1`
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/openapi.yaml", get(openapi))
+        .route("/plates/:plate", get(plate_status))
+        .route("/subscriptions", post(create_subscription))
+        .route("/subscriptions/:plate", delete(delete_subscription))
+        .route("/vehicles/found", get(found_vehicles))
+        .route("/webhooks", get(webhooks).post(create_webhook))
+        .route("/webhooks/:id", delete(delete_webhook))
+        .route("/webhooks/:id/deliveries", get(webhook_deliveries))
+}
+
+#[derive(Debug, Error)]
+pub enum RestError {
+    #[error("Missing or invalid API key")]
+    Unauthorized,
+    #[error(transparent)]
+    InvalidPlate(#[from] PlateError),
+    #[error("Plate '{0}' is already registered")]
+    AlreadySubscribed(String),
+    #[error("Plate '{0}' is not registered")]
+    NotSubscribed(String),
+    #[error("Invalid webhook URL '{0}', it must be http or https")]
+    InvalidUrl(String),
+    #[error("Webhook URL '{0}' points to an internal address")]
+    InternalUrl(String),
+    #[error("Webhook {0} not found")]
+    WebhookNotFound(i64),
+    #[error(transparent)]
+    DbError(#[from] BotDbError),
+}
+
+impl IntoResponse for RestError {
+    fn into_response(self) -> axum::response::Response {
+        let status = match self {
+            RestError::Unauthorized => StatusCode::UNAUTHORIZED,
+            RestError::InvalidPlate(_) => StatusCode::BAD_REQUEST,
+            RestError::AlreadySubscribed(_) => StatusCode::CONFLICT,
+            RestError::NotSubscribed(_) => StatusCode::NOT_FOUND,
+            RestError::InvalidUrl(_) | RestError::InternalUrl(_) => StatusCode::BAD_REQUEST,
+            RestError::WebhookNotFound(_) => StatusCode::NOT_FOUND,
+            RestError::DbError(ref err) => {
+                log::error!("REST API database error: {err:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+
+        let message = match status {
+            StatusCode::INTERNAL_SERVER_ERROR => "Internal error".to_string(),
+            _ => self.to_string(),
+        };
+
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiKey {
+    type Rejection = RestError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let key = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(RestError::Unauthorized)?;
+
+        state
+            .repo
+            .get_api_key(key)
+            .await?
+            .ok_or(RestError::Unauthorized)
+    }
+}
+
+/// Status of a plate as the bot knows it
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PlateStatus {
+    pub plate: String,
+    /// `found`, `not_found` or `unchecked`
+    pub status: &'static str,
+    pub found_at: Option<DateTime<Utc>>,
+    pub checked_at: Option<DateTime<Utc>>,
+    pub details: Option<FoundDetails>,
+}
+
+impl From<Vehicle> for PlateStatus {
+    fn from(vehicle: Vehicle) -> Self {
+        let status = match (vehicle.found_at, vehicle.checked_at) {
+            (Some(_), _) => "found",
+            (None, Some(_)) => "not_found",
+            (None, None) => "unchecked",
+        };
+
+        PlateStatus {
+            plate: vehicle.plate,
+            status,
+            found_at: vehicle.found_at,
+            checked_at: vehicle.checked_at,
+            details: vehicle.found_at.map(|_| vehicle.details),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewSubscription {
+    pub plate: String,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionResponse {
+    pub plate: String,
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub vehicle: PlateStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FoundQuery {
+    pub since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Every plate registered with the key when missing
+    pub plates: Option<Vec<String>>,
+}
+
+/// Webhook as listed, the secret is only returned when it's created
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub id: i64,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub plates: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PartnerWebhook> for WebhookResponse {
+    fn from(webhook: PartnerWebhook) -> Self {
+        WebhookResponse {
+            id: webhook.id,
+            url: webhook.url,
+            secret: None,
+            plates: webhook.plates,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryResponse {
+    pub id: i64,
+    pub event: String,
+    pub plate: String,
+    pub status: NotificationStatus,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub attempts: Vec<WebhookAttempt>,
+}
+
+async fn openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/yaml")], OPENAPI)
+}
+
+async fn plate_status(
+    State(state): State<AppState>,
+    _key: ApiKey,
+    Path(plate): Path<String>,
+) -> Result<Json<PlateStatus>, RestError> {
+    let plate = Plate::parse(&plate)?;
+
+    // Same as /check_vehicle in the bot
+    let vehicle = state.repo.find_or_create_vehicle(plate.as_str()).await?;
+
+    Ok(Json(vehicle.into()))
+}
+
+async fn create_subscription(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Json(body): Json<NewSubscription>,
+) -> Result<(StatusCode, Json<SubscriptionResponse>), RestError> {
+    let plate = Plate::parse(&body.plate)?;
+
+    let vehicle = state.repo.find_or_create_vehicle(plate.as_str()).await?;
+
+    let ApiSubscription {
+        plate,
+        reference,
+        created_at,
+        ..
+    } = state
+        .repo
+        .create_api_subscription(key.id, plate.as_str(), body.reference.as_deref())
+        .await?
+        .ok_or_else(|| RestError::AlreadySubscribed(plate.to_string()))?;
+
+    log::info!("API key {} registered {}", key.name, plate);
+
+    let response = SubscriptionResponse {
+        plate,
+        reference,
+        created_at,
+        vehicle: vehicle.into(),
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+async fn delete_subscription(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Path(plate): Path<String>,
+) -> Result<StatusCode, RestError> {
+    let plate = Plate::parse(&plate)?;
+
+    match state
+        .repo
+        .end_api_subscription(key.id, plate.as_str())
+        .await?
+    {
+        0 => Err(RestError::NotSubscribed(plate.to_string())),
+        _ => Ok(StatusCode::NO_CONTENT),
+    }
+}
+
+async fn found_vehicles(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Query(query): Query<FoundQuery>,
+) -> Result<Json<Vec<PlateStatus>>, RestError> {
+    let vehicles = state
+        .repo
+        .get_found_vehicles_by_api_key(key.id, query.since)
+        .await?;
+
+    Ok(Json(vehicles.into_iter().map(PlateStatus::from).collect()))
+}
+
+async fn webhooks(
+    State(state): State<AppState>,
+    key: ApiKey,
+) -> Result<Json<Vec<WebhookResponse>>, RestError> {
+    let webhooks = state.repo.get_partner_webhooks(key.id).await?;
+
+    Ok(Json(
+        webhooks.into_iter().map(WebhookResponse::from).collect(),
+    ))
+}
+
+async fn create_webhook(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Json(body): Json<NewWebhook>,
+) -> Result<(StatusCode, Json<WebhookResponse>), RestError> {
+    let url = body.url.trim();
+    check_webhook_url(url)?;
+
+    let plates = body
+        .plates
+        .map(|plates| {
+            plates
+                .iter()
+                .map(|plate| Plate::parse(plate).map(String::from))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+
+    let webhook = state
+        .repo
+        .create_partner_webhook(key.id, url, plates.as_deref())
+        .await?;
+
+    log::info!("API key {} registered webhook {}", key.name, webhook.id);
+
+    let secret = webhook.secret.clone();
+    let response = WebhookResponse {
+        secret: Some(secret),
+        ..webhook.into()
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Only public http and https URLs, the bot must not be used to reach its own network
+pub fn check_webhook_url(url: &str) -> Result<(), RestError> {
+    let uri = match url.parse::<axum::http::Uri>() {
+        Ok(uri) if matches!(uri.scheme_str(), Some("http" | "https")) => uri,
+        _ => return Err(RestError::InvalidUrl(url.to_string())),
+    };
+    let Some(host) = uri.host() else {
+        return Err(RestError::InvalidUrl(url.to_string()));
+    };
+
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") {
+        return Err(RestError::InternalUrl(url.to_string()));
+    }
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if is_internal_ip(ip) {
+            return Err(RestError::InternalUrl(url.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Loopback, private and link-local addresses, IPv4 mapped to IPv6 included
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    let ip = ip.to_canonical();
+    INTERNAL_IP_RANGES
+        .iter()
+        .filter_map(|range| range.parse::<IpNet>().ok())
+        .any(|range| range.contains(&ip))
+}
+
+async fn delete_webhook(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, RestError> {
+    match state.repo.delete_partner_webhook(id, key.id).await? {
+        0 => Err(RestError::WebhookNotFound(id)),
+        _ => Ok(StatusCode::NO_CONTENT),
+    }
+}
+
+async fn webhook_deliveries(
+    State(state): State<AppState>,
+    key: ApiKey,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<DeliveryResponse>>, RestError> {
+    let webhooks = state.repo.get_partner_webhooks(key.id).await?;
+    if !webhooks.iter().any(|webhook| webhook.id == id) {
+        return Err(RestError::WebhookNotFound(id));
+    }
+
+    let deliveries = state
+        .repo
+        .get_webhook_deliveries(id, key.id, DELIVERIES_LIMIT)
+        .await?;
+
+    let mut response = Vec::with_capacity(deliveries.len());
+    for delivery in deliveries {
+        response.push(DeliveryResponse {
+            attempts: state.repo.get_webhook_attempts(delivery.id).await?,
+            id: delivery.id,
+            event: delivery.event,
+            plate: delivery.plate,
+            status: delivery.status,
+            created_at: delivery.created_at,
+            delivered_at: delivery.delivered_at,
+        });
+    }
+
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod rest_api_tests {
+    use axum::body::Body;
+    use axum::extract::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::db::Repo;
+    use crate::server::{app_with_guard, WebhookGuard};
+    use crate::update_handler::dispatcher::UpdateDispatcher;
+
+    async fn call(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        key: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+
+        if let Some(key) = key {
+            request = request.header(API_KEY_HEADER, key);
+        }
+
+        let request = match body {
+            Some(body) => request
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_rest_api() {
+        // The router needs it for as long as the app lives
+        let repo: &'static Repo =
+            Box::leak(Box::new(Repo::new_for_test("test_rest_api").await.unwrap()));
+        let queue = repo.create_testing_queue(false).await.unwrap();
+        let app = app_with_guard(
+            queue,
+            repo,
+            UpdateDispatcher::start(),
+            WebhookGuard::builder().secret_token("s3cr3t").build(),
+        );
+
+        let (_, key) = repo.create_api_key("Ayuntamiento").await.unwrap();
+        let key = Some(key.as_str());
+
+        let (status, _) = call(&app, "GET", "/api/v1/plates/1234BCD", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = call(
+            &app,
+            "GET",
+            "/api/v1/plates/1234BCD",
+            Some("tcd_nope"),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(&app, "GET", "/api/v1/plates/hola", key, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+        let (status, body) = call(&app, "GET", "/api/v1/plates/1234%20bcd", key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["plate"], "1234BCD");
+        assert_eq!(body["status"], "unchecked");
+
+        let new = json!({ "plate": "1234-BCD", "reference": "expediente 42" });
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/api/v1/subscriptions",
+            key,
+            Some(new.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        assert_eq!(body["plate"], "1234BCD");
+        assert_eq!(body["reference"], "expediente 42");
+
+        let (status, _) = call(&app, "POST", "/api/v1/subscriptions", key, Some(new)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // API subscriptions are swept too
+        let pending = repo.get_pending_vehicles().await.unwrap();
+        assert!(pending.iter().any(|vehicle| vehicle.plate == "1234BCD"));
+
+        let (status, body) = call(&app, "GET", "/api/v1/vehicles/found", key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([]));
+
+        repo.modify_found_at_vehicle("1234BCD", Utc::now())
+            .await
+            .unwrap();
+
+        let (status, body) = call(&app, "GET", "/api/v1/vehicles/found", key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["plate"], "1234BCD");
+        assert_eq!(body[0]["status"], "found");
+
+        let (status, body) = call(
+            &app,
+            "GET",
+            "/api/v1/vehicles/found?since=2999-01-01T00:00:00Z",
+            key,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([]));
+
+        let (status, _) = call(&app, "DELETE", "/api/v1/subscriptions/1234BCD", key, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, _) = call(&app, "DELETE", "/api/v1/subscriptions/1234BCD", key, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = call(&app, "GET", "/api/v1/openapi.yaml", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        repo.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rest_api_webhooks() {
+        let repo: &'static Repo = Box::leak(Box::new(
+            Repo::new_for_test("test_rest_api_webhooks").await.unwrap(),
+        ));
+        let queue = repo.create_testing_queue(false).await.unwrap();
+        let app = app_with_guard(
+            queue,
+            repo,
+            UpdateDispatcher::start(),
+            WebhookGuard::builder().secret_token("s3cr3t").build(),
+        );
+
+        let (_, key) = repo.create_api_key("Ayuntamiento").await.unwrap();
+        let key = Some(key.as_str());
+        let (_, other_key) = repo.create_api_key("Aseguradora").await.unwrap();
+        let other_key = Some(other_key.as_str());
+
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/api/v1/webhooks",
+            key,
+            Some(json!({ "url": "ftp://example.com" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/api/v1/webhooks",
+            key,
+            Some(json!({ "url": "http://169.254.169.254/latest/meta-data" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            body["error"].as_str().unwrap().contains("internal"),
+            "{body}"
+        );
+
+        let (status, _) = call(
+            &app,
+            "POST",
+            "/api/v1/webhooks",
+            key,
+            Some(json!({ "url": "https://example.com/hooks", "plates": ["hola"] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/api/v1/webhooks",
+            key,
+            Some(json!({ "url": "https://example.com/hooks", "plates": ["1234-bcd"] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        assert_eq!(body["plates"], json!(["1234BCD"]));
+        assert_eq!(body["secret"].as_str().unwrap().len(), 64);
+        let id = body["id"].as_i64().unwrap();
+
+        let (status, body) = call(&app, "GET", "/api/v1/webhooks", key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["id"], id);
+        assert!(body[0].get("secret").is_none());
+
+        let (status, body) = call(&app, "GET", "/api/v1/webhooks", other_key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([]));
+
+        let uri = format!("/api/v1/webhooks/{id}/deliveries");
+        let (status, body) = call(&app, "GET", &uri, key, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!([]));
+
+        let (status, _) = call(&app, "GET", &uri, other_key, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let uri = format!("/api/v1/webhooks/{id}");
+        let (status, _) = call(&app, "DELETE", &uri, other_key, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = call(&app, "DELETE", &uri, key, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, _) = call(&app, "DELETE", &uri, key, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        repo.cleanup_test_db().await.unwrap();
+    }
+
+    #[test]
+    fn test_check_webhook_url() {
+        for url in [
+            "https://example.com/hooks",
+            "http://93.184.216.34:8080/hooks",
+            "https://[2606:2800:220:1::]/hooks",
+        ] {
+            assert!(check_webhook_url(url).is_ok(), "{url}");
+        }
+
+        for url in ["ftp://example.com", "https://", "example.com/hooks"] {
+            assert!(
+                matches!(check_webhook_url(url), Err(RestError::InvalidUrl(_))),
+                "{url}"
+            );
+        }
+
+        for url in [
+            "http://localhost:3000/hooks",
+            "http://api.localhost/hooks",
+            "http://127.0.0.1/hooks",
+            "http://10.1.2.3/hooks",
+            "http://172.20.0.5/hooks",
+            "http://192.168.1.1/hooks",
+            "http://169.254.169.254/latest/meta-data",
+            "http://0.0.0.0/hooks",
+            "http://[::1]/hooks",
+            "http://[fd00::1]/hooks",
+            "http://[fe80::1]/hooks",
+            "http://[::ffff:127.0.0.1]/hooks",
+        ] {
+            assert!(
+                matches!(check_webhook_url(url), Err(RestError::InternalUrl(_))),
+                "{url}"
+            );
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use tokio::signal;
+
+use crate::{
+    db::Repo,
+    server::app,
+    telegram::client::ApiClient,
+    update_handler::{command::register_commands, dispatcher::UpdateDispatcher},
+    workers, BotError, SERVER_PORT, WEBHOOK_CERT, WEBHOOK_PORT, WEBHOOK_SECRET_TOKEN, WEBHOOK_URL,
+};
+
+/// How the bot receives the updates from Telegram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeMode {
+    /// Telegram pushes the updates to the HTTP server, needs public TLS
+    Webhook,
+    /// The bot asks for the updates with `getUpdates`, handy to run it locally
+    Polling,
+}
+
+impl FromStr for RuntimeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "webhook" => Ok(RuntimeMode::Webhook),
+            "polling" => Ok(RuntimeMode::Polling),
+            other => Err(format!("Unknown runtime mode '{other}'")),
+        }
+    }
+}
+
+impl RuntimeMode {
+    /// Starts the workers and the HTTP server, then receives updates until the process is asked to stop.
+    ///
+    /// Health, metrics and the partner API are served in both modes
+    pub async fn run(self) -> Result<(), BotError> {
+        let queue = workers::start_workers().await.map_err(|err| {
+            BotError::StartupError(format!("the workers didn't start: {}", err.description))
+        })?;
+
+        log::info!("Running in {:?} mode", self);
+
+        if let Err(err) = register_commands(ApiClient::api_client().await).await {
+            log::error!("Failed to register the commands in Telegram: {:?}", err);
+        }
+
+        let dispatcher = UpdateDispatcher::start();
+
+        // Http Server
+        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", *SERVER_PORT))
+            .await
+            .map_err(|err| {
+                BotError::StartupError(format!("port {} can't be bound: {err}", *SERVER_PORT))
+            })?;
+        log::info!("listening on {}", listener.local_addr()?);
+        // The source address is needed by the IP allowlist
+        let repo = Repo::repo().await.map_err(|err| {
+            BotError::StartupError(format!("the database isn't available: {err}"))
+        })?;
+        let service = app(queue, repo, dispatcher.clone())
+            .into_make_service_with_connect_info::<SocketAddr>();
+        let server = axum::serve(listener, service).with_graceful_shutdown(shutdown_signal());
+
+        match self {
+            RuntimeMode::Webhook => {
+                set_webhook().await?;
+                server.await?;
+            }
+            RuntimeMode::Polling => {
+                // Updates left in the `getUpdates` buffer aren't confirmed, Telegram sends them again
+                tokio::select! {
+                    result = server => result?,
+                    _ = poll_updates(&dispatcher) => (),
+                }
+            }
+        }
+
+        // The updates already acknowledged are only in memory
+        dispatcher.drain().await;
+
+        log::info!("Bot stopped");
+        Ok(())
+    }
+}
+
+/// Wait between the attempts to remove the webhook before polling
+const REMOVE_WEBHOOK_RETRY: Duration = Duration::from_secs(5);
+
+/// URL registered in Telegram for the webhook mode
+pub fn webhook_url() -> String {
+    match *WEBHOOK_PORT {
+        443 | 80 => format!("{}/webhook", *WEBHOOK_URL), //Debe estar bien formateado (http o https)
+        _ => format!("{}:{}/webhook", *WEBHOOK_URL, *WEBHOOK_PORT),
+    }
+}
+
+/// Telegram refusing the URL is only logged, the server still answers health checks
+async fn set_webhook() -> Result<(), BotError> {
+    let telegram = ApiClient::api_client().await;
+    let webhook = webhook_url();
+    let response = telegram
+        .set_webhook(
+            &webhook,
+            None,
+            WEBHOOK_CERT.clone(),
+            WEBHOOK_SECRET_TOKEN.as_str(),
+        )
+        .await
+        .map_err(|err| {
+            BotError::StartupError(format!("the webhook {webhook} couldn't be set: {err}"))
+        })?;
+    if response.ok && response.result {
+        log::info!("Setted Telegram webhook at URL {}", webhook);
+    } else {
+        log::error!("{:?}", response.description);
+    }
+    Ok(())
+}
+
+/// Receives the updates with `getUpdates` until the dispatcher stops taking them
+async fn poll_updates(dispatcher: &UpdateDispatcher) {
+    // Own client, `next_update` keeps the offset of the updates already received
+    let mut telegram = ApiClient::new().await;
+
+    // getUpdates doesn't work while a webhook is set, so it's retried until it's removed
+    loop {
+        match telegram.remove_webhook().await {
+            Ok(response) if response.ok => break,
+            Ok(response) => log::error!("Failed to remove the webhook: {:?}", response.description),
+            Err(err) => log::error!("Failed to remove the webhook: {err}"),
+        }
+        tokio::time::sleep(REMOVE_WEBHOOK_RETRY).await;
+    }
+
+    loop {
+        match telegram.next_update().await {
+            Some(update) => {
+                // `getUpdates` won't return it again, so wait instead of dropping it
+                if dispatcher.dispatch_wait(update).await.is_err() {
+                    break;
+                }
+            }
+            // Long polling returns nothing after the timeout, errors are already logged
+            None => tokio::time::sleep(Duration::from_secs(1)).await,
+        }
+    }
+}
+
+/// Resolves on Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    log::info!("Shutting down");
+}
+
+#[cfg(test)]
+mod runtime_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_runtime_mode() {
+        assert_eq!("webhook".parse(), Ok(RuntimeMode::Webhook));
+        assert_eq!(" Polling ".parse(), Ok(RuntimeMode::Polling));
+        assert!("socket".parse::<RuntimeMode>().is_err());
+    }
+}
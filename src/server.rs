@@ -1,32 +1,202 @@
-use std::sync::Arc;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{ConnectInfo, DefaultBodyLimit, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Json, Router,
+    Router,
 };
+use bon::Builder;
+use bytes::Bytes;
 use fang::NoTls;
 
 use fang::AsyncQueue;
 use frankenstein::Update;
+use ipnet::IpNet;
+use rand::{distributions::Alphanumeric, Rng};
 use tokio::sync::Mutex;
 
-use crate::{update_handler::process_update::UpdateProcessor, BotError};
+use crate::{
+    db::Repo, health, metrics, rest_api, update_handler::dispatcher::UpdateDispatcher, BotError,
+    WEBHOOK_ALLOWED_IPS, WEBHOOK_MAX_BODY_BYTES, WEBHOOK_SECRET_TOKEN,
+};
+
+/// Header Telegram fills with the `secret_token` given to `setWebhook`
+pub const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+/// Ranges Telegram sends the webhooks from https://core.telegram.org/bots/webhooks#the-short-version
+pub const TELEGRAM_IP_RANGES: &[&str] = &["149.154.160.0/20", "91.108.4.0/22"];
+
+/// Checks every request to `/webhook` has to pass before being processed
+#[derive(Debug, Clone, Builder)]
+pub struct WebhookGuard {
+    #[builder(into)]
+    secret_token: String,
+    /// Anyone can call the webhook when empty
+    #[builder(default)]
+    allowed_ips: Vec<IpNet>,
+    #[builder(default = 262_144)]
+    max_body_bytes: usize,
+}
+
+impl WebhookGuard {
+    pub fn from_env() -> Self {
+        WebhookGuard::builder()
+            .secret_token(WEBHOOK_SECRET_TOKEN.as_str())
+            .allowed_ips(WEBHOOK_ALLOWED_IPS.clone())
+            .max_body_bytes(*WEBHOOK_MAX_BODY_BYTES)
+            .build()
+    }
+
+    fn check(&self, headers: &HeaderMap, source: Option<IpAddr>) -> Result<(), StatusCode> {
+        let token = headers
+            .get(SECRET_TOKEN_HEADER)
+            .map(|value| value.as_bytes())
+            .unwrap_or_default();
+
+        if !constant_time_eq(token, self.secret_token.as_bytes()) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        if self.allowed_ips.is_empty() {
+            return Ok(());
+        }
+
+        match source {
+            Some(ip) if self.allowed_ips.iter().any(|range| range.contains(&ip)) => Ok(()),
+            _ => Err(StatusCode::FORBIDDEN),
+        }
+    }
+}
+
+/// Valid `secret_token` for `setWebhook`: 1-256 characters among `A-Z`, `a-z`, `0-9`, `_` and `-`
+pub fn random_secret_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect()
+}
+
+/// Comma separated list of IP ranges, `telegram` stands for `TELEGRAM_IP_RANGES`
+pub fn parse_ip_ranges(ranges: &str) -> Result<Vec<IpNet>, ipnet::AddrParseError> {
+    ranges
+        .split(',')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+        .flat_map(|range| match range {
+            "telegram" => TELEGRAM_IP_RANGES.to_vec(),
+            range => vec![range],
+        })
+        .map(|range| {
+            // Single addresses are ranges too
+            range
+                .parse::<IpNet>()
+                .or_else(|err| range.parse::<IpAddr>().map(IpNet::from).map_err(|_| err))
+        })
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn app(queue: AsyncQueue<NoTls>, repo: &'static Repo, dispatcher: UpdateDispatcher) -> Router {
+    app_with_guard(queue, repo, dispatcher, WebhookGuard::from_env())
+}
+
+/// Shared by the handlers
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub queue: Arc<Mutex<AsyncQueue<NoTls>>>,
+    pub dispatcher: UpdateDispatcher,
+    pub repo: &'static Repo,
+}
+
+pub fn app_with_guard(
+    queue: AsyncQueue<NoTls>,
+    repo: &'static Repo,
+    dispatcher: UpdateDispatcher,
+    guard: WebhookGuard,
+) -> Router {
+    let max_body_bytes = guard.max_body_bytes;
+
+    let state = AppState {
+        queue: Arc::new(Mutex::new(queue)),
+        dispatcher,
+        repo,
+    };
 
-pub fn app(queue: AsyncQueue<NoTls>) -> Router {
     Router::new()
         .route("/", get(|| async { "Hello!" }))
-        .route("/webhook", post(parse_update))
-        .with_state(Arc::new(Mutex::new(queue)))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/metrics", get(render_metrics))
+        .nest("/api/v1", rest_api::router())
+        .route(
+            "/webhook",
+            post(parse_update)
+                .layer(DefaultBodyLimit::max(max_body_bytes))
+                .route_layer(middleware::from_fn_with_state(guard, verify_webhook)),
+        )
+        .with_state(state)
 }
-async fn parse_update(
-    State(state): State<Arc<Mutex<AsyncQueue<NoTls>>>>,
-    Json(update): Json<Update>,
-) -> axum::response::Result<()> {
-    UpdateProcessor::run(&update, state).await.unwrap();
-    Ok(())
+
+async fn verify_webhook(
+    State(guard): State<WebhookGuard>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let source = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Err(status) = guard.check(request.headers(), source) {
+        log::warn!("Rejected webhook request from {source:?}: {status}");
+        return Err(status);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// The gauges are read from the database on each scrape
+async fn render_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    match state.repo.count_active_chats().await {
+        Ok(n) => metrics::ACTIVE_CHATS.set(n as i64),
+        Err(err) => log::error!("Failed to count the active chats: {err}"),
+    }
+    match state.repo.count_pending_vehicles().await {
+        Ok(n) => metrics::PENDING_VEHICLES.set(n as i64),
+        Err(err) => log::error!("Failed to count the pending vehicles: {err}"),
+    }
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
+
+/// Acknowledges the update right away, Telegram sends it again on any other answer
+async fn parse_update(State(state): State<AppState>, body: Bytes) -> StatusCode {
+    let update: Update = match serde_json::from_slice(&body) {
+        Ok(update) => update,
+        Err(err) => {
+            // Sending it again won't fix it
+            log::error!("Ignoring update that can't be parsed: {err}");
+            return StatusCode::OK;
+        }
+    };
+
+    match state.dispatcher.dispatch(update) {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
 }
 
 impl IntoResponse for BotError {
@@ -62,7 +232,7 @@ mod server_tests {
 
         let queue = Repo::create_testing_queue(repo, true).await.unwrap();
         // Initialize the app
-        let app = app(queue);
+        let app = app(queue, repo, UpdateDispatcher::start());
 
         let db_chat = repo.get_testing_chat().await.unwrap();
 
@@ -100,6 +270,7 @@ mod server_tests {
                     .uri("/webhook")
                     .header("Content-Type", "application/json")
                     .header("Cache-Control", "no-cache")
+                    .header(SECRET_TOKEN_HEADER, WEBHOOK_SECRET_TOKEN.as_str())
                     .body(serde_json::to_string(&update).unwrap())
                     .unwrap(),
             )
@@ -118,11 +289,10 @@ mod server_tests {
     async fn test_root_handler() {
         dotenvy::dotenv().ok();
 
-        let queue = Repo::create_testing_queue(Repo::repo().await.unwrap(), true)
-            .await
-            .unwrap();
+        let repo = Repo::repo().await.unwrap();
+        let queue = Repo::create_testing_queue(repo, true).await.unwrap();
         // Initialize the app
-        let app = app(queue);
+        let app = app(queue, repo, UpdateDispatcher::start());
 
         // Build the request with the mock IP address in the request extensions
         let request = Request::builder()
@@ -140,4 +310,139 @@ mod server_tests {
             response.body()
         );
     }
+
+    #[tokio::test]
+    async fn test_healthz() {
+        dotenvy::dotenv().ok();
+
+        let repo = Repo::repo().await.unwrap();
+        let queue = Repo::create_testing_queue(repo, true).await.unwrap();
+        let app = app_with_guard(
+            queue,
+            repo,
+            UpdateDispatcher::start(),
+            WebhookGuard::builder().secret_token("s3cr3t").build(),
+        );
+
+        let request = Request::builder()
+            .method("GET")
+            .uri("/healthz")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"status":"ok"}"#);
+    }
+
+    async fn guarded_app(guard: WebhookGuard) -> Router {
+        dotenvy::dotenv().ok();
+
+        let repo = Repo::repo().await.unwrap();
+        let queue = Repo::create_testing_queue(repo, true).await.unwrap();
+
+        app_with_guard(queue, repo, UpdateDispatcher::start(), guard)
+    }
+
+    fn webhook_request(token: Option<&str>, source: Option<&str>, body: String) -> Request {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/webhook")
+            .header("Content-Type", "application/json");
+
+        if let Some(token) = token {
+            request = request.header(SECRET_TOKEN_HEADER, token);
+        }
+
+        let mut request = request.body(Body::from(body)).unwrap();
+
+        if let Some(source) = source {
+            let addr: SocketAddr = source.parse().unwrap();
+            request.extensions_mut().insert(ConnectInfo(addr));
+        }
+
+        request
+    }
+
+    #[tokio::test]
+    async fn test_webhook_secret_token() {
+        let guard = WebhookGuard::builder().secret_token("s3cr3t").build();
+
+        for token in [None, Some("wrong"), Some("s3cr3")] {
+            let response = guarded_app(guard.clone())
+                .await
+                .oneshot(webhook_request(token, None, "{}".to_string()))
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{token:?}");
+        }
+
+        // Passes the guard, the body is not an update though so it's just ignored
+        let response = guarded_app(guard)
+            .await
+            .oneshot(webhook_request(Some("s3cr3t"), None, "{}".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_allowed_ips() {
+        let guard = WebhookGuard::builder()
+            .secret_token("s3cr3t")
+            .allowed_ips(parse_ip_ranges("telegram, 10.0.0.1").unwrap())
+            .build();
+
+        for (source, status) in [
+            (None, StatusCode::FORBIDDEN),
+            (Some("1.2.3.4:443"), StatusCode::FORBIDDEN),
+            (Some("149.154.167.220:443"), StatusCode::OK),
+            (Some("10.0.0.1:8080"), StatusCode::OK),
+        ] {
+            let response = guarded_app(guard.clone())
+                .await
+                .oneshot(webhook_request(Some("s3cr3t"), source, "{}".to_string()))
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), status, "{source:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_body_limit() {
+        let guard = WebhookGuard::builder()
+            .secret_token("s3cr3t")
+            .max_body_bytes(1024)
+            .build();
+
+        let body = format!(r#"{{"update_id": 1, "padding": "{}"}}"#, "a".repeat(2048));
+
+        let response = guarded_app(guard)
+            .await
+            .oneshot(webhook_request(Some("s3cr3t"), None, body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_parse_ip_ranges() {
+        let ranges = parse_ip_ranges("telegram,192.168.1.0/24, ::1").unwrap();
+        assert_eq!(ranges.len(), 4);
+        assert!(ranges[2].contains(&"192.168.1.20".parse::<IpAddr>().unwrap()));
+        assert!(parse_ip_ranges("").unwrap().is_empty());
+        assert!(parse_ip_ranges("not an ip").is_err());
+
+        let token = random_secret_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
 }
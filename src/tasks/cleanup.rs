@@ -0,0 +1,49 @@
+use crate::db::Repo;
+use crate::{metrics, MAX_RETRIES, TASK_NAME, UPDATE_DEDUP_HOURS};
+
+use chrono::{Duration, Utc};
+use fang::{
+    async_trait, typetag, AsyncQueueable, AsyncRunnable, Deserialize, FangError, Scheduled,
+    Serialize,
+};
+
+/// Forgets the processed updates Telegram won't deliver again
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+#[serde(crate = "fang::serde")]
+pub struct CleanupTask {}
+
+#[typetag::serde]
+#[async_trait]
+impl AsyncRunnable for CleanupTask {
+    async fn run(&self, _queueable: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+        let older_than = Utc::now() - Duration::hours(*UPDATE_DEDUP_HOURS);
+
+        let result = match Repo::repo().await {
+            Ok(repo) => repo.delete_processed_updates(older_than).await,
+            Err(err) => Err(err),
+        };
+        metrics::record_task("cleanup", &result);
+
+        log::info!("Forgot {} processed updates", result?);
+
+        Ok(())
+    }
+
+    fn uniq(&self) -> bool {
+        true
+    }
+
+    fn cron(&self) -> Option<Scheduled> {
+        Some(Scheduled::CronPattern(String::from("0 0 * * * *")))
+    }
+
+    fn task_type(&self) -> String {
+        TASK_NAME.to_string()
+    }
+    fn max_retries(&self) -> i32 {
+        *MAX_RETRIES
+    }
+    fn backoff(&self, attempt: u32) -> u32 {
+        u32::pow(2, attempt)
+    }
+}
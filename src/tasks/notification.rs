@@ -0,0 +1,405 @@
+use crate::db::model::chat_channel::NotificationChannel;
+use crate::db::model::notification::{Notification, NotificationStatus};
+use crate::db::Repo;
+use crate::notifier::{Notifiers, Recipient};
+use crate::{metrics, BotError, MAX_RETRIES, NOTIFICATION_TASK_NAME};
+
+use chrono::Utc;
+use fang::{
+    async_trait, typetag, AsyncQueueable, AsyncRunnable, Deserialize, FangError, Serialize,
+};
+
+/// Delivers one notification of the outbox
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(crate = "fang::serde")]
+pub struct NotificationTask {
+    pub id: i64,
+}
+
+#[typetag::serde]
+#[async_trait]
+impl AsyncRunnable for NotificationTask {
+    async fn run(&self, _queueable: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+        let repo = Repo::repo().await?;
+
+        let notifiers = Notifiers::notifiers().await;
+
+        let result = Self::deliver(repo, notifiers, self.id).await;
+        metrics::record_task("notification", &result);
+        result?;
+
+        Ok(())
+    }
+
+    fn uniq(&self) -> bool {
+        true
+    }
+
+    fn task_type(&self) -> String {
+        NOTIFICATION_TASK_NAME.to_string()
+    }
+    fn max_retries(&self) -> i32 {
+        *MAX_RETRIES
+    }
+    fn backoff(&self, attempt: u32) -> u32 {
+        30 * u32::pow(2, attempt)
+    }
+}
+
+impl NotificationTask {
+    /// Sends the notification through its channel if nobody else has claimed it yet.
+    ///
+    /// A failed attempt returns the error so fang retries the task,
+    /// the last one marks the notification as failed instead
+    pub async fn deliver(
+        repo: &Repo,
+        notifiers: &Notifiers<'_>,
+        id: i64,
+    ) -> Result<NotificationStatus, BotError> {
+        let Some(notification) = repo.claim_notification(id).await? else {
+            let notification = repo.get_notification(id).await?;
+            log::info!(
+                "Notification {id} is already {:?}, skipping it",
+                notification.status
+            );
+            return Ok(notification.status);
+        };
+
+        let result = Self::send(repo, notifiers, &notification).await;
+        if let Err(err) = &result {
+            // The retry has to find it pending, otherwise it would be skipped and never sent
+            log::error!("Releasing notification {id} after an error: {:?}", err);
+            repo.release_notification(id).await?;
+        }
+        result
+    }
+
+    /// Sends a claimed notification and stores the result of the attempt
+    async fn send(
+        repo: &Repo,
+        notifiers: &Notifiers<'_>,
+        notification: &Notification,
+    ) -> Result<NotificationStatus, BotError> {
+        let id = notification.id;
+        let vehicle = repo.get_vehicle(&notification.plate).await?;
+
+        let address = match notification.channel {
+            NotificationChannel::Telegram => None,
+            channel => repo
+                .get_chat_channel(&notification.chat_id, channel)
+                .await?
+                .and_then(|chat_channel| chat_channel.address),
+        };
+        let chat = repo.get_chat(&notification.chat_id).await?;
+        let recipient = Recipient {
+            chat_id: notification.chat_id,
+            address,
+            locale: chat.locale(),
+        };
+
+        let result = match notifiers.get(notification.channel) {
+            Some(notifier) => notifier.notify(&recipient, &vehicle).await,
+            None => Err(BotError::ChannelUnavailable(notification.channel)),
+        };
+
+        match result {
+            Ok(_) => {
+                repo.modify_delivered_notification(id, Utc::now()).await?;
+                metrics::NOTIFICATIONS_TOTAL
+                    .with_label_values(&["delivered"])
+                    .inc();
+                Ok(NotificationStatus::Delivered)
+            }
+            Err(err) => {
+                log::error!(
+                    "Attempt {} to notify {} about {} by {} failed: {:?}",
+                    notification.attempts,
+                    notification.chat_id,
+                    notification.plate,
+                    notification.channel.name(),
+                    err
+                );
+
+                if notification.attempts > *MAX_RETRIES {
+                    repo.modify_failed_notification(
+                        id,
+                        NotificationStatus::Failed,
+                        &err.to_string(),
+                    )
+                    .await?;
+                    metrics::NOTIFICATIONS_TOTAL
+                        .with_label_values(&["failed"])
+                        .inc();
+                    return Ok(NotificationStatus::Failed);
+                }
+
+                repo.modify_failed_notification(id, NotificationStatus::Pending, &err.to_string())
+                    .await?;
+                metrics::NOTIFICATIONS_TOTAL
+                    .with_label_values(&["retrying"])
+                    .inc();
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod notification_task_tests {
+
+    use super::*;
+    use crate::notifier::{notifier_tests::smtp_sink, EmailNotifier, TelegramNotifier};
+    use crate::telegram::client::ApiClient;
+
+    #[tokio::test]
+    async fn test_deliver_once() {
+        let db_controller = Repo::new_for_test("test_deliver_once").await.unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        // Chat 1 is subscribed to ABC123 and DEF456, chat 2 to DEF456
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+        db_controller.modify_active_chat(&2, true).await.unwrap();
+
+        let found_at = Utc::now();
+        db_controller
+            .modify_found_at_vehicle("DEF456", found_at)
+            .await
+            .unwrap();
+
+        let ids = db_controller
+            .create_found_notifications("DEF456", found_at)
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 2);
+
+        // The same event is only queued once
+        let again = db_controller
+            .create_found_notifications("DEF456", found_at)
+            .await
+            .unwrap();
+        assert!(again.is_empty());
+
+        let sent = server
+            .mock("POST", "/sendMessage")
+            .with_status(200)
+            .with_body(
+                r#"{"ok": true, "result": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let telegram = ApiClient::new_url(server.url()).await;
+        let notifiers = Notifiers::new(vec![Box::new(TelegramNotifier::new(&telegram))]);
+
+        for id in ids.iter() {
+            let status = NotificationTask::deliver(&db_controller, &notifiers, *id)
+                .await
+                .unwrap();
+            assert_eq!(status, NotificationStatus::Delivered);
+        }
+
+        // Delivering again doesn't send anything
+        let status = NotificationTask::deliver(&db_controller, &notifiers, ids[0])
+            .await
+            .unwrap();
+        assert_eq!(status, NotificationStatus::Delivered);
+
+        sent.assert_async().await;
+
+        let notification = db_controller.get_notification(ids[0]).await.unwrap();
+        assert_eq!(notification.attempts, 1);
+        assert!(notification.delivered_at.is_some());
+        assert!(db_controller
+            .get_pending_notifications()
+            .await
+            .unwrap()
+            .is_empty());
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries() {
+        let db_controller = Repo::new_for_test("test_deliver_retries").await.unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+
+        let found_at = Utc::now();
+        let ids = db_controller
+            .create_found_notifications("ABC123", found_at)
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let _blocked = server
+            .mock("POST", "/sendMessage")
+            .with_status(403)
+            .with_body(r#"{"ok": false, "error_code": 403, "description": "Forbidden: bot was blocked by the user"}"#)
+            .create_async()
+            .await;
+
+        let telegram = ApiClient::new_url(server.url()).await;
+        let notifiers = Notifiers::new(vec![Box::new(TelegramNotifier::new(&telegram))]);
+
+        // Every attempt but the last one goes back to pending so fang retries it
+        for attempt in 1..=*MAX_RETRIES {
+            let result = NotificationTask::deliver(&db_controller, &notifiers, ids[0]).await;
+            assert!(result.is_err(), "attempt {attempt}: {result:?}");
+
+            let notification = db_controller.get_notification(ids[0]).await.unwrap();
+            assert_eq!(notification.status, NotificationStatus::Pending);
+            assert_eq!(notification.attempts, attempt);
+            assert!(notification.last_error.is_some());
+        }
+
+        let status = NotificationTask::deliver(&db_controller, &notifiers, ids[0])
+            .await
+            .unwrap();
+        assert_eq!(status, NotificationStatus::Failed);
+
+        let notification = db_controller.get_notification(ids[0]).await.unwrap();
+        assert_eq!(notification.attempts, *MAX_RETRIES + 1);
+        assert!(notification.delivered_at.is_none());
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_release_and_recover_claims() {
+        let db_controller = Repo::new_for_test("test_release_and_recover_claims")
+            .await
+            .unwrap();
+
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+        let ids = db_controller
+            .create_found_notifications("ABC123", Utc::now())
+            .await
+            .unwrap();
+        let id = ids[0];
+
+        // A claimed notification can't be claimed twice
+        assert!(db_controller
+            .claim_notification(id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(db_controller
+            .claim_notification(id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db_controller
+            .get_pending_notifications()
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Released after an error it can be claimed by the retry
+        assert_eq!(db_controller.release_notification(id).await.unwrap(), 1);
+        let notification = db_controller.get_notification(id).await.unwrap();
+        assert_eq!(notification.status, NotificationStatus::Pending);
+        assert!(db_controller
+            .claim_notification(id)
+            .await
+            .unwrap()
+            .is_some());
+
+        // The claim of a worker that died is taken over once it's stale
+        let connection = db_controller.get_connection().get().await.unwrap();
+        connection
+            .execute(
+                "UPDATE notifications SET claimed_at = NOW() - INTERVAL '1 hour' WHERE id = $1",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        drop(connection);
+
+        let pending = db_controller.get_pending_notifications().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        let notification = db_controller.claim_notification(id).await.unwrap().unwrap();
+        assert_eq!(notification.attempts, 3);
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deliver_every_channel() {
+        let db_controller = Repo::new_for_test("test_deliver_every_channel")
+            .await
+            .unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let (url, emails) = smtp_sink().await;
+
+        // Chat 1 adds an email, chat 2 only wants emails but has no address
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+        db_controller.modify_active_chat(&2, true).await.unwrap();
+        db_controller
+            .modify_chat_channel(
+                &1,
+                NotificationChannel::Email,
+                Some("familia@example.com"),
+                true,
+            )
+            .await
+            .unwrap();
+        db_controller
+            .modify_chat_channel(&2, NotificationChannel::Telegram, None, false)
+            .await
+            .unwrap();
+
+        let found_at = Utc::now();
+        let ids = db_controller
+            .create_found_notifications("DEF456", found_at)
+            .await
+            .unwrap();
+
+        let mut channels = vec![];
+        for id in ids.iter() {
+            let notification = db_controller.get_notification(*id).await.unwrap();
+            channels.push((notification.chat_id, notification.channel));
+        }
+        channels.sort_by_key(|(chat_id, channel)| (*chat_id, channel.name()));
+        assert_eq!(
+            channels,
+            vec![
+                (1, NotificationChannel::Email),
+                (1, NotificationChannel::Telegram)
+            ]
+        );
+
+        let sent = server
+            .mock("POST", "/sendMessage")
+            .with_status(200)
+            .with_body(
+                r#"{"ok": true, "result": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let telegram = ApiClient::new_url(server.url()).await;
+        let email = EmailNotifier::new(&url, "avisos@example.com").unwrap();
+        let notifiers = Notifiers::new(vec![
+            Box::new(TelegramNotifier::new(&telegram)),
+            Box::new(email),
+        ]);
+
+        for id in ids.iter() {
+            let status = NotificationTask::deliver(&db_controller, &notifiers, *id)
+                .await
+                .unwrap();
+            assert_eq!(status, NotificationStatus::Delivered);
+        }
+
+        sent.assert_async().await;
+        let emails = emails.lock().unwrap().clone();
+        assert_eq!(emails.len(), 1);
+        assert!(emails[0].contains("To: familia@example.com"));
+        assert!(emails[0].contains("DEF456"));
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+}
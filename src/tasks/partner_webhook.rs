@@ -0,0 +1,542 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::model::notification::NotificationStatus;
+use crate::db::model::partner_webhook::WebhookDelivery;
+use crate::db::model::vehicle::Vehicle;
+use crate::db::Repo;
+use crate::rest_api::{check_webhook_url, is_internal_ip, PlateStatus};
+use crate::{metrics, BotError, NOTIFICATION_TASK_NAME, PARTNER_WEBHOOK_MAX_RETRIES};
+
+use chrono::Utc;
+use fang::{
+    async_trait, typetag, AsyncQueueable, AsyncRunnable, Deserialize, FangError, Serialize,
+};
+use frankenstein::reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Client, ClientBuilder,
+};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+
+/// `sha256=` followed by the hex HMAC of `<timestamp>.<body>` with the secret of the webhook
+pub const SIGNATURE_HEADER: &str = "X-TuCocheDana-Signature";
+/// Unix timestamp of the request, part of the signature so it can't be replayed later
+pub const TIMESTAMP_HEADER: &str = "X-TuCocheDana-Timestamp";
+pub const EVENT_HEADER: &str = "X-TuCocheDana-Event";
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events the partners are told about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VehicleEvent {
+    Found,
+    /// The lookup result changed, i.e. the first time a plate is checked
+    StatusChanged,
+}
+
+impl VehicleEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VehicleEvent::Found => "vehicle.found",
+            VehicleEvent::StatusChanged => "vehicle.status_changed",
+        }
+    }
+}
+
+/// Signature of the payload sent in `SIGNATURE_HEADER`
+pub fn sign(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{timestamp}.{payload}").as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Resolves the hosts of the webhooks leaving out the internal addresses, the URLs are
+/// checked when registered but their names can point anywhere later
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| !is_internal_ip(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// POSTs one event to a partner webhook
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(crate = "fang::serde")]
+pub struct WebhookDeliveryTask {
+    pub id: i64,
+}
+
+#[typetag::serde]
+#[async_trait]
+impl AsyncRunnable for WebhookDeliveryTask {
+    async fn run(&self, _queueable: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+        let repo = Repo::repo().await?;
+
+        let client = Self::client()?;
+
+        let result = Self::deliver(repo, &client, self.id).await;
+        metrics::record_task("partner_webhook", &result);
+        result?;
+
+        Ok(())
+    }
+
+    fn uniq(&self) -> bool {
+        true
+    }
+
+    fn task_type(&self) -> String {
+        NOTIFICATION_TASK_NAME.to_string()
+    }
+    fn max_retries(&self) -> i32 {
+        *PARTNER_WEBHOOK_MAX_RETRIES
+    }
+    fn backoff(&self, attempt: u32) -> u32 {
+        30 * u32::pow(2, attempt)
+    }
+}
+
+impl WebhookDeliveryTask {
+    pub fn client() -> Result<Client, BotError> {
+        Ok(Self::client_builder().build()?)
+    }
+
+    /// Redirects aren't followed, they could point to an internal address
+    fn client_builder() -> ClientBuilder {
+        Client::builder()
+            .timeout(TIMEOUT)
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+    }
+
+    /// Queues the event for the webhooks of the partners that registered the vehicle
+    pub async fn queue_event(
+        repo: &Repo,
+        event: VehicleEvent,
+        vehicle: &Vehicle,
+        previous_status: Option<&str>,
+    ) -> Result<Vec<i64>, BotError> {
+        let payload = json!({
+            "event": event.name(),
+            "occurred_at": Utc::now(),
+            "previous_status": previous_status,
+            "vehicle": PlateStatus::from(vehicle.clone()),
+        })
+        .to_string();
+
+        Ok(repo
+            .create_webhook_deliveries(&vehicle.plate, event.name(), &payload)
+            .await?)
+    }
+
+    /// Sends the delivery if nobody else is sending it, every request is recorded.
+    ///
+    /// A failed attempt returns the error so fang retries the task,
+    /// the last one marks the delivery as failed instead
+    pub async fn deliver(
+        repo: &Repo,
+        client: &Client,
+        id: i64,
+    ) -> Result<NotificationStatus, BotError> {
+        let Some(delivery) = repo.claim_webhook_delivery(id).await? else {
+            let delivery = repo.get_webhook_delivery(id).await?;
+            log::info!(
+                "Webhook delivery {id} is already {:?}, skipping it",
+                delivery.status
+            );
+            return Ok(delivery.status);
+        };
+
+        let result = Self::send(repo, client, &delivery).await;
+        if let Err(err) = &result {
+            // The retry has to find it pending, otherwise it would be skipped and never sent
+            log::error!("Releasing webhook delivery {id} after an error: {:?}", err);
+            repo.release_webhook_delivery(id).await?;
+        }
+        result
+    }
+
+    /// Sends a claimed delivery and records the attempt
+    async fn send(
+        repo: &Repo,
+        client: &Client,
+        delivery: &WebhookDelivery,
+    ) -> Result<NotificationStatus, BotError> {
+        let id = delivery.id;
+        let webhook = repo.get_partner_webhook(delivery.webhook_id).await?;
+
+        let timestamp = Utc::now().timestamp();
+        let signature = sign(&webhook.secret, timestamp, &delivery.payload);
+
+        // IP addresses aren't resolved, so they are checked before connecting
+        let result = match check_webhook_url(&webhook.url) {
+            Ok(()) => client
+                .post(&webhook.url)
+                .header("Content-Type", "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(EVENT_HEADER, &delivery.event)
+                .body(delivery.payload.clone())
+                .send()
+                .await
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                repo.modify_webhook_delivery(
+                    id,
+                    NotificationStatus::Delivered,
+                    Some(i32::from(response.status().as_u16())),
+                    None,
+                )
+                .await?;
+                metrics::NOTIFICATIONS_TOTAL
+                    .with_label_values(&["webhook_delivered"])
+                    .inc();
+                return Ok(NotificationStatus::Delivered);
+            }
+            Ok(response) => (
+                Some(i32::from(response.status().as_u16())),
+                format!("Answered {}", response.status()),
+            ),
+            Err(error) => (None, error),
+        };
+
+        log::error!(
+            "Attempt {} to deliver {} to webhook {} failed: {error}",
+            delivery.attempts,
+            delivery.event,
+            webhook.id
+        );
+
+        if delivery.attempts > *PARTNER_WEBHOOK_MAX_RETRIES {
+            repo.modify_webhook_delivery(
+                id,
+                NotificationStatus::Failed,
+                response_status,
+                Some(&error),
+            )
+            .await?;
+            metrics::NOTIFICATIONS_TOTAL
+                .with_label_values(&["webhook_failed"])
+                .inc();
+            return Ok(NotificationStatus::Failed);
+        }
+
+        repo.modify_webhook_delivery(
+            id,
+            NotificationStatus::Pending,
+            response_status,
+            Some(&error),
+        )
+        .await?;
+        metrics::NOTIFICATIONS_TOTAL
+            .with_label_values(&["webhook_retrying"])
+            .inc();
+
+        Err(BotError::WebhookDeliveryError(id, error))
+    }
+}
+
+#[cfg(test)]
+mod partner_webhook_tests {
+
+    use super::*;
+
+    /// Host of the partner in the tests, `mockito` listens on a loopback address
+    const PARTNER_HOST: &str = "partner.example";
+
+    fn test_client(server: &mockito::Server) -> Client {
+        WebhookDeliveryTask::client_builder()
+            .resolve(PARTNER_HOST, server.socket_address())
+            .build()
+            .unwrap()
+    }
+
+    fn partner_url(server: &mockito::Server, path: &str) -> String {
+        format!(
+            "http://{PARTNER_HOST}:{}{path}",
+            server.socket_address().port()
+        )
+    }
+
+    #[test]
+    fn test_sign() {
+        // echo -n '1700000000.{"a":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000, r#"{"a":1}"#),
+            "sha256=49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_to_partner() {
+        let db_controller = Repo::new_for_test("test_deliver_to_partner").await.unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        let (api_key, _) = db_controller.create_api_key("Ayuntamiento").await.unwrap();
+        db_controller
+            .create_api_subscription(api_key.id, "ABC123", None)
+            .await
+            .unwrap();
+
+        let url = partner_url(&server, "/hooks");
+        let webhook = db_controller
+            .create_partner_webhook(api_key.id, &url, None)
+            .await
+            .unwrap();
+        // Only interested in another plate
+        let other = db_controller
+            .create_partner_webhook(api_key.id, &url, Some(&["DEF456".to_string()]))
+            .await
+            .unwrap();
+
+        db_controller
+            .modify_found_at_vehicle("ABC123", Utc::now())
+            .await
+            .unwrap();
+        let vehicle = db_controller.get_vehicle("ABC123").await.unwrap();
+
+        let ids = WebhookDeliveryTask::queue_event(
+            &db_controller,
+            VehicleEvent::Found,
+            &vehicle,
+            Some("not_found"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let delivery = db_controller.get_webhook_delivery(ids[0]).await.unwrap();
+        assert_eq!(delivery.webhook_id, webhook.id);
+        assert_ne!(delivery.webhook_id, other.id);
+
+        let payload: serde_json::Value = serde_json::from_str(&delivery.payload).unwrap();
+        assert_eq!(payload["event"], "vehicle.found");
+        assert_eq!(payload["vehicle"]["plate"], "ABC123");
+
+        let failing = server
+            .mock("POST", "/hooks")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+
+        let result = WebhookDeliveryTask::deliver(&db_controller, &client, ids[0]).await;
+        assert!(result.is_err(), "{result:?}");
+        failing.assert_async().await;
+        failing.remove_async().await;
+
+        let delivered = server
+            .mock("POST", "/hooks")
+            .match_header(EVENT_HEADER, "vehicle.found")
+            .match_header(
+                TIMESTAMP_HEADER,
+                mockito::Matcher::Regex(r"^\d+$".to_string()),
+            )
+            .match_header(
+                SIGNATURE_HEADER,
+                mockito::Matcher::Regex(r"^sha256=[0-9a-f]{64}$".to_string()),
+            )
+            .match_body(delivery.payload.as_str())
+            .with_status(204)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let status = WebhookDeliveryTask::deliver(&db_controller, &client, ids[0])
+            .await
+            .unwrap();
+        assert_eq!(status, NotificationStatus::Delivered);
+        delivered.assert_async().await;
+
+        // Never sent twice
+        let status = WebhookDeliveryTask::deliver(&db_controller, &client, ids[0])
+            .await
+            .unwrap();
+        assert_eq!(status, NotificationStatus::Delivered);
+
+        let attempts = db_controller.get_webhook_attempts(ids[0]).await.unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].response_status, Some(500));
+        assert!(attempts[0].error.is_some());
+        assert_eq!(attempts[1].response_status, Some(204));
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refuse_internal_addresses() {
+        let db_controller = Repo::new_for_test("test_refuse_internal_addresses")
+            .await
+            .unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        let (api_key, _) = db_controller.create_api_key("Ayuntamiento").await.unwrap();
+        db_controller
+            .create_api_subscription(api_key.id, "ABC123", None)
+            .await
+            .unwrap();
+        // Public name that redirects to the loopback
+        let redirecting = db_controller
+            .create_partner_webhook(api_key.id, &partner_url(&server, "/redirect"), None)
+            .await
+            .unwrap();
+        // Stored without being checked
+        let internal = db_controller
+            .create_partner_webhook(api_key.id, &format!("{}/hooks", server.url()), None)
+            .await
+            .unwrap();
+
+        let vehicle = db_controller.get_vehicle("ABC123").await.unwrap();
+        WebhookDeliveryTask::queue_event(&db_controller, VehicleEvent::Found, &vehicle, None)
+            .await
+            .unwrap();
+        let deliveries = db_controller
+            .get_pending_webhook_deliveries()
+            .await
+            .unwrap();
+        assert_eq!(deliveries.len(), 2);
+
+        let redirect = server
+            .mock("POST", "/redirect")
+            .with_status(302)
+            .with_header("Location", &format!("{}/hooks", server.url()))
+            .expect(1)
+            .create_async()
+            .await;
+        let hooks = server
+            .mock("POST", "/hooks")
+            .with_status(204)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = test_client(&server);
+        for delivery in &deliveries {
+            let result = WebhookDeliveryTask::deliver(&db_controller, &client, delivery.id).await;
+            assert!(result.is_err(), "{result:?}");
+        }
+        redirect.assert_async().await;
+        hooks.assert_async().await;
+
+        for delivery in deliveries {
+            let attempts = db_controller
+                .get_webhook_attempts(delivery.id)
+                .await
+                .unwrap();
+            if delivery.webhook_id == redirecting.id {
+                assert_eq!(attempts[0].response_status, Some(302));
+            } else {
+                assert_eq!(delivery.webhook_id, internal.id);
+                assert_eq!(attempts[0].response_status, None);
+            }
+        }
+
+        // Names resolving to internal addresses are refused too
+        let resolved = PublicResolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .map(|addrs| addrs.collect::<Vec<_>>());
+        assert!(resolved.is_err(), "{resolved:?}");
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_release_and_recover_delivery_claims() {
+        let db_controller = Repo::new_for_test("test_release_and_recover_delivery_claims")
+            .await
+            .unwrap();
+
+        let (api_key, _) = db_controller.create_api_key("Ayuntamiento").await.unwrap();
+        db_controller
+            .create_api_subscription(api_key.id, "ABC123", None)
+            .await
+            .unwrap();
+        db_controller
+            .create_partner_webhook(api_key.id, "https://example.com/hooks", None)
+            .await
+            .unwrap();
+
+        let vehicle = db_controller.get_vehicle("ABC123").await.unwrap();
+        let ids =
+            WebhookDeliveryTask::queue_event(&db_controller, VehicleEvent::Found, &vehicle, None)
+                .await
+                .unwrap();
+        let id = ids[0];
+
+        // A claimed delivery can't be claimed twice
+        assert!(db_controller
+            .claim_webhook_delivery(id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(db_controller
+            .claim_webhook_delivery(id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db_controller
+            .get_pending_webhook_deliveries()
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Released after an error it can be claimed by the retry
+        assert_eq!(db_controller.release_webhook_delivery(id).await.unwrap(), 1);
+        let delivery = db_controller.get_webhook_delivery(id).await.unwrap();
+        assert_eq!(delivery.status, NotificationStatus::Pending);
+        assert!(db_controller
+            .claim_webhook_delivery(id)
+            .await
+            .unwrap()
+            .is_some());
+
+        // The claim of a worker that died is taken over once it's stale
+        let connection = db_controller.get_connection().get().await.unwrap();
+        connection
+            .execute(
+                "UPDATE webhook_deliveries SET claimed_at = NOW() - INTERVAL '1 hour' WHERE id = $1",
+                &[&id],
+            )
+            .await
+            .unwrap();
+        drop(connection);
+
+        let pending = db_controller
+            .get_pending_webhook_deliveries()
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        let delivery = db_controller
+            .claim_webhook_delivery(id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivery.attempts, 3);
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+use std::time::Duration;
+
+use crate::db::model::vehicle::Vehicle;
+use crate::db::Repo;
+use crate::tasks::notification::NotificationTask;
+use crate::tasks::partner_webhook::{VehicleEvent, WebhookDeliveryTask};
+
+use crate::tucochedana::client::TuCocheDanaClient;
+use crate::tucochedana::lookup::LookupOutcome;
+use crate::{
+    metrics, BotError, FETCH_IN_MINUTES, MAX_RETRIES, SWEEP_CONCURRENCY, SWEEP_JITTER_MS, TASK_NAME,
+};
+
+use chrono::Utc;
+use fang::{
+    async_trait, typetag, AsyncQueueable, AsyncRunnable, Deserialize, FangError, Scheduled,
+    Serialize,
+};
+use futures::future::join_all;
+use rand::Rng;
+
+/// Looks up every vehicle that active chats are waiting for
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+#[serde(crate = "fang::serde")]
+pub struct SweepTask {}
+
+#[typetag::serde]
+#[async_trait]
+impl AsyncRunnable for SweepTask {
+    async fn run(&self, queueable: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+        let result = self.sweep_and_notify(queueable).await;
+        metrics::record_task("sweep", &result);
+        result
+    }
+
+    fn uniq(&self) -> bool {
+        true //Solo una tarea para todos los vehículos
+    }
+
+    fn cron(&self) -> Option<Scheduled> {
+        let expression = format!("0 */{} * * * *", *FETCH_IN_MINUTES);
+        Some(Scheduled::CronPattern(expression))
+    }
+
+    fn task_type(&self) -> String {
+        TASK_NAME.to_string()
+    }
+    fn max_retries(&self) -> i32 {
+        *MAX_RETRIES
+    }
+    fn backoff(&self, attempt: u32) -> u32 {
+        u32::pow(2, attempt)
+    }
+}
+
+impl SweepTask {
+    /// Sweeps and queues the notifications of the vehicles found
+    async fn sweep_and_notify(&self, queueable: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+        let repo = Repo::repo().await?;
+
+        let tu_coche_dana = TuCocheDanaClient::new(None).await;
+
+        // The notifications are stored along with the vehicles found
+        Self::sweep(repo, &tu_coche_dana).await?;
+
+        // Also picks up the notifications left behind by a previous run
+        for notification in repo.get_pending_notifications().await? {
+            queueable
+                .insert_task(&NotificationTask {
+                    id: notification.id,
+                })
+                .await?;
+        }
+
+        for delivery in repo.get_pending_webhook_deliveries().await? {
+            queueable
+                .insert_task(&WebhookDeliveryTask { id: delivery.id })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the pending vehicles in batches of `SWEEP_CONCURRENCY` lookups
+    /// and returns the ones that have been found
+    pub async fn sweep(repo: &Repo, client: &TuCocheDanaClient) -> Result<Vec<Vehicle>, BotError> {
+        Self::sweep_in_batches(repo, client, *SWEEP_CONCURRENCY).await
+    }
+
+    /// An error stops the sweep, what the previous vehicles changed is kept
+    async fn sweep_in_batches(
+        repo: &Repo,
+        client: &TuCocheDanaClient,
+        batch_size: usize,
+    ) -> Result<Vec<Vehicle>, BotError> {
+        let vehicles = repo.get_pending_vehicles().await?;
+        log::info!("Sweeping {} vehicles", vehicles.len());
+
+        let mut found = vec![];
+
+        for batch in vehicles.chunks(batch_size.max(1)) {
+            let outcomes = join_all(batch.iter().map(|vehicle| Self::check(client, vehicle))).await;
+
+            let mut rate_limited = false;
+
+            for (vehicle, outcome) in batch.iter().zip(outcomes) {
+                let plate = vehicle.plate.as_str();
+                let checked_at = Utc::now();
+                // Errors would look like changes of the status when the next lookup works
+                let status = (!outcome.is_transient()).then(|| outcome.status());
+                repo.modify_checked_vehicle(plate, checked_at, status)
+                    .await?;
+
+                let previous_status = vehicle.check_status.as_deref();
+                let mut vehicle = vehicle.clone();
+                vehicle.checked_at = Some(checked_at);
+                if let Some(status) = status {
+                    vehicle.check_status = Some(status.to_string());
+                }
+
+                // Failed lookups are only logged, they are retried in the next sweep
+                match outcome {
+                    LookupOutcome::Found(details) => {
+                        metrics::VEHICLES_FOUND_TOTAL.inc();
+                        repo.modify_found_vehicle(plate, details.found_at, &details.details)
+                            .await?;
+                        vehicle.found_at = Some(details.found_at);
+                        vehicle.details = details.details;
+                        WebhookDeliveryTask::queue_event(
+                            repo,
+                            VehicleEvent::Found,
+                            &vehicle,
+                            previous_status,
+                        )
+                        .await?;
+                        found.push(vehicle);
+                    }
+                    LookupOutcome::NotFound if previous_status != Some("not_found") => {
+                        WebhookDeliveryTask::queue_event(
+                            repo,
+                            VehicleEvent::StatusChanged,
+                            &vehicle,
+                            previous_status,
+                        )
+                        .await?;
+                    }
+                    LookupOutcome::NotFound => (),
+                    LookupOutcome::RateLimited { retry_after } => {
+                        log::warn!(
+                            "Rate limited while fetching {plate}, retry after {retry_after:?}"
+                        );
+                        rate_limited = true;
+                    }
+                    LookupOutcome::UpstreamError { status, reason } => {
+                        log::error!("Failed to fetch {plate}: {status:?} {reason}");
+                    }
+                    LookupOutcome::Malformed(body) => {
+                        log::error!("Unexpected response while fetching {plate}: {body}");
+                    }
+                }
+            }
+
+            // The rest of the vehicles will be checked first in the next sweep
+            if rate_limited {
+                log::warn!("Stopping the sweep after being rate limited");
+                break;
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Waits a random jitter so the lookups of a batch don't hit the API at the same time
+    async fn check(client: &TuCocheDanaClient, vehicle: &Vehicle) -> LookupOutcome {
+        let jitter = rand::thread_rng().gen_range(0..=*SWEEP_JITTER_MS);
+        tokio::time::sleep(Duration::from_millis(jitter)).await;
+
+        client.lookup(&vehicle.plate).await
+    }
+}
+
+#[cfg(test)]
+mod sweep_task_tests {
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sweep() {
+        let db_controller = Repo::new_for_test("test_sweep").await.unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        // Chat 1 is subscribed to ABC123 and DEF456, chat 2 to DEF456
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+
+        let _found = server
+            .mock("GET", "/")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "matricula".to_string(),
+                "ABC123".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"encontrado": true, "deposito": "Campa de Ribarroja"}"#)
+            .create_async()
+            .await;
+
+        let _not_found = server
+            .mock("GET", "/")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "matricula".to_string(),
+                "DEF456".to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"encontrado": false}"#)
+            .create_async()
+            .await;
+
+        let client = TuCocheDanaClient::new(Some(server.url())).await;
+
+        let found = SweepTask::sweep(&db_controller, &client).await.unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].plate, "ABC123");
+        assert_eq!(
+            found[0].details.location.as_deref(),
+            Some("Campa de Ribarroja")
+        );
+
+        let vehicle = db_controller.get_vehicle("ABC123").await.unwrap();
+        assert!(vehicle.found_at.is_some());
+        assert_eq!(vehicle.check_status.as_deref(), Some("found"));
+
+        let vehicle = db_controller.get_vehicle("DEF456").await.unwrap();
+        assert!(vehicle.found_at.is_none());
+        assert!(vehicle.checked_at.is_some());
+        assert_eq!(vehicle.check_status.as_deref(), Some("not_found"));
+
+        // GHI789 has no active subscribers
+        let vehicle = db_controller.get_vehicle("GHI789").await.unwrap();
+        assert!(vehicle.checked_at.is_none());
+
+        // Found vehicles are not checked again
+        let pending = db_controller.get_pending_vehicles().await.unwrap();
+        assert_eq!(
+            pending.into_iter().map(|v| v.plate).collect::<Vec<_>>(),
+            vec!["DEF456"]
+        );
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transient_outcomes_keep_the_status() {
+        let db_controller = Repo::new_for_test("test_transient_outcomes_keep_the_status")
+            .await
+            .unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        let (api_key, _) = db_controller.create_api_key("Ayuntamiento").await.unwrap();
+        db_controller
+            .create_api_subscription(api_key.id, "GHI789", None)
+            .await
+            .unwrap();
+        db_controller
+            .create_partner_webhook(api_key.id, "https://example.com/hooks", None)
+            .await
+            .unwrap();
+
+        let client = TuCocheDanaClient::new(Some(server.url())).await;
+        // Status of GHI789 and the events queued after a sweep answered with the response
+        async fn sweep(
+            server: &mut mockito::Server,
+            client: &TuCocheDanaClient,
+            db_controller: &Repo,
+            status: usize,
+            body: &str,
+        ) -> (Option<String>, usize) {
+            let mock = server
+                .mock("GET", "/")
+                .match_query(mockito::Matcher::Any)
+                .with_status(status)
+                .with_body(body)
+                .create_async()
+                .await;
+            SweepTask::sweep(db_controller, client).await.unwrap();
+            mock.remove_async().await;
+
+            let vehicle = db_controller.get_vehicle("GHI789").await.unwrap();
+            let events = db_controller
+                .get_pending_webhook_deliveries()
+                .await
+                .unwrap()
+                .len();
+            (vehicle.check_status, events)
+        }
+        let not_found = r#"{"encontrado": false}"#;
+
+        // Failures don't count as the first status
+        assert_eq!(
+            sweep(&mut server, &client, &db_controller, 500, "").await,
+            (None, 0)
+        );
+        assert_eq!(
+            sweep(&mut server, &client, &db_controller, 200, not_found).await,
+            (Some("not_found".to_string()), 1)
+        );
+        // Nor as changes of it
+        assert_eq!(
+            sweep(&mut server, &client, &db_controller, 429, "").await,
+            (Some("not_found".to_string()), 1)
+        );
+        assert_eq!(
+            sweep(&mut server, &client, &db_controller, 200, "<html>").await,
+            (Some("not_found".to_string()), 1)
+        );
+        assert_eq!(
+            sweep(&mut server, &client, &db_controller, 200, not_found).await,
+            (Some("not_found".to_string()), 1)
+        );
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sweep_keeps_notifications_before_an_error() {
+        let db_controller = Repo::new_for_test("test_sweep_keeps_notifications_before_an_error")
+            .await
+            .unwrap();
+        let mut server = mockito::Server::new_async().await;
+
+        db_controller.modify_active_chat(&1, true).await.unwrap();
+
+        // ABC123 goes in the first batch and storing DEF456 fails in the second one
+        let connection = db_controller.get_connection().get().await.unwrap();
+        connection
+            .batch_execute(
+                "UPDATE vehicles SET checked_at = NOW() WHERE plate = 'DEF456';
+                CREATE FUNCTION fail_def456() RETURNS trigger AS $$
+                BEGIN
+                    RAISE EXCEPTION 'DEF456 is locked';
+                END $$ LANGUAGE plpgsql;
+                CREATE TRIGGER fail_def456 BEFORE UPDATE ON vehicles
+                FOR EACH ROW WHEN (NEW.plate = 'DEF456') EXECUTE FUNCTION fail_def456();",
+            )
+            .await
+            .unwrap();
+
+        let _found = server
+            .mock("GET", "/")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"encontrado": true}"#)
+            .create_async()
+            .await;
+
+        let client = TuCocheDanaClient::new(Some(server.url())).await;
+
+        assert!(SweepTask::sweep_in_batches(&db_controller, &client, 1)
+            .await
+            .is_err());
+
+        let vehicle = db_controller.get_vehicle("ABC123").await.unwrap();
+        assert!(vehicle.found_at.is_some());
+        let vehicle = db_controller.get_vehicle("DEF456").await.unwrap();
+        assert!(vehicle.found_at.is_none());
+
+        let notifications = db_controller.get_pending_notifications().await.unwrap();
+        assert_eq!(
+            notifications
+                .iter()
+                .map(|notification| notification.plate.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ABC123"]
+        );
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+}
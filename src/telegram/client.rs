@@ -2,18 +2,28 @@ use crate::TELEGRAM_BOT_TOKEN;
 use fang::FangError;
 use fang::ToFangError;
 use frankenstein::AllowedUpdate;
+use frankenstein::AnswerCallbackQueryParams;
+use frankenstein::AnswerInlineQueryParams;
 use frankenstein::AnswerPreCheckoutQueryParams;
 use frankenstein::AsyncApi;
 use frankenstein::AsyncTelegramApi;
+use frankenstein::BotCommand;
+use frankenstein::BotCommandScope;
 use frankenstein::ChatAction;
+use frankenstein::ChatMember;
 use frankenstein::DeleteWebhookParams;
 use frankenstein::EditMessageReplyMarkupParams;
 use frankenstein::EditMessageTextParams;
 use frankenstein::FileUpload;
+use frankenstein::ForceReply;
+use frankenstein::GetChatMemberParams;
+use frankenstein::GetFileParams;
 use frankenstein::GetStickerSetParams;
 use frankenstein::GetUpdatesParams;
 use frankenstein::InlineKeyboardButton;
 use frankenstein::InlineKeyboardMarkup;
+use frankenstein::InlineQueryResult;
+use frankenstein::InlineQueryResultsButton;
 use frankenstein::InputFile;
 use frankenstein::Message;
 use frankenstein::MessageOrBool;
@@ -25,6 +35,7 @@ use frankenstein::SendChatActionParams;
 use frankenstein::SendMessageParams;
 use frankenstein::SendStickerParams;
 use frankenstein::SendVideoParams;
+use frankenstein::SetMyCommandsParams;
 use frankenstein::SetWebhookParams;
 use frankenstein::StickerSet;
 use frankenstein::Update;
@@ -39,10 +50,18 @@ use std::fmt::Debug;
 
 static API_CLIENT: OnceCell<ApiClient> = OnceCell::const_new();
 
+/// Seconds `getUpdates` waits for new updates before answering
+const LONG_POLLING_TIMEOUT: u32 = 30;
+
 #[derive(Debug, Error, ToFangError)]
 pub enum ApiError {
     #[error(transparent)]
     FrankensteinError(#[from] frankenstein::Error),
+    /// The URL is removed, it contains the token of the bot
+    #[error(transparent)]
+    DownloadError(frankenstein::reqwest::Error),
+    #[error("File {0} can't be downloaded")]
+    FileUnavailable(String),
 }
 
 #[derive(Debug, Clone)]
@@ -70,20 +89,52 @@ impl From<InlineKeyboardMarkup> for Buttons {
     }
 }
 
+/// Escapes text that is sent with `ParseMode::Html`
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes text that is sent with `ParseMode::MarkdownV2`
+pub fn escape_markdown(text: &str) -> String {
+    const SPECIAL: &str = "\\_*[]()~`>#+-=|{}.!";
+
+    text.chars().fold(String::new(), |mut escaped, c| {
+        if SPECIAL.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
 impl ApiClient {
     pub async fn api_client() -> &'static Self {
         API_CLIENT.get_or_init(ApiClient::new).await
     }
 
     pub async fn new() -> Self {
-        let telegram_client = AsyncApi::new(&TELEGRAM_BOT_TOKEN);
+        Self::new_url(format!(
+            "{}{}",
+            frankenstein::BASE_API_URL,
+            *TELEGRAM_BOT_TOKEN
+        ))
+        .await
+    }
+
+    /// Client for a Bot API server other than the official one
+    pub async fn new_url(api_url: String) -> Self {
+        let telegram_client = AsyncApi::new_url(api_url);
 
         let update_params = GetUpdatesParams::builder()
             .allowed_updates(vec![
                 AllowedUpdate::Message,
                 //AllowedUpdate::ChannelPost,
                 AllowedUpdate::CallbackQuery,
+                AllowedUpdate::InlineQuery,
             ])
+            .timeout(LONG_POLLING_TIMEOUT)
             .build();
 
         let buffer = VecDeque::new();
@@ -100,6 +151,7 @@ impl ApiClient {
         url: &String,
         ip_address: Option<String>,
         certificate_path: Option<String>,
+        secret_token: &str,
     ) -> Result<MethodResponse<bool>, ApiError> {
         let file: Option<InputFile> = certificate_path.map(|path| {
             InputFile::builder()
@@ -107,18 +159,13 @@ impl ApiClient {
                 .build()
         });
 
-        let params: SetWebhookParams = match ip_address {
-            Some(ip) => SetWebhookParams::builder()
-                .url(url)
-                .ip_address(ip)
-                .maybe_certificate(file)
-                .allowed_updates(self.update_params.allowed_updates.clone().unwrap())
-                .build(),
-            None => SetWebhookParams::builder()
-                .url(url)
-                .allowed_updates(self.update_params.allowed_updates.clone().unwrap())
-                .build(),
-        };
+        let params: SetWebhookParams = SetWebhookParams::builder()
+            .url(url)
+            .maybe_ip_address(ip_address)
+            .maybe_certificate(file)
+            .allowed_updates(self.update_params.allowed_updates.clone().unwrap())
+            .secret_token(secret_token)
+            .build();
 
         Ok(self.telegram_client.set_webhook(&params).await?)
     }
@@ -279,6 +326,30 @@ impl ApiClient {
             .await?)
     }
 
+    /// Opens the reply of the users mentioned in `text`, the only messages of a group
+    /// the bot gets with the privacy mode on
+    pub async fn send_force_reply(
+        &self,
+        chat_id: i64,
+        text: impl Into<String>,
+    ) -> Result<MethodResponse<Message>, ApiError> {
+        let force_reply = ForceReply::builder()
+            .force_reply(true)
+            .selective(true)
+            .build();
+        let send_message_params = SendMessageParams::builder()
+            .chat_id(chat_id)
+            .text(text.into())
+            .reply_markup(ReplyMarkup::ForceReply(force_reply))
+            .parse_mode(ParseMode::Html)
+            .build();
+
+        Ok(self
+            .telegram_client
+            .send_message(&send_message_params)
+            .await?)
+    }
+
     pub async fn approve_payment(
         &self,
         checkout_id: &String,
@@ -294,6 +365,108 @@ impl ApiClient {
             .await?)
     }
 
+    /// Without an answer the button keeps spinning for a while, `text` is shown as a toast
+    /// or as an alert the user has to close
+    pub async fn answer_callback_query(
+        &self,
+        callback_query_id: &str,
+        text: Option<&str>,
+        show_alert: bool,
+    ) -> Result<MethodResponse<bool>, ApiError> {
+        let params = AnswerCallbackQueryParams::builder()
+            .callback_query_id(callback_query_id)
+            .maybe_text(text)
+            .show_alert(show_alert)
+            .build();
+
+        Ok(self.telegram_client.answer_callback_query(&params).await?)
+    }
+
+    /// Results are cached by Telegram for `cache_time` seconds and only for the user asking
+    pub async fn answer_inline_query(
+        &self,
+        inline_query_id: &str,
+        results: Vec<InlineQueryResult>,
+        button: Option<InlineQueryResultsButton>,
+        cache_time: u32,
+    ) -> Result<MethodResponse<bool>, ApiError> {
+        let params = AnswerInlineQueryParams::builder()
+            .inline_query_id(inline_query_id)
+            .results(results)
+            .maybe_button(button)
+            .cache_time(cache_time)
+            .is_personal(true)
+            .build();
+
+        Ok(self.telegram_client.answer_inline_query(&params).await?)
+    }
+
+    /// Owner or administrator of the group
+    pub async fn is_chat_admin(&self, chat_id: i64, user_id: u64) -> Result<bool, ApiError> {
+        let params = GetChatMemberParams::builder()
+            .chat_id(chat_id)
+            .user_id(user_id)
+            .build();
+
+        let member = self.telegram_client.get_chat_member(&params).await?.result;
+
+        Ok(matches!(
+            member,
+            ChatMember::Creator(_) | ChatMember::Administrator(_)
+        ))
+    }
+
+    /// Menu of commands shown to the users of `scope`, for every language when `language_code`
+    /// is `None`
+    pub async fn set_my_commands(
+        &self,
+        commands: Vec<BotCommand>,
+        scope: BotCommandScope,
+        language_code: Option<&str>,
+    ) -> Result<MethodResponse<bool>, ApiError> {
+        let params = SetMyCommandsParams::builder()
+            .commands(commands)
+            .scope(scope)
+            .maybe_language_code(language_code)
+            .build();
+
+        Ok(self.telegram_client.set_my_commands(&params).await?)
+    }
+
+    /// Contents of a file sent to the bot, Telegram only serves files of up to 20 MB
+    pub async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, ApiError> {
+        let params = GetFileParams::builder().file_id(file_id).build();
+        let file = self.telegram_client.get_file(&params).await?.result;
+
+        let Some(file_path) = file.file_path else {
+            return Err(ApiError::FileUnavailable(file_id.to_string()));
+        };
+
+        // `<server>/bot<token>` serves the files at `<server>/file/bot<token>/<path>`
+        let url = format!(
+            "{}/{file_path}",
+            self.telegram_client
+                .api_url
+                .replacen("/bot", "/file/bot", 1)
+        );
+
+        let hide_url =
+            |err: frankenstein::reqwest::Error| ApiError::DownloadError(err.without_url());
+        let bytes = self
+            .telegram_client
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(hide_url)?
+            .bytes()
+            .await
+            .map_err(hide_url)?;
+
+        Ok(bytes.to_vec())
+    }
+
     pub async fn get_sticker_set(&self, name: &str) -> Result<StickerSet, ApiError> {
         let params = GetStickerSetParams::builder().name(name).build();
         Ok(self.telegram_client.get_sticker_set(&params).await?.result)
@@ -356,3 +529,45 @@ impl ApiClient {
             .await?)
     }
 }
+
+#[cfg(test)]
+mod client_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_download_file_hides_token() {
+        let mut server = mockito::Server::new_async().await;
+
+        let _file = server
+            .mock("POST", "/bot123:s3cr3t/getFile")
+            .with_status(200)
+            .with_body(
+                r#"{"ok": true, "result": {"file_id": "f1", "file_unique_id": "u1", "file_path": "documents/plates.txt"}}"#,
+            )
+            .create_async()
+            .await;
+        let download = server
+            .mock("GET", "/file/bot123:s3cr3t/documents/plates.txt")
+            .with_status(200)
+            .with_body("1234BCD\n5678FGH")
+            .create_async()
+            .await;
+
+        let api = ApiClient::new_url(format!("{}/bot123:s3cr3t", server.url())).await;
+        assert_eq!(
+            api.download_file("f1").await.unwrap(),
+            b"1234BCD\n5678FGH".to_vec()
+        );
+
+        download.remove_async().await;
+        let _missing = server
+            .mock("GET", "/file/bot123:s3cr3t/documents/plates.txt")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let err = api.download_file("f1").await.unwrap_err();
+        assert!(matches!(err, ApiError::DownloadError(_)), "{err:?}");
+        assert!(!format!("{err:?} {err}").contains("s3cr3t"), "{err:?}");
+    }
+}
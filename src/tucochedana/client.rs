@@ -1,7 +1,12 @@
-use chrono::{DateTime, Utc};
-use frankenstein::reqwest::{Client, StatusCode};
+use std::time::Duration;
 
-use crate::{BotError, API_URL};
+use frankenstein::reqwest::Client;
+
+use crate::{metrics, API_URL};
+
+use super::lookup::LookupOutcome;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct TuCocheDanaClient {
     client: Client,
@@ -10,75 +15,192 @@ pub struct TuCocheDanaClient {
 
 impl TuCocheDanaClient {
     pub async fn new(url: Option<String>) -> Self {
-        let client = frankenstein::reqwest::ClientBuilder::new().build().unwrap();
+        let client = frankenstein::reqwest::ClientBuilder::new()
+            .timeout(TIMEOUT)
+            .build()
+            .unwrap();
         let base_url = url.unwrap_or(API_URL.to_string());
         TuCocheDanaClient { client, base_url }
     }
 
-    pub async fn is_vehicle_found(&self, plate: &str) -> Result<DateTime<Utc>, BotError> {
-        let result = self
+    pub async fn lookup(&self, plate: &str) -> LookupOutcome {
+        let timer = metrics::LOOKUP_DURATION.start_timer();
+        let outcome = self.request(plate).await;
+        timer.observe_duration();
+
+        metrics::LOOKUPS_TOTAL
+            .with_label_values(&[outcome.status()])
+            .inc();
+
+        outcome
+    }
+
+    async fn request(&self, plate: &str) -> LookupOutcome {
+        let result = match self
             .client
             .get(&self.base_url)
             .query(&[("matricula", &plate)])
             .send()
-            .await?;
-
-        match result.status() {
-            StatusCode::OK => Ok(Utc::now()),
-            code => {
-                let test = result.text().await?;
-                Err(BotError::TuCocheDanaError(code, test))
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                return LookupOutcome::UpstreamError {
+                    status: err.status(),
+                    reason: err.to_string(),
+                }
             }
+        };
+
+        let status = result.status();
+        let headers = result.headers().clone();
+
+        match result.text().await {
+            Ok(body) => LookupOutcome::from_response(status, &headers, &body),
+            Err(err) => LookupOutcome::UpstreamError {
+                status: Some(status),
+                reason: err.to_string(),
+            },
         }
     }
 }
 
 #[cfg(test)]
 mod tu_coche_dana_client_tests {
+    use std::time::Duration;
+
+    use chrono::NaiveDate;
+    use frankenstein::reqwest::StatusCode;
+
     use super::*;
-    #[tokio::test]
-    async fn test_is_vehicle_found_ok() {
+
+    async fn lookup_with_mock(
+        plate: &str,
+        status: usize,
+        body: &str,
+        header: Option<(&str, &str)>,
+    ) -> LookupOutcome {
         let mut server = mockito::Server::new_async().await;
 
-        let _mock = server
+        let mut mock = server
             .mock("GET", "/")
             .match_query(mockito::Matcher::UrlEncoded(
                 "matricula".to_string(),
-                "ABC123".to_string(),
+                plate.to_string(),
             ))
-            .with_status(200)
-            .with_body("hello")
-            .create();
+            .with_status(status)
+            .with_body(body);
+
+        if let Some((name, value)) = header {
+            mock = mock.with_header(name, value);
+        }
+        let _mock = mock.create_async().await;
+
         let client = TuCocheDanaClient::new(Some(server.url())).await;
-        let result = client.is_vehicle_found("ABC123").await;
+        client.lookup(plate).await
+    }
+
+    #[tokio::test]
+    async fn test_lookup_found() {
+        let result = lookup_with_mock(
+            "1234BCD",
+            200,
+            r#"{"encontrado": true, "fecha": "2024-11-05T10:00:00Z"}"#,
+            None,
+        )
+        .await;
 
         log::error!("{:#?}", result);
-        assert!(result.is_ok());
+        match result {
+            LookupOutcome::Found(vehicle) => {
+                assert_eq!(vehicle.found_at.to_rfc3339(), "2024-11-05T10:00:00+00:00");
+                assert!(vehicle.details.is_empty());
+            }
+            _ => panic!("Expected LookupOutcome::Found"),
+        }
     }
 
     #[tokio::test]
-    async fn test_is_vehicle_found_not_found() {
-        let mut server = mockito::Server::new_async().await;
+    async fn test_lookup_found_with_details() {
+        let result = lookup_with_mock(
+            "1234BCD",
+            200,
+            r#"{
+                "encontrado": true,
+                "fecha": "2024-11-05T10:00:00Z",
+                "deposito": "Campa de Ribarroja",
+                "fecha_registro": "2024-11-04",
+                "referencia": "VLC-0042",
+                "contacto": " ",
+                "otro": 1
+            }"#,
+            None,
+        )
+        .await;
 
-        let _mock = server
-            .mock("GET", "/")
-            .match_query(mockito::Matcher::UrlEncoded(
-                "matricula".to_string(),
-                "XYZ789".to_string(),
-            ))
-            .with_status(404)
-            .with_body("Vehicle not found")
-            .create();
+        let LookupOutcome::Found(vehicle) = result else {
+            panic!("Expected LookupOutcome::Found, got {:?}", result);
+        };
 
-        let client = TuCocheDanaClient::new(Some(server.url())).await;
-        let result = client.is_vehicle_found("XYZ789").await;
+        assert_eq!(
+            vehicle.details.location.as_deref(),
+            Some("Campa de Ribarroja")
+        );
+        assert_eq!(
+            vehicle.details.registered_at,
+            NaiveDate::from_ymd_opt(2024, 11, 4)
+        );
+        assert_eq!(vehicle.details.reference.as_deref(), Some("VLC-0042"));
+        assert_eq!(vehicle.details.contact, None);
+    }
 
-        match result {
-            Err(BotError::TuCocheDanaError(status, message)) => {
-                assert_eq!(status, StatusCode::NOT_FOUND);
-                assert_eq!(message, "Vehicle not found");
+    #[tokio::test]
+    async fn test_lookup_not_found() {
+        let result = lookup_with_mock("1234BCD", 200, r#"{"encontrado": false}"#, None).await;
+        assert_eq!(result, LookupOutcome::NotFound);
+
+        let result = lookup_with_mock("XYZ789", 404, "Vehicle not found", None).await;
+        assert_eq!(result, LookupOutcome::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_rate_limited() {
+        let result = lookup_with_mock("1234BCD", 429, "", Some(("Retry-After", "120"))).await;
+
+        assert_eq!(
+            result,
+            LookupOutcome::RateLimited {
+                retry_after: Some(Duration::from_secs(120))
             }
-            _ => panic!("Expected BotError::TuCocheDanaError with NOT_FOUND status"),
-        }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_upstream_error() {
+        let result = lookup_with_mock("1234BCD", 500, "Internal error", None).await;
+
+        assert_eq!(
+            result,
+            LookupOutcome::UpstreamError {
+                status: Some(StatusCode::INTERNAL_SERVER_ERROR),
+                reason: "Internal error".to_string()
+            }
+        );
+
+        let client = TuCocheDanaClient::new(Some("http://127.0.0.1:1".to_string())).await;
+        assert!(matches!(
+            client.lookup("1234BCD").await,
+            LookupOutcome::UpstreamError { status: None, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_malformed() {
+        let result = lookup_with_mock("1234BCD", 200, "<html>hello</html>", None).await;
+
+        assert_eq!(
+            result,
+            LookupOutcome::Malformed("<html>hello</html>".to_string())
+        );
     }
 }
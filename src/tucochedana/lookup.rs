@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use frankenstein::reqwest::{header::HeaderMap, header::RETRY_AFTER, StatusCode};
+use serde::Deserialize;
+
+use crate::db::model::vehicle::FoundDetails;
+
+/// Result of asking tucochedana.es about a plate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupOutcome {
+    Found(FoundVehicle),
+    NotFound,
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    /// Non successful status code or the request never completed (timeout, DNS...)
+    UpstreamError {
+        status: Option<StatusCode>,
+        reason: String,
+    },
+    /// The API answered but the body couldn't be understood
+    Malformed(String),
+}
+
+/// Details returned by the API when the vehicle has been found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundVehicle {
+    pub found_at: DateTime<Utc>,
+    pub details: FoundDetails,
+}
+
+/// Body of a successful response
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    #[serde(alias = "encontrado")]
+    found: bool,
+    #[serde(default, alias = "fecha")]
+    found_at: Option<DateTime<Utc>>,
+    #[serde(default, alias = "ubicacion", alias = "deposito")]
+    location: Option<String>,
+    #[serde(default, alias = "fecha_registro")]
+    registered_at: Option<NaiveDate>,
+    #[serde(default, alias = "referencia")]
+    reference: Option<String>,
+    #[serde(default, alias = "contacto", alias = "telefono")]
+    contact: Option<String>,
+}
+
+impl LookupResponse {
+    fn details(self) -> FoundDetails {
+        // Blank fields are as good as missing ones
+        let not_blank = |field: Option<String>| {
+            field
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        FoundDetails::builder()
+            .maybe_location(not_blank(self.location))
+            .maybe_registered_at(self.registered_at)
+            .maybe_reference(not_blank(self.reference))
+            .maybe_contact(not_blank(self.contact))
+            .build()
+    }
+}
+
+impl LookupOutcome {
+    pub fn from_response(status: StatusCode, headers: &HeaderMap, body: &str) -> Self {
+        match status {
+            StatusCode::OK => Self::from_body(body),
+            StatusCode::NOT_FOUND => LookupOutcome::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => LookupOutcome::RateLimited {
+                retry_after: Self::retry_after(headers),
+            },
+            code => LookupOutcome::UpstreamError {
+                status: Some(code),
+                reason: body.to_string(),
+            },
+        }
+    }
+
+    fn from_body(body: &str) -> Self {
+        match serde_json::from_str::<LookupResponse>(body) {
+            Ok(LookupResponse { found: false, .. }) => LookupOutcome::NotFound,
+            Ok(response) => LookupOutcome::Found(FoundVehicle {
+                found_at: response.found_at.unwrap_or_else(Utc::now),
+                details: response.details(),
+            }),
+            Err(err) => {
+                log::error!("Malformed response from tucochedana: {err} -> {body}");
+                LookupOutcome::Malformed(body.to_string())
+            }
+        }
+    }
+
+    /// `Retry-After` can be either a number of seconds or an HTTP date
+    fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+        let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+    }
+
+    /// Short name stored with the vehicle after each lookup, unless it's transient
+    pub fn status(&self) -> &'static str {
+        match self {
+            LookupOutcome::Found(_) => "found",
+            LookupOutcome::NotFound => "not_found",
+            LookupOutcome::RateLimited { .. } => "rate_limited",
+            LookupOutcome::UpstreamError { .. } => "upstream_error",
+            LookupOutcome::Malformed(_) => "malformed",
+        }
+    }
+
+    pub fn is_found(&self) -> bool {
+        matches!(self, LookupOutcome::Found(_))
+    }
+
+    /// Says nothing about the vehicle, it's looked up again in the next sweep
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            LookupOutcome::RateLimited { .. }
+                | LookupOutcome::UpstreamError { .. }
+                | LookupOutcome::Malformed(_)
+        )
+    }
+}
@@ -0,0 +1,115 @@
+use chrono_tz::Tz;
+use thiserror::Error;
+
+use crate::{
+    i18n::Lang,
+    plate::{Plate, PlateError},
+};
+
+/// Arguments of the commands, typed after them or carried by the buttons
+pub trait CommandArg: Sized {
+    fn parse_arg(arg: &str) -> Result<Self, ArgError>;
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ArgError {
+    #[error("Missing argument")]
+    Missing,
+    #[error("Unexpected argument '{0}'")]
+    Unexpected(String),
+    #[error(transparent)]
+    InvalidPlate(#[from] PlateError),
+    #[error("Unknown language '{0}'")]
+    InvalidLanguage(String),
+    #[error("Unknown timezone '{0}'")]
+    InvalidTimezone(String),
+}
+
+impl CommandArg for Plate {
+    fn parse_arg(arg: &str) -> Result<Self, ArgError> {
+        Ok(Plate::parse(arg)?)
+    }
+}
+
+impl CommandArg for Lang {
+    fn parse_arg(arg: &str) -> Result<Self, ArgError> {
+        let arg = arg.trim();
+        arg.parse()
+            .map_err(|_| ArgError::InvalidLanguage(arg.to_string()))
+    }
+}
+
+impl CommandArg for Tz {
+    fn parse_arg(arg: &str) -> Result<Self, ArgError> {
+        let arg = arg.trim();
+        arg.parse()
+            .map_err(|_| ArgError::InvalidTimezone(arg.to_string()))
+    }
+}
+
+pub fn required<T: CommandArg>(arg: Option<&str>) -> Result<T, ArgError> {
+    T::parse_arg(arg.ok_or(ArgError::Missing)?)
+}
+
+/// Plate of a vehicle the chat may already follow, the ones that don't parse are let through
+/// as `PlateKind::Unverified` for the handler to check they are followed
+pub fn followed_plate(arg: Option<&str>) -> Result<Plate, ArgError> {
+    let arg = arg.ok_or(ArgError::Missing)?;
+    Plate::parse(arg)
+        .or_else(|err| Plate::unverified(arg).map_err(|_| err))
+        .map_err(ArgError::from)
+}
+
+pub fn optional<T: CommandArg>(arg: Option<&str>) -> Result<Option<T>, ArgError> {
+    arg.map(T::parse_arg).transpose()
+}
+
+#[cfg(test)]
+mod args_tests {
+    use super::*;
+    use crate::plate::PlateKind;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            required::<Plate>(Some("1234 bcd")).map(String::from),
+            Ok("1234BCD".to_string())
+        );
+        assert_eq!(required::<Plate>(None), Err(ArgError::Missing));
+        assert_eq!(
+            required::<Plate>(Some("1234 abc")),
+            Err(ArgError::InvalidPlate(PlateError::UnknownFormat(
+                "1234ABC".to_string()
+            )))
+        );
+
+        assert_eq!(optional::<Lang>(None), Ok(None));
+        assert_eq!(optional::<Lang>(Some(" ca ")), Ok(Some(Lang::Ca)));
+        assert_eq!(
+            optional::<Lang>(Some("fr")),
+            Err(ArgError::InvalidLanguage("fr".to_string()))
+        );
+
+        assert_eq!(
+            required::<Tz>(Some("Atlantic/Canary")),
+            Ok(chrono_tz::Atlantic::Canary)
+        );
+        assert_eq!(
+            required::<Tz>(Some("Mars/Olympus")),
+            Err(ArgError::InvalidTimezone("Mars/Olympus".to_string()))
+        );
+
+        assert_eq!(
+            followed_plate(Some("1234 bcd")).map(|plate| plate.kind()),
+            Ok(PlateKind::National)
+        );
+        assert_eq!(
+            followed_plate(Some("1234 abc")).map(|plate| plate.kind()),
+            Ok(PlateKind::Unverified)
+        );
+        assert_eq!(
+            followed_plate(Some(" ")),
+            Err(ArgError::InvalidPlate(PlateError::Empty))
+        );
+    }
+}
@@ -0,0 +1,470 @@
+use chrono_tz::Tz;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::{
+    i18n::Lang,
+    plate::Plate,
+    update_handler::{
+        args::{followed_plate, optional, required, ArgError},
+        command::Command,
+    },
+    CALLBACK_SECRET,
+};
+
+/// Longest `callback_data` accepted by Telegram
+pub const MAX_CALLBACK_LEN: usize = 64;
+
+/// Bumped whenever the encoding changes, buttons of other versions are rejected as outdated
+pub const CALLBACK_VERSION: &str = "1";
+
+const SEPARATOR: char = ':';
+
+/// Hex characters of the HMAC appended to the buttons when `CALLBACK_SECRET` is set
+const MAC_LEN: usize = 12;
+
+/// What the inline buttons ask the bot to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallbackAction {
+    Menu,
+    AddVehiclePrompt,
+    MyVehicles,
+    StartFetch,
+    StopFetch,
+    Channels,
+    LanguageMenu,
+    SetLanguage(Lang),
+    TimezoneMenu,
+    SetTimezone(Tz),
+    Help,
+    FollowVehicle(Plate),
+    CheckVehicle(Plate),
+    DeleteVehicle(Plate),
+    ShareVehicle(Plate),
+    SetEmail,
+    EmailOff,
+    TelegramOn,
+    TelegramOff,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum CallbackError {
+    #[error("Callback data is {0} bytes long, {MAX_CALLBACK_LEN} at most")]
+    TooLong(usize),
+    #[error("Callback data '{0}' isn't of version {CALLBACK_VERSION}")]
+    Outdated(String),
+    #[error("Unknown action '{0}'")]
+    Unknown(String),
+    #[error(transparent)]
+    InvalidArgument(#[from] ArgError),
+    #[error("Callback data '{0}' isn't signed")]
+    MissingSignature(String),
+    #[error("Invalid signature of callback data '{0}'")]
+    InvalidSignature(String),
+}
+
+impl CallbackAction {
+    /// Short code of the action and its argument
+    fn parts(&self) -> (&'static str, Option<String>) {
+        match self {
+            CallbackAction::Menu => ("m", None),
+            CallbackAction::AddVehiclePrompt => ("ap", None),
+            CallbackAction::MyVehicles => ("mv", None),
+            CallbackAction::StartFetch => ("sf", None),
+            CallbackAction::StopFetch => ("xf", None),
+            CallbackAction::Channels => ("ch", None),
+            CallbackAction::LanguageMenu => ("lm", None),
+            CallbackAction::SetLanguage(lang) => ("l", Some(lang.code().to_string())),
+            CallbackAction::TimezoneMenu => ("tm", None),
+            CallbackAction::SetTimezone(tz) => ("tz", Some(tz.name().to_string())),
+            CallbackAction::Help => ("h", None),
+            CallbackAction::FollowVehicle(plate) => ("f", Some(plate.to_string())),
+            CallbackAction::CheckVehicle(plate) => ("c", Some(plate.to_string())),
+            CallbackAction::DeleteVehicle(plate) => ("d", Some(plate.to_string())),
+            CallbackAction::ShareVehicle(plate) => ("s", Some(plate.to_string())),
+            CallbackAction::SetEmail => ("em", None),
+            CallbackAction::EmailOff => ("eo", None),
+            CallbackAction::TelegramOn => ("t1", None),
+            CallbackAction::TelegramOff => ("t0", None),
+        }
+    }
+
+    fn from_parts(code: &str, arg: Option<&str>) -> Result<Self, CallbackError> {
+        let action = match code {
+            "m" => CallbackAction::Menu,
+            "ap" => CallbackAction::AddVehiclePrompt,
+            "mv" => CallbackAction::MyVehicles,
+            "sf" => CallbackAction::StartFetch,
+            "xf" => CallbackAction::StopFetch,
+            "ch" => CallbackAction::Channels,
+            "lm" => CallbackAction::LanguageMenu,
+            "l" => CallbackAction::SetLanguage(required(arg)?),
+            "tm" => CallbackAction::TimezoneMenu,
+            "tz" => CallbackAction::SetTimezone(required(arg)?),
+            "h" => CallbackAction::Help,
+            "f" => CallbackAction::FollowVehicle(required(arg)?),
+            "c" => CallbackAction::CheckVehicle(followed_plate(arg)?),
+            "d" => CallbackAction::DeleteVehicle(followed_plate(arg)?),
+            "s" => CallbackAction::ShareVehicle(required(arg)?),
+            "em" => CallbackAction::SetEmail,
+            "eo" => CallbackAction::EmailOff,
+            "t1" => CallbackAction::TelegramOn,
+            "t0" => CallbackAction::TelegramOff,
+            _ => return Err(CallbackError::Unknown(code.to_string())),
+        };
+
+        match (action.parts().1, arg) {
+            (None, Some(arg)) => Err(ArgError::Unexpected(arg.to_string()).into()),
+            _ => Ok(action),
+        }
+    }
+
+    /// Typed commands are parsed into the same actions as the buttons, the ones
+    /// without an action (`/start`, `/cancel`...) handle the text themselves
+    pub fn from_command(command: &Command, arg: Option<&str>) -> Result<Option<Self>, ArgError> {
+        let action = match command {
+            Command::StartBack => CallbackAction::Menu,
+            Command::AddVehicleMessage => CallbackAction::AddVehiclePrompt,
+            Command::MyAddedVehicles => CallbackAction::MyVehicles,
+            Command::StartFetch => CallbackAction::StartFetch,
+            Command::StopFetch => CallbackAction::StopFetch,
+            Command::Channels => CallbackAction::Channels,
+            Command::Language => match optional(arg)? {
+                Some(lang) => CallbackAction::SetLanguage(lang),
+                None => CallbackAction::LanguageMenu,
+            },
+            Command::Timezone => match optional(arg)? {
+                Some(tz) => CallbackAction::SetTimezone(tz),
+                None => CallbackAction::TimezoneMenu,
+            },
+            Command::Help => CallbackAction::Help,
+            Command::AddVehicle => CallbackAction::FollowVehicle(required(arg)?),
+            Command::VehicleInfo => CallbackAction::CheckVehicle(followed_plate(arg)?),
+            Command::RemoveVehicle => CallbackAction::DeleteVehicle(followed_plate(arg)?),
+            Command::ShareVehicle => CallbackAction::ShareVehicle(required(arg)?),
+            Command::SetEmailMessage => CallbackAction::SetEmail,
+            Command::EmailOff => CallbackAction::EmailOff,
+            Command::TelegramOn => CallbackAction::TelegramOn,
+            Command::TelegramOff => CallbackAction::TelegramOff,
+            Command::Start
+            | Command::Cancel
+            | Command::InlineQuery
+            | Command::ChatMigrated
+            | Command::InvalidButton(_)
+            | Command::UnknownCommand(_) => return Ok(None),
+        };
+
+        Ok(Some(action))
+    }
+
+    /// Command that handles the action
+    pub fn command(&self) -> Command {
+        match self {
+            CallbackAction::Menu => Command::StartBack,
+            CallbackAction::AddVehiclePrompt => Command::AddVehicleMessage,
+            CallbackAction::MyVehicles => Command::MyAddedVehicles,
+            CallbackAction::StartFetch => Command::StartFetch,
+            CallbackAction::StopFetch => Command::StopFetch,
+            CallbackAction::Channels => Command::Channels,
+            CallbackAction::LanguageMenu | CallbackAction::SetLanguage(_) => Command::Language,
+            CallbackAction::TimezoneMenu | CallbackAction::SetTimezone(_) => Command::Timezone,
+            CallbackAction::Help => Command::Help,
+            CallbackAction::FollowVehicle(_) => Command::AddVehicle,
+            CallbackAction::CheckVehicle(_) => Command::VehicleInfo,
+            CallbackAction::DeleteVehicle(_) => Command::RemoveVehicle,
+            CallbackAction::ShareVehicle(_) => Command::ShareVehicle,
+            CallbackAction::SetEmail => Command::SetEmailMessage,
+            CallbackAction::EmailOff => Command::EmailOff,
+            CallbackAction::TelegramOn => Command::TelegramOn,
+            CallbackAction::TelegramOff => Command::TelegramOff,
+        }
+    }
+
+    /// Plate the action is about
+    pub fn plate(&self) -> Option<&Plate> {
+        match self {
+            CallbackAction::FollowVehicle(plate)
+            | CallbackAction::CheckVehicle(plate)
+            | CallbackAction::DeleteVehicle(plate)
+            | CallbackAction::ShareVehicle(plate) => Some(plate),
+            _ => None,
+        }
+    }
+
+    /// `callback_data` of the buttons, signed with `CALLBACK_SECRET` when it's set
+    pub fn encode(&self) -> String {
+        self.encode_with(CALLBACK_SECRET.as_deref())
+    }
+
+    pub fn decode(data: &str) -> Result<Self, CallbackError> {
+        Self::decode_with(data, CALLBACK_SECRET.as_deref())
+    }
+
+    /// `<version>:<code>[:<argument>][:<mac>]`
+    pub fn encode_with(&self, secret: Option<&str>) -> String {
+        let (code, arg) = self.parts();
+
+        let mut data = format!("{CALLBACK_VERSION}{SEPARATOR}{code}");
+        if let Some(arg) = arg {
+            data.push(SEPARATOR);
+            data.push_str(&arg);
+        }
+        if let Some(secret) = secret {
+            let mac = sign(secret, &data);
+            data.push(SEPARATOR);
+            data.push_str(&mac);
+        }
+
+        // Arguments are plates, language codes and IANA names, `test_longest_actions_fit`
+        // checks the longest of each one fits
+        debug_assert!(data.len() <= MAX_CALLBACK_LEN, "{data} is too long");
+        data
+    }
+
+    pub fn decode_with(data: &str, secret: Option<&str>) -> Result<Self, CallbackError> {
+        if data.len() > MAX_CALLBACK_LEN {
+            return Err(CallbackError::TooLong(data.len()));
+        }
+
+        let signed = match secret {
+            Some(secret) => {
+                let Some((signed, mac)) = data.rsplit_once(SEPARATOR) else {
+                    return Err(CallbackError::MissingSignature(data.to_string()));
+                };
+                if mac.len() != MAC_LEN {
+                    return Err(CallbackError::MissingSignature(data.to_string()));
+                }
+                if !verify(secret, signed, mac) {
+                    return Err(CallbackError::InvalidSignature(data.to_string()));
+                }
+                signed
+            }
+            None => data,
+        };
+
+        let mut parts = signed.splitn(3, SEPARATOR);
+        if parts.next() != Some(CALLBACK_VERSION) {
+            return Err(CallbackError::Outdated(data.to_string()));
+        }
+        let code = parts.next().unwrap_or_default();
+        let arg = parts.next();
+
+        Self::from_parts(code, arg)
+    }
+}
+
+fn hmac(secret: &str, data: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(data.as_bytes());
+    mac
+}
+
+/// Truncated HMAC of the data, enough to tell forged buttons apart
+fn sign(secret: &str, data: &str) -> String {
+    let mut mac = hex::encode(hmac(secret, data).finalize().into_bytes());
+    mac.truncate(MAC_LEN);
+    mac
+}
+
+/// Compares the truncated HMAC in constant time
+fn verify(secret: &str, data: &str, mac: &str) -> bool {
+    match hex::decode(mac) {
+        Ok(mac) => hmac(secret, data).verify_truncated_left(&mac).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod callback_tests {
+    use super::*;
+    use crate::plate::PlateError;
+
+    fn actions() -> Vec<CallbackAction> {
+        let plate = Plate::parse("PGC 1234 A").unwrap();
+        vec![
+            CallbackAction::Menu,
+            CallbackAction::AddVehiclePrompt,
+            CallbackAction::MyVehicles,
+            CallbackAction::StartFetch,
+            CallbackAction::StopFetch,
+            CallbackAction::Channels,
+            CallbackAction::LanguageMenu,
+            CallbackAction::SetLanguage(Lang::Ca),
+            CallbackAction::TimezoneMenu,
+            CallbackAction::SetTimezone(chrono_tz::America::Argentina::ComodRivadavia),
+            CallbackAction::Help,
+            CallbackAction::FollowVehicle(plate.clone()),
+            CallbackAction::CheckVehicle(plate.clone()),
+            CallbackAction::DeleteVehicle(plate.clone()),
+            CallbackAction::ShareVehicle(plate),
+            CallbackAction::SetEmail,
+            CallbackAction::EmailOff,
+            CallbackAction::TelegramOn,
+            CallbackAction::TelegramOff,
+        ]
+    }
+
+    #[test]
+    fn test_callback_round_trip() {
+        for secret in [None, Some("secret")] {
+            for action in actions() {
+                let data = action.encode_with(secret);
+                assert!(data.len() <= MAX_CALLBACK_LEN, "{data}");
+                assert_eq!(CallbackAction::decode_with(&data, secret), Ok(action));
+            }
+        }
+
+        assert_eq!(
+            CallbackAction::CheckVehicle(Plate::parse("1234BCD").unwrap()).encode_with(None),
+            "1:c:1234BCD"
+        );
+    }
+
+    #[test]
+    fn test_every_timezone_fits() {
+        for tz in chrono_tz::TZ_VARIANTS {
+            let data = CallbackAction::SetTimezone(tz).encode_with(Some("secret"));
+            assert!(data.len() <= MAX_CALLBACK_LEN, "{data}");
+        }
+    }
+
+    #[test]
+    fn test_longest_actions_fit() {
+        // Official prefix of three letters, six digits and two letters
+        let plate = Plate::parse("PGC123456AB").unwrap();
+        let tz = chrono_tz::TZ_VARIANTS
+            .into_iter()
+            .max_by_key(|tz| tz.name().len())
+            .unwrap();
+
+        let mut longest = vec![
+            CallbackAction::SetTimezone(tz),
+            CallbackAction::FollowVehicle(plate.clone()),
+            CallbackAction::CheckVehicle(plate.clone()),
+            CallbackAction::DeleteVehicle(plate.clone()),
+            CallbackAction::ShareVehicle(plate),
+        ];
+        longest.extend(Lang::ALL.into_iter().map(CallbackAction::SetLanguage));
+        longest.extend(actions());
+
+        for action in longest {
+            let data = action.encode_with(Some("secret"));
+            assert!(data.len() <= MAX_CALLBACK_LEN, "{data}");
+        }
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let data = CallbackAction::Menu.encode_with(Some("secret"));
+        let (signed, mac) = data.rsplit_once(SEPARATOR).unwrap();
+
+        assert!(verify("secret", signed, mac));
+        assert!(verify("secret", signed, &mac.to_uppercase()));
+        assert!(!verify("other", signed, mac));
+        assert!(!verify("secret", signed, "zzzzzzzzzzzz"));
+    }
+
+    #[test]
+    fn test_invalid_callbacks() {
+        // Buttons sent before the encoding existed
+        assert_eq!(
+            CallbackAction::decode_with("/check_vehicle 1234BCD", None),
+            Err(CallbackError::Outdated(
+                "/check_vehicle 1234BCD".to_string()
+            ))
+        );
+        assert_eq!(
+            CallbackAction::decode_with("1:zz", None),
+            Err(CallbackError::Unknown("zz".to_string()))
+        );
+        assert_eq!(
+            CallbackAction::decode_with("1:l:fr", None),
+            Err(CallbackError::InvalidArgument(ArgError::InvalidLanguage(
+                "fr".to_string()
+            )))
+        );
+        assert_eq!(
+            CallbackAction::decode_with("1:c", None),
+            Err(CallbackError::InvalidArgument(ArgError::Missing))
+        );
+        assert_eq!(
+            CallbackAction::decode_with("1:m:1234BCD", None),
+            Err(CallbackError::InvalidArgument(ArgError::Unexpected(
+                "1234BCD".to_string()
+            )))
+        );
+        assert_eq!(
+            CallbackAction::decode_with("1:f:1234ABC", None),
+            Err(CallbackError::InvalidArgument(ArgError::InvalidPlate(
+                PlateError::UnknownFormat("1234ABC".to_string())
+            )))
+        );
+        // Vehicles followed before plates were validated can still be checked and removed
+        assert_eq!(
+            CallbackAction::decode_with("1:c:1234ABC", None),
+            Ok(CallbackAction::CheckVehicle(
+                Plate::unverified("1234ABC").unwrap()
+            ))
+        );
+        assert_eq!(
+            CallbackAction::decode_with(&"1".repeat(65), None),
+            Err(CallbackError::TooLong(65))
+        );
+    }
+
+    #[test]
+    fn test_typed_commands() {
+        let plate = Plate::parse("1234BCD").unwrap();
+
+        assert_eq!(
+            CallbackAction::from_command(&Command::VehicleInfo, Some("1234 bcd")),
+            Ok(Some(CallbackAction::CheckVehicle(plate.clone())))
+        );
+        assert_eq!(
+            CallbackAction::from_command(&Command::RemoveVehicle, None),
+            Err(ArgError::Missing)
+        );
+        assert_eq!(
+            CallbackAction::from_command(&Command::Language, None),
+            Ok(Some(CallbackAction::LanguageMenu))
+        );
+        assert_eq!(
+            CallbackAction::from_command(&Command::Start, Some("add_1234BCD")),
+            Ok(None)
+        );
+
+        // Both ways end in the same action
+        for action in actions() {
+            let (_, arg) = action.parts();
+            assert_eq!(
+                CallbackAction::from_command(&action.command(), arg.as_deref()),
+                Ok(Some(action))
+            );
+        }
+    }
+
+    #[test]
+    fn test_signed_callbacks() {
+        let action = CallbackAction::DeleteVehicle(Plate::parse("1234BCD").unwrap());
+
+        let unsigned = action.encode_with(None);
+        assert_eq!(
+            CallbackAction::decode_with(&unsigned, Some("secret")),
+            Err(CallbackError::MissingSignature(unsigned))
+        );
+
+        let signed = action.encode_with(Some("other secret"));
+        assert_eq!(
+            CallbackAction::decode_with(&signed, Some("secret")),
+            Err(CallbackError::InvalidSignature(signed))
+        );
+
+        let forged = format!("1:d:5678BCD:{}", &action.encode_with(Some("secret"))[12..]);
+        assert!(matches!(
+            CallbackAction::decode_with(&forged, Some("secret")),
+            Err(CallbackError::InvalidSignature(_))
+        ));
+    }
+}
@@ -1,9 +1,23 @@
-use frankenstein::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
+use frankenstein::{
+    BotCommand, BotCommandScope, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode,
+};
 
-use crate::{db::model::client_state::ClientState, BotError, BOT_NAME};
-use std::str::{FromStr, SplitAsciiWhitespace};
+use crate::{
+    db::model::{chat::ChatKind, client_state::ClientState},
+    i18n::{Lang, Locale, Msg},
+    plate::{Plate, PlateKind},
+    telegram::client::{escape_html, ApiClient},
+    BotError, BOT_NAME,
+};
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
 
-use super::process_update::UpdateProcessor;
+use super::{
+    args::ArgError,
+    callback::{CallbackAction, CallbackError},
+    process_update::UpdateProcessor,
+};
+use frontend::add_vehicle::invalid_plate_text;
 
 /// Available bots commands as a Enum
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -13,37 +27,299 @@ pub enum Command {
     AddVehicleMessage,
     MyAddedVehicles,
     VehicleInfo,
+    ShareVehicle,
     StartFetch,
     StopFetch,
     Help,
     Start,
     StartBack,
     Cancel,
+    Channels,
+    SetEmailMessage,
+    EmailOff,
+    TelegramOn,
+    TelegramOff,
+    Language,
+    Timezone,
+    /// Not typed by the users, it's how inline queries are processed
+    InlineQuery,
+    /// Service message of a group that became a supergroup
+    ChatMigrated,
+    /// Button of an older version of the bot or with forged data
+    InvalidButton(CallbackError),
     UnknownCommand(String),
 }
 
+/// How a command is offered to the users
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandInfo {
+    /// Hidden commands don't have one, they're sent by the buttons
+    pub description: Option<Msg>,
+    /// Chats where the command is listed
+    pub scopes: &'static [ChatKind],
+}
+
+const EVERYWHERE: &[ChatKind] = &[ChatKind::Private, ChatKind::Group];
+const PRIVATE: &[ChatKind] = &[ChatKind::Private];
+
+impl CommandInfo {
+    const fn public(description: Msg, scopes: &'static [ChatKind]) -> Self {
+        CommandInfo {
+            description: Some(description),
+            scopes,
+        }
+    }
+
+    const fn hidden() -> Self {
+        CommandInfo {
+            description: None,
+            scopes: EVERYWHERE,
+        }
+    }
+}
+
+/// Lists of commands Telegram shows to the users
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandMenu {
+    Private,
+    /// Members of a group without the commands only admins can use
+    Group,
+    GroupAdmins,
+}
+
+impl CommandMenu {
+    pub const ALL: [CommandMenu; 3] = [
+        CommandMenu::Private,
+        CommandMenu::Group,
+        CommandMenu::GroupAdmins,
+    ];
+
+    pub fn scope(&self) -> BotCommandScope {
+        match self {
+            CommandMenu::Private => BotCommandScope::AllPrivateChats,
+            CommandMenu::Group => BotCommandScope::AllGroupChats,
+            CommandMenu::GroupAdmins => BotCommandScope::AllChatAdministrators,
+        }
+    }
+
+    pub fn includes(&self, command: &Command) -> bool {
+        let scopes = command.info().scopes;
+        match self {
+            CommandMenu::Private => scopes.contains(&ChatKind::Private),
+            CommandMenu::Group => scopes.contains(&ChatKind::Group) && !command.requires_admin(),
+            CommandMenu::GroupAdmins => scopes.contains(&ChatKind::Group),
+        }
+    }
+
+    /// Public commands of the menu in the language
+    pub fn commands(&self, lang: Lang) -> Vec<BotCommand> {
+        Command::ALL
+            .iter()
+            .filter(|command| self.includes(command))
+            .filter_map(|command| {
+                let description = command.info().description?;
+                Some(
+                    BotCommand::builder()
+                        .command(command.name())
+                        .description(description.text(lang))
+                        .build(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Sends the menus of commands to Telegram for every language, the default one included
+pub async fn register_commands(api: &ApiClient) -> Result<(), BotError> {
+    let langs = std::iter::once(None).chain(Lang::ALL.into_iter().map(Some));
+
+    for lang in langs {
+        for menu in CommandMenu::ALL {
+            let commands = menu.commands(lang.unwrap_or_default());
+            api.set_my_commands(commands, menu.scope(), lang.map(|lang| lang.code()))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
 impl FromStr for Command {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let command_str = s.replace(&*BOT_NAME, "");
+        Ok(Self::parse(s).0)
+    }
+}
+
+impl Command {
+    /// Command and the arguments typed after it, `/check@BotName 1234 BCD` is
+    /// `(VehicleInfo, Some("1234 BCD"))`
+    pub fn parse(s: &str) -> (Command, Option<String>) {
+        let command_str = Self::strip_bot_name(s);
+        let (name, arg) = command_str
+            .split_once(char::is_whitespace)
+            .unwrap_or((&command_str, ""));
+        let arg = Some(arg.trim())
+            .filter(|arg| !arg.is_empty())
+            .map(String::from);
 
-        let result = match command_str.trim() {
+        let command = match name {
             "/start" => Command::Start,
             "/help" => Command::Help,
             "/cancel" => Command::Cancel,
             "/start_back" => Command::StartBack,
             "/add_vehicle" => Command::AddVehicle,
-            "/check_vehicle" => Command::VehicleInfo,
+            "/check" | "/check_vehicle" => Command::VehicleInfo,
+            "/share_vehicle" => Command::ShareVehicle,
             "/delete_vehicle" => Command::RemoveVehicle,
             "/add_vehicle_message" => Command::AddVehicleMessage,
             "/get_my_vehicles" => Command::MyAddedVehicles,
             "/start_fetch" => Command::StartFetch,
             "/stop_fetch" => Command::StopFetch,
-            _ => Command::UnknownCommand(command_str.to_string()),
+            "/channels" => Command::Channels,
+            "/set_email" => Command::SetEmailMessage,
+            "/email_off" => Command::EmailOff,
+            "/telegram_on" => Command::TelegramOn,
+            "/telegram_off" => Command::TelegramOff,
+            "/language" => Command::Language,
+            "/timezone" => Command::Timezone,
+            _ => {
+                return (
+                    Command::UnknownCommand(command_str.trim().to_string()),
+                    None,
+                )
+            }
         };
 
-        Ok(result)
+        (command, arg)
+    }
+
+    /// Commands the users can send, in the order they're listed in the menus and the help
+    pub const ALL: [Command; 19] = [
+        Command::Start,
+        Command::AddVehicleMessage,
+        Command::AddVehicle,
+        Command::MyAddedVehicles,
+        Command::VehicleInfo,
+        Command::RemoveVehicle,
+        Command::StartFetch,
+        Command::StopFetch,
+        Command::Channels,
+        Command::Language,
+        Command::Timezone,
+        Command::Cancel,
+        Command::Help,
+        Command::StartBack,
+        Command::ShareVehicle,
+        Command::SetEmailMessage,
+        Command::EmailOff,
+        Command::TelegramOn,
+        Command::TelegramOff,
+    ];
+
+    pub fn info(&self) -> CommandInfo {
+        match self {
+            Command::Start => CommandInfo::public(Msg::CmdStart, EVERYWHERE),
+            Command::AddVehicleMessage => {
+                CommandInfo::public(Msg::CmdAddVehicleMessage, EVERYWHERE)
+            }
+            Command::AddVehicle => CommandInfo::public(Msg::CmdAddVehicle, EVERYWHERE),
+            Command::MyAddedVehicles => CommandInfo::public(Msg::CmdGetMyVehicles, EVERYWHERE),
+            Command::VehicleInfo => CommandInfo::public(Msg::CmdCheck, EVERYWHERE),
+            Command::RemoveVehicle => CommandInfo::public(Msg::CmdDeleteVehicle, EVERYWHERE),
+            Command::StartFetch => CommandInfo::public(Msg::CmdStartFetch, EVERYWHERE),
+            Command::StopFetch => CommandInfo::public(Msg::CmdStopFetch, EVERYWHERE),
+            // Email alerts are personal
+            Command::Channels => CommandInfo::public(Msg::CmdChannels, PRIVATE),
+            Command::Language => CommandInfo::public(Msg::CmdLanguage, EVERYWHERE),
+            Command::Timezone => CommandInfo::public(Msg::CmdTimezone, EVERYWHERE),
+            Command::Cancel => CommandInfo::public(Msg::CmdCancel, EVERYWHERE),
+            Command::Help => CommandInfo::public(Msg::CmdHelp, EVERYWHERE),
+            Command::StartBack
+            | Command::ShareVehicle
+            | Command::SetEmailMessage
+            | Command::EmailOff
+            | Command::TelegramOn
+            | Command::TelegramOff
+            | Command::InlineQuery
+            | Command::ChatMigrated
+            | Command::InvalidButton(_)
+            | Command::UnknownCommand(_) => CommandInfo::hidden(),
+        }
+    }
+
+    /// `/cmd@BotName` is `/cmd`, commands for other bots are left as they are
+    fn strip_bot_name(s: &str) -> String {
+        let s = s.trim();
+        let (command, args) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+
+        match command.split_once('@') {
+            Some((command, bot)) if bot.eq_ignore_ascii_case(BOT_NAME.trim_start_matches('@')) => {
+                format!("{command} {args}")
+            }
+            _ => s.to_string(),
+        }
+    }
+
+    /// Commands that change the plates or settings of the chat, only admins can use them in groups
+    pub fn requires_admin(&self) -> bool {
+        matches!(
+            self,
+            Command::AddVehicle
+                | Command::AddVehicleMessage
+                | Command::RemoveVehicle
+                | Command::StartFetch
+                | Command::StopFetch
+                | Command::SetEmailMessage
+                | Command::EmailOff
+                | Command::TelegramOn
+                | Command::TelegramOff
+                | Command::Language
+                | Command::Timezone
+        )
+    }
+
+    /// Example shown when the arguments are missing or invalid
+    pub fn usage(&self) -> Option<&'static str> {
+        match self {
+            Command::AddVehicle => Some("/add_vehicle 1234BCD"),
+            Command::VehicleInfo => Some("/check 1234BCD"),
+            Command::RemoveVehicle => Some("/delete_vehicle 1234BCD"),
+            Command::ShareVehicle => Some("/share_vehicle 1234BCD"),
+            Command::Language => Some("/language ca"),
+            Command::Timezone => Some("/timezone Europe/Madrid"),
+            _ => None,
+        }
+    }
+
+    /// Name without arguments, used as metrics label
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::RemoveVehicle => "delete_vehicle",
+            Command::AddVehicle => "add_vehicle",
+            Command::AddVehicleMessage => "add_vehicle_message",
+            Command::MyAddedVehicles => "get_my_vehicles",
+            Command::VehicleInfo => "check",
+            Command::ShareVehicle => "share_vehicle",
+            Command::StartFetch => "start_fetch",
+            Command::StopFetch => "stop_fetch",
+            Command::Help => "help",
+            Command::Start => "start",
+            Command::StartBack => "start_back",
+            Command::Cancel => "cancel",
+            Command::Channels => "channels",
+            Command::SetEmailMessage => "set_email",
+            Command::EmailOff => "email_off",
+            Command::TelegramOn => "telegram_on",
+            Command::TelegramOff => "telegram_off",
+            Command::Language => "language",
+            Command::Timezone => "timezone",
+            Command::InlineQuery => "inline_query",
+            Command::ChatMigrated => "chat_migrated",
+            Command::InvalidButton(_) => "invalid_button",
+            Command::UnknownCommand(_) => "unknown",
+        }
     }
 }
 
@@ -64,22 +340,214 @@ pub mod backend;
 pub mod backend {
     pub mod add_vehicle;
     pub mod cancel;
+    pub mod channels;
+    pub mod language;
     pub mod remove_vehicle;
+    pub mod share_vehicle;
     pub mod start_fetch;
     pub mod stop_fetch;
+    pub mod timezone;
 }
 
 /// Comandos que solo mandan mensajes o consultan la BD
 pub mod frontend {
     pub mod add_vehicle;
+    pub mod channels;
     pub mod check_vehicle;
     pub mod help;
+    pub mod inline_query;
+    pub mod language;
     pub mod list_vehicles;
     pub mod start;
+    pub mod timezone;
 }
 
 impl UpdateProcessor {
+    pub fn lang(&self) -> Lang {
+        self.chat.lang()
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.chat.locale()
+    }
+
+    /// Text in the language of the chat
+    pub fn t(&self, msg: Msg) -> &'static str {
+        msg.text(self.lang())
+    }
+
+    /// Text in the language of the chat with its placeholders replaced
+    pub fn tf(&self, msg: Msg, args: &[(&str, &str)]) -> String {
+        msg.format(self.lang(), args)
+    }
+
+    /// Prompt of a group opened by another member, it isn't theirs to answer or cancel
+    pub fn is_others_prompt(&self) -> bool {
+        self.chat.is_group()
+            && self.chat.state != ClientState::Initial
+            && self
+                .chat
+                .state_user_id
+                .is_some_and(|user_id| user_id != self.from.id as i64)
+    }
+
+    /// State the update is processed in, other members of a group don't see the prompts
+    pub fn state(&self) -> ClientState {
+        match self.is_others_prompt() {
+            true => ClientState::Initial,
+            false => self.chat.state.clone(),
+        }
+    }
+
+    /// Answers to the prompts change the chat too, so they're also restricted in groups
+    pub fn requires_admin(&self) -> bool {
+        match (self.state(), &self.command) {
+            (ClientState::AddVehicle | ClientState::SetEmail, Command::UnknownCommand(_)) => true,
+            (_, command) => command.requires_admin(),
+        }
+    }
+
+    /// Anonymous admins write as the group itself
+    pub async fn is_admin(&self) -> Result<bool, BotError> {
+        if self.sender_chat_id == Some(self.chat.id) {
+            return Ok(true);
+        }
+
+        Ok(self.api.is_chat_admin(self.chat.id, self.from.id).await?)
+    }
+
+    /// Waits for the next message of the user in `state`. Groups get a reply for the user,
+    /// otherwise the bot wouldn't get the answer with the privacy mode on
+    pub async fn prompt(&self, text: &str, state: ClientState) -> Result<(), BotError> {
+        match self.chat.is_group() {
+            true => {
+                self.api
+                    .send_force_reply(self.chat.id, self.with_mention(text))
+                    .await?
+            }
+            false => {
+                self.api
+                    .send_message_without_reply(self.chat.id, text)
+                    .await?
+            }
+        };
+
+        self.repo
+            .modify_prompt_state(&self.chat.id, state, self.from.id)
+            .await?;
+        Ok(())
+    }
+
+    /// Buttons pressed by other members only show it to them
+    pub async fn admin_only(&self) -> Result<(), BotError> {
+        match self.callback_query_id {
+            Some(_) => self.alert(self.t(Msg::AdminOnly)).await,
+            None => self.send_message(self.t(Msg::AdminOnly)).await,
+        }
+    }
+
+    /// Stops the spinner of the pressed button, once per update
+    pub async fn answer_callback(&self, text: Option<&str>, alert: bool) -> Result<(), BotError> {
+        let Some(callback_query_id) = &self.callback_query_id else {
+            return Ok(());
+        };
+
+        if self.callback_answered.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        self.api
+            .answer_callback_query(callback_query_id, text, alert)
+            .await?;
+        Ok(())
+    }
+
+    /// Short notice over the chat that goes away on its own
+    pub async fn toast(&self, text: &str) -> Result<(), BotError> {
+        self.answer_callback(Some(text), false).await
+    }
+
+    /// Notice the user has to close
+    pub async fn alert(&self, text: &str) -> Result<(), BotError> {
+        self.answer_callback(Some(text), true).await
+    }
+
+    /// Toast for buttons, nothing changes so there is no need for a message
+    pub async fn notice(&self, text: &str) -> Result<(), BotError> {
+        match self.callback_query_id {
+            Some(_) => self.toast(text).await,
+            None => {
+                self.api
+                    .send_message_without_reply(self.chat.id, text)
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Edits the message of the pressed button, anything else gets a new message
+    pub async fn edit_or_send(
+        &self,
+        text: &str,
+        keyboard: InlineKeyboardMarkup,
+    ) -> Result<(), BotError> {
+        self.edit_or_send_with_parse_mode(text, keyboard, ParseMode::Html)
+            .await
+    }
+
+    pub async fn edit_or_send_with_parse_mode(
+        &self,
+        text: &str,
+        keyboard: InlineKeyboardMarkup,
+        parse_mode: ParseMode,
+    ) -> Result<(), BotError> {
+        match self.editable {
+            true => {
+                self.api
+                    .edit_or_send_message_with_parse_mode(
+                        self.chat.id,
+                        self.message_id,
+                        text,
+                        keyboard,
+                        parse_mode,
+                    )
+                    .await?
+            }
+            false => {
+                self.api
+                    .send_message_with_buttons(self.chat.id, text, keyboard, parse_mode)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Acting user, groups link to them so they're notified
+    pub fn mention(&self) -> String {
+        match self.chat.is_group() {
+            true => format!(
+                "<a href=\"tg://user?id={}\">{}</a>",
+                self.from.id,
+                escape_html(&self.from.first_name)
+            ),
+            false => self.chat.username.clone(),
+        }
+    }
+
+    /// Everybody in a group sees the changes, they say who made them
+    pub fn with_mention(&self, text: &str) -> String {
+        match self.chat.is_group() {
+            true => format!("{}: {text}", self.mention()),
+            false => text.to_string(),
+        }
+    }
+
     pub async fn return_to_initial(&self) -> Result<(), BotError> {
+        // Left for the member that opened it
+        if self.is_others_prompt() {
+            return Ok(());
+        }
+
         self.repo
             .modify_state(&self.chat.id, ClientState::Initial)
             .await?;
@@ -87,40 +555,21 @@ impl UpdateProcessor {
     }
 
     pub async fn unknown_command(&self, command: &str) -> Result<(), BotError> {
-        self.cancel(Some(format!(
-            "Comando '{}' desconocido. Ejecuta /start para ver los comandos disponibles",
-            command
-        )))
-        .await?;
+        self.cancel(Some(self.tf(Msg::UnknownCommand, &[("command", command)])))
+            .await?;
         Ok(())
     }
 
-    pub fn texts_to_buttons<S: AsRef<str> + Into<String>, S2: AsRef<str> + Into<String>>(
-        rows: Vec<Vec<(S, S2)>>,
-        url: bool,
+    pub fn texts_to_buttons<S: Into<String>>(
+        rows: Vec<Vec<(S, CallbackAction)>>,
     ) -> InlineKeyboardMarkup {
-        if url {
-            let vec = rows
-                .into_iter()
-                .map(|row| {
-                    row.into_iter()
-                        .map(|(text, url)| {
-                            InlineKeyboardButton::builder().url(url).text(text).build()
-                        })
-                        .collect()
-                })
-                .collect();
-
-            return InlineKeyboardMarkup::builder().inline_keyboard(vec).build();
-        }
-
         let vec = rows
             .into_iter()
             .map(|row| {
                 row.into_iter()
-                    .map(|(text, command)| {
+                    .map(|(text, action)| {
                         InlineKeyboardButton::builder()
-                            .callback_data(command)
+                            .callback_data(action.encode())
                             .text(text)
                             .build()
                     })
@@ -131,8 +580,26 @@ impl UpdateProcessor {
         InlineKeyboardMarkup::builder().inline_keyboard(vec).build()
     }
 
+    pub fn urls_to_buttons<S: Into<String>, S2: Into<String>>(
+        rows: Vec<Vec<(S, S2)>>,
+    ) -> InlineKeyboardMarkup {
+        let vec = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(text, url)| InlineKeyboardButton::builder().url(url).text(text).build())
+                    .collect()
+            })
+            .collect();
+
+        InlineKeyboardMarkup::builder().inline_keyboard(vec).build()
+    }
+
     pub async fn send_message(&self, text: &str) -> Result<(), BotError> {
-        let text_with_username = format!("Hola, {}!\n{}", self.chat.username, text);
+        let text_with_username = self.tf(
+            Msg::Greeting,
+            &[("username", &self.mention()), ("text", text)],
+        );
 
         self.api
             .send_message(self.chat.id, self.message_id, text_with_username)
@@ -146,14 +613,58 @@ impl UpdateProcessor {
         Ok(())
     }
 
-    pub fn get_parse_iterator(&self) -> SplitAsciiWhitespace<'_> {
-        let mut iter = self
-            .callback_data
-            .as_ref()
-            .unwrap()
-            .split_ascii_whitespace();
-        iter.next();
-        iter
+    /// Text typed after the command, buttons carry their arguments in `action`
+    pub fn argument(&self) -> Option<String> {
+        Command::parse(&self.text).1
+    }
+
+    /// Plate of the pressed button or typed after the command
+    pub fn plate_argument(&self) -> Result<Plate, ArgError> {
+        match &self.action {
+            Ok(action) => action
+                .as_ref()
+                .and_then(CallbackAction::plate)
+                .cloned()
+                .ok_or(ArgError::Missing),
+            Err(err) => Err(err.clone()),
+        }
+    }
+
+    /// Like `plate_argument`, but plates that don't parse are only accepted when the chat
+    /// already follows them
+    pub async fn followed_plate_argument(&self) -> Result<Result<Plate, ArgError>, BotError> {
+        let plate = match self.plate_argument() {
+            Ok(plate) if plate.kind() == PlateKind::Unverified => plate,
+            result => return Ok(result),
+        };
+
+        let followed = self
+            .repo
+            .get_vehicles_by_chat_id(&self.chat.id)
+            .await?
+            .iter()
+            .any(|vehicle| vehicle.plate == plate.as_str());
+
+        if followed {
+            Ok(Ok(plate))
+        } else {
+            Ok(Plate::parse(plate.as_str()).map_err(ArgError::from))
+        }
+    }
+
+    /// Tells what was wrong with the arguments, with an example of the command
+    pub fn arg_error_text(&self, err: &ArgError) -> String {
+        let usage = self.command.usage().unwrap_or_default();
+        match err {
+            ArgError::InvalidPlate(err) => invalid_plate_text(err, self.lang()),
+            ArgError::Missing => self.tf(Msg::MissingArgument, &[("usage", usage)]),
+            ArgError::Unexpected(arg)
+            | ArgError::InvalidLanguage(arg)
+            | ArgError::InvalidTimezone(arg) => self.tf(
+                Msg::InvalidArgument,
+                &[("argument", &escape_html(arg)), ("usage", usage)],
+            ),
+        }
     }
 
     pub async fn send_long_text(
@@ -162,11 +673,7 @@ impl UpdateProcessor {
         rows: InlineKeyboardMarkup,
         parse_mode: ParseMode,
     ) -> Result<(), BotError> {
-        let chunks: Vec<&str> = text
-            .as_bytes()
-            .chunks(1000)
-            .map(|chunk| std::str::from_utf8(chunk).unwrap())
-            .collect();
+        let chunks = split_lines(&text, MAX_CHUNK_LEN);
 
         for (i, chunk) in chunks.iter().enumerate() {
             if i == chunks.len() - 1 {
@@ -176,10 +683,369 @@ impl UpdateProcessor {
             } else {
                 // Otherwise, send a regular message
                 self.api
-                    .send_message_without_reply(self.chat.id, *chunk)
+                    .send_message_without_reply(self.chat.id, chunk.as_str())
                     .await?;
             }
         }
         Ok(())
     }
 }
+
+/// Bytes of each message sent by `send_long_text`
+const MAX_CHUNK_LEN: usize = 1000;
+
+/// Chunks of up to `max_len` bytes cut between lines, so no HTML tag or entity is split.
+/// Only a line longer than that is cut, between characters
+fn split_lines(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut chunk = String::new();
+
+    for line in text.split('\n') {
+        if !chunk.is_empty() && chunk.len() + 1 + line.len() > max_len {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+
+        let mut line = line;
+        while line.len() > max_len {
+            let mut end = max_len;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            chunks.push(line[..end].to_string());
+            line = &line[end..];
+        }
+
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        chunk.push_str(line);
+    }
+    chunks.push(chunk);
+
+    // Telegram refuses empty messages
+    chunks
+        .into_iter()
+        .map(|chunk| chunk.trim_matches('\n').to_string())
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+    use crate::db::{model::chat::Chat, Repo};
+    use crate::plate::PlateError;
+    use crate::telegram::client::ApiClient;
+    use frankenstein::User;
+
+    #[test]
+    fn test_listed_commands_are_parsed() {
+        for command in Command::ALL {
+            assert_eq!(
+                Command::from_str(&format!("/{}", command.name())),
+                Ok(command.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_command_menus() {
+        let names = |menu: CommandMenu| -> Vec<String> {
+            menu.commands(Lang::En)
+                .into_iter()
+                .map(|command| command.command)
+                .collect()
+        };
+
+        let private = names(CommandMenu::Private);
+        assert!(private.contains(&"channels".to_string()));
+        assert!(private.contains(&"start_fetch".to_string()));
+        assert!(private.contains(&"check".to_string()));
+        assert!(!private.contains(&"start_back".to_string()));
+
+        let group = names(CommandMenu::Group);
+        assert!(!group.contains(&"channels".to_string()));
+        assert!(!group.contains(&"start_fetch".to_string()));
+        assert!(!group.contains(&"delete_vehicle".to_string()));
+        assert!(group.contains(&"get_my_vehicles".to_string()));
+
+        let admins = names(CommandMenu::GroupAdmins);
+        assert!(!admins.contains(&"channels".to_string()));
+        assert!(admins.contains(&"start_fetch".to_string()));
+        assert!(admins.contains(&"delete_vehicle".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_answer_callback_once() {
+        let repo: &'static Repo = Box::leak(Box::new(
+            Repo::new_for_test("test_answer_callback_once")
+                .await
+                .unwrap(),
+        ));
+        let mut server = mockito::Server::new_async().await;
+
+        let answered = server
+            .mock("POST", "/answerCallbackQuery")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"callback_query_id": "42", "text": "Hi", "show_alert": false}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"ok": true, "result": true}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let api: &'static ApiClient = Box::leak(Box::new(ApiClient::new_url(server.url()).await));
+        let user = User::builder()
+            .id(1)
+            .is_bot(false)
+            .first_name("Test".to_string())
+            .build();
+        let chat = Chat::builder()
+            .id(1)
+            .user_id(1)
+            .username("Test".to_string())
+            .state(ClientState::Initial)
+            .active(false)
+            .build();
+
+        let processor = UpdateProcessor::builder()
+            .api(api)
+            .repo(repo)
+            .text(String::new())
+            .action(Ok(Some(CallbackAction::StartFetch)))
+            .callback_query_id("42".to_string())
+            .message_id(1)
+            .editable(true)
+            .from(user)
+            .command(Command::StartFetch)
+            .chat(chat)
+            .is_first(false)
+            .build();
+
+        // The toast of the handler wins over the answer made after every update
+        processor.toast("Hi").await.unwrap();
+        processor.answer_callback(None, false).await.unwrap();
+
+        answered.assert_async().await;
+        repo.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_group_prompt_belongs_to_member() {
+        let repo: &'static Repo = Box::leak(Box::new(
+            Repo::new_for_test("test_group_prompt_belongs_to_member")
+                .await
+                .unwrap(),
+        ));
+        // Nothing is asked to Telegram
+        let server = mockito::Server::new_async().await;
+        let api: &'static ApiClient = Box::leak(Box::new(ApiClient::new_url(server.url()).await));
+
+        let (chat, _) = repo
+            .find_or_create_chat(&-100, 1, "Familia", &None, ChatKind::Group)
+            .await
+            .unwrap();
+        repo.modify_prompt_state(&chat.id, ClientState::AddVehicle, 1)
+            .await
+            .unwrap();
+        let chat = repo.get_chat(&chat.id).await.unwrap();
+
+        let processor = |user_id: u64, command: Command| {
+            let user = User::builder()
+                .id(user_id)
+                .is_bot(false)
+                .first_name("Test".to_string())
+                .build();
+            UpdateProcessor::builder()
+                .api(api)
+                .repo(repo)
+                .text("1234BCD".to_string())
+                .message_id(1)
+                .from(user)
+                .command(command)
+                .chat(chat.clone())
+                .is_first(false)
+                .build()
+        };
+
+        // The answer of the admin that opened the prompt
+        let admin = processor(1, Command::UnknownCommand("1234BCD".to_string()));
+        assert!(!admin.is_others_prompt());
+        assert_eq!(admin.state(), ClientState::AddVehicle);
+        assert!(admin.requires_admin());
+
+        // Other members keep talking, they can't close the prompt
+        let member = processor(2, Command::UnknownCommand("1234BCD".to_string()));
+        assert!(member.is_others_prompt());
+        assert_eq!(member.state(), ClientState::Initial);
+        assert!(!member.requires_admin());
+        member.return_to_initial().await.unwrap();
+        let stored = repo.get_chat(&chat.id).await.unwrap();
+        assert_eq!(stored.state, ClientState::AddVehicle);
+        assert_eq!(stored.state_user_id, Some(1));
+
+        // Anonymous admins send as the group
+        let mut anonymous = processor(1087968824, Command::MyAddedVehicles);
+        anonymous.sender_chat_id = Some(-100);
+        assert!(anonymous.is_admin().await.unwrap());
+
+        repo.cleanup_test_db().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_followed_unverified_plate() {
+        let repo: &'static Repo = Box::leak(Box::new(
+            Repo::new_for_test("test_followed_unverified_plate")
+                .await
+                .unwrap(),
+        ));
+        let server = mockito::Server::new_async().await;
+        let api: &'static ApiClient = Box::leak(Box::new(ApiClient::new_url(server.url()).await));
+
+        let (chat, _) = repo
+            .find_or_create_chat(&1, 1, "Test", &None, ChatKind::Private)
+            .await
+            .unwrap();
+        // Followed before plates were validated
+        repo.find_or_create_vehicle("1234ABC").await.unwrap();
+        repo.create_subscription("1234ABC", chat.id).await.unwrap();
+
+        let processor = |plate: &str| {
+            let user = User::builder()
+                .id(1)
+                .is_bot(false)
+                .first_name("Test".to_string())
+                .build();
+            UpdateProcessor::builder()
+                .api(api)
+                .repo(repo)
+                .text(format!("/delete_vehicle {plate}"))
+                .action(CallbackAction::from_command(
+                    &Command::RemoveVehicle,
+                    Some(plate),
+                ))
+                .message_id(1)
+                .from(user)
+                .command(Command::RemoveVehicle)
+                .chat(chat.clone())
+                .is_first(false)
+                .build()
+        };
+
+        assert_eq!(
+            processor("1234 abc")
+                .followed_plate_argument()
+                .await
+                .unwrap(),
+            Ok(Plate::unverified("1234ABC").unwrap())
+        );
+        assert_eq!(
+            processor("5678 abc")
+                .followed_plate_argument()
+                .await
+                .unwrap(),
+            Err(ArgError::InvalidPlate(PlateError::UnknownFormat(
+                "5678ABC".to_string()
+            )))
+        );
+        assert_eq!(
+            processor("5678 bcd")
+                .followed_plate_argument()
+                .await
+                .unwrap(),
+            Ok(Plate::parse("5678BCD").unwrap())
+        );
+
+        repo.cleanup_test_db().await.unwrap();
+    }
+
+    #[test]
+    fn test_split_long_text() {
+        let lines: Vec<String> = (0..100)
+            .map(|i| match i % 2 {
+                0 => Msg::BulkAlreadyFound.format(
+                    Lang::Es,
+                    &[("plate", &format!("{i:04}BCD")), ("date", "4 de noviembre")],
+                ),
+                _ => {
+                    let reason = escape_html(&format!("'{i:04}ABC' <no es válida>"));
+                    Msg::BulkInvalid.format(Lang::Es, &[("reason", &reason)])
+                }
+            })
+            .collect();
+        let text = format!(
+            "<b>Matrículas añadidas: 0 de 100</b>\n\n{}",
+            lines.join("\n")
+        );
+
+        let chunks = split_lines(&text, MAX_CHUNK_LEN);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.len() <= MAX_CHUNK_LEN);
+        }
+        // Every line is sent whole, with its tags and entities
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            assert!(
+                chunks.iter().any(|chunk| chunk.lines().any(|l| l == line)),
+                "{line}"
+            );
+        }
+        assert_eq!(
+            chunks.join("\n").replace("\n\n", "\n"),
+            text.replace("\n\n", "\n")
+        );
+
+        // A single line too long is cut between characters
+        let chunks = split_lines(&"ñ".repeat(600), MAX_CHUNK_LEN);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.concat(), "ñ".repeat(600));
+    }
+
+    #[test]
+    fn test_command_addressed_to_bot() {
+        let bot = BOT_NAME.trim_start_matches('@');
+
+        assert_eq!(
+            Command::from_str(&format!("/help@{bot}")),
+            Ok(Command::Help)
+        );
+        assert_eq!(
+            Command::from_str(&format!("/start@{} ", bot.to_uppercase())),
+            Ok(Command::Start)
+        );
+        assert_eq!(
+            Command::from_str("/help@other_bot"),
+            Ok(Command::UnknownCommand("/help@other_bot".to_string()))
+        );
+        assert!(Command::AddVehicle.requires_admin());
+        assert!(!Command::MyAddedVehicles.requires_admin());
+    }
+
+    #[test]
+    fn test_command_arguments() {
+        let bot = BOT_NAME.trim_start_matches('@');
+
+        assert_eq!(
+            Command::parse("/check 1234 BCD"),
+            (Command::VehicleInfo, Some("1234 BCD".to_string()))
+        );
+        assert_eq!(
+            Command::parse(&format!("/check_vehicle@{bot}  1234BCD ")),
+            (Command::VehicleInfo, Some("1234BCD".to_string()))
+        );
+        assert_eq!(
+            Command::parse("/delete_vehicle"),
+            (Command::RemoveVehicle, None)
+        );
+        assert_eq!(
+            Command::parse("/start add_1234BCD"),
+            (Command::Start, Some("add_1234BCD".to_string()))
+        );
+        // Plates typed after the prompt
+        assert_eq!(
+            Command::parse("1234 BCD"),
+            (Command::UnknownCommand("1234 BCD".to_string()), None)
+        );
+    }
+}
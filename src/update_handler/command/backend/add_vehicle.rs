@@ -1,60 +1,203 @@
+use chrono::Utc;
+use frankenstein::{Document, ParseMode};
+
 use crate::{
-    db::model::vehicle::Vehicle,
-    tasks::fetch::FetchTask,
-    tucochedana::client::TuCocheDanaClient,
-    update_handler::process_update::{TaskToManage, UpdateProcessor},
+    db::{
+        model::{subscription::SubscribeOutcome, vehicle::Vehicle},
+        BotDbError,
+    },
+    i18n::Msg,
+    metrics,
+    plate::{parse_plates, Plate, PlateError},
+    tasks::partner_webhook::{VehicleEvent, WebhookDeliveryTask},
+    telegram::client::escape_html,
+    tucochedana::{
+        client::TuCocheDanaClient,
+        lookup::{FoundVehicle, LookupOutcome},
+    },
+    update_handler::{
+        args::ArgError,
+        callback::CallbackAction,
+        command::{frontend::add_vehicle::invalid_plate_text, Command},
+        process_update::UpdateProcessor,
+    },
     BotError,
 };
 
+/// `.txt` and `.csv` files, the only ones read as lists of plates
+pub fn is_plates_document(document: &Document) -> bool {
+    let name = document
+        .file_name
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    name.ends_with(".txt")
+        || name.ends_with(".csv")
+        || matches!(
+            document.mime_type.as_deref(),
+            Some("text/plain" | "text/csv")
+        )
+}
+
+/// Most plates added with one message
+pub const MAX_BULK_PLATES: usize = 100;
+
+/// Largest file with a list of plates
+pub const MAX_DOCUMENT_BYTES: usize = 64 * 1024;
+
 impl UpdateProcessor {
-    fn sanitize_input(input: &str) -> Option<String> {
-        if input.contains(',') {
-            return None;
+    /// `/add_vehicle <plates>` from a button or typed, the plates written after the prompt
+    /// or a file with a list of them
+    pub async fn add_vehicle(&self) -> Result<(), BotError> {
+        let input = match (&self.document, &self.command, &self.action) {
+            (Some(document), _, _) => match self.read_plates_document(document).await? {
+                Some(input) => input,
+                None => {
+                    let size = (MAX_DOCUMENT_BYTES / 1024).to_string();
+                    let text = self.tf(Msg::InvalidPlatesDocument, &[("size", &size)]);
+                    return self.add_vehicle_prompt(Some(&text)).await;
+                }
+            },
+            (None, Command::AddVehicle, Ok(Some(CallbackAction::FollowVehicle(plate)))) => {
+                plate.to_string()
+            }
+            (None, Command::AddVehicle, _) => self.argument().unwrap_or_default(),
+            (None, _, _) => self.text.clone(),
+        };
+
+        let mut plates = parse_plates(&input);
+        if plates.len() > 1 {
+            return self.add_vehicles(plates).await;
         }
 
-        Some(String::from(input))
+        let plate = match plates.pop() {
+            Some(plate) => plate.map_err(ArgError::from),
+            None if self.command == Command::AddVehicle => Err(ArgError::Missing),
+            None => Err(PlateError::Empty.into()),
+        };
+
+        match plate {
+            Ok(plate) => self.follow_vehicle(plate.to_string()).await,
+            Err(err) => {
+                self.add_vehicle_prompt(Some(&self.arg_error_text(&err)))
+                    .await
+            }
+        }
     }
 
-    pub async fn add_vehicle(&self) -> Result<TaskToManage, BotError> {
-        let Some(plate) = Self::sanitize_input(&self.text) else {
-            self.add_vehicle_prompt(Some(
-                "La matrícula introducida sigue un formato incorrecto, pruebe de nuevo",
-            ))
-            .await?;
-            return Ok(TaskToManage::NoTask);
-        };
+    /// Text of `.txt` and `.csv` files, `None` for any other file
+    async fn read_plates_document(&self, document: &Document) -> Result<Option<String>, BotError> {
+        let size = document.file_size.unwrap_or_default() as usize;
+
+        if !is_plates_document(document) || size > MAX_DOCUMENT_BYTES {
+            return Ok(None);
+        }
+
+        let bytes = self.api.download_file(&document.file_id).await?;
+        if bytes.len() > MAX_DOCUMENT_BYTES {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    /// Follows every valid plate of the list at once, they aren't looked up so the sweep
+    /// doesn't have to wait for dozens of lookups
+    pub async fn add_vehicles(
+        &self,
+        plates: Vec<Result<Plate, PlateError>>,
+    ) -> Result<(), BotError> {
+        if plates.len() > MAX_BULK_PLATES {
+            let text = self.tf(Msg::BulkTooMany, &[("max", &MAX_BULK_PLATES.to_string())]);
+            return self.add_vehicle_prompt(Some(&text)).await;
+        }
+
+        let total = plates.len();
+        let (valid, invalid): (Vec<_>, Vec<_>) = plates.into_iter().partition(Result::is_ok);
+        let valid: Vec<String> = valid.into_iter().flatten().map(String::from).collect();
+        log::info!("Adding {} vehicles", valid.len());
+
+        let outcomes = self.repo.create_subscriptions(&valid, self.chat.id).await?;
+
+        let locale = self.locale();
+        let mut added = 0;
+        let mut lines: Vec<String> = valid
+            .iter()
+            .zip(outcomes)
+            .map(|(plate, outcome)| {
+                let plate = plate.as_str();
+                match outcome {
+                    SubscribeOutcome::Added => {
+                        added += 1;
+                        self.tf(Msg::BulkAdded, &[("plate", plate)])
+                    }
+                    SubscribeOutcome::AlreadyFollowed => {
+                        self.tf(Msg::BulkAlreadyFollowed, &[("plate", plate)])
+                    }
+                    SubscribeOutcome::AlreadyFound(found_at) => self.tf(
+                        Msg::BulkAlreadyFound,
+                        &[
+                            ("plate", plate),
+                            ("date", &locale.format_datetime(&found_at)),
+                        ],
+                    ),
+                }
+            })
+            .collect();
+
+        lines.extend(invalid.into_iter().filter_map(Result::err).map(|err| {
+            let reason = escape_html(&invalid_plate_text(&err, self.lang()));
+            self.tf(Msg::BulkInvalid, &[("reason", &reason)])
+        }));
+
+        let mut text = self.tf(
+            Msg::BulkSummary,
+            &[("added", &added.to_string()), ("total", &total.to_string())],
+        );
+        text.push_str("\n\n");
+        text.push_str(&lines.join("\n"));
+        if added > 0 && self.chat.active {
+            text.push_str("\n\n");
+            text.push_str(self.t(Msg::BulkWithAlerts));
+        }
+
+        let keyboard = Self::texts_to_buttons(vec![
+            vec![(self.t(Msg::StartMyVehicles), CallbackAction::MyVehicles)],
+            vec![(self.t(Msg::Back), CallbackAction::Menu)],
+        ]);
+
+        self.send_long_text(self.with_mention(&text), keyboard, ParseMode::Html)
+            .await
+    }
+
+    /// Subscribes the chat to the plate, the vehicle is looked up first in case it's been found
+    pub async fn follow_vehicle(&self, plate: String) -> Result<(), BotError> {
         log::info!("Adding vehicle {plate}");
         let client = TuCocheDanaClient::new(None).await;
 
-        let found_at = client.is_vehicle_found(&plate).await.ok();
-
-        let vehicle = Vehicle::builder()
-            .plate(plate.clone())
-            .subscribers_ids(format!("{},", self.chat.id))
-            .maybe_found_at(found_at)
-            .build();
+        let (found, lookup_note) = match client.lookup(&plate).await {
+            LookupOutcome::Found(found) => (Some(found), ""),
+            LookupOutcome::NotFound => (None, ""),
+            outcome => {
+                log::warn!("Couldn't check {plate} while adding it: {outcome:?}");
+                (None, self.t(Msg::LookupUnavailable))
+            }
+        };
 
-        if found_at.is_some() {
-            self.api
-                .send_message_without_reply(self.chat.id, vehicle.found_at_to_text())
-                .await?;
-            return Ok(TaskToManage::NoTask);
+        if let Some(found) = found {
+            return self.follow_found_vehicle(&plate, found).await;
         }
+        let vehicle = Vehicle::builder().plate(plate.clone()).build();
 
+        let args = [("plate", plate.as_str()), ("note", lookup_note)];
         let text = if self.repo.insert_vehicle(vehicle).await.is_ok() {
-            self.repo
-                .append_subscription_to_chat(&plate, &self.chat.id)
-                .await?;
-            //Si el vehículo es añadido por un usuario activo -> Lanzar task
+            self.repo.create_subscription(&plate, self.chat.id).await?;
+            //Si el vehículo es añadido por un usuario activo -> Se incluirá en el barrido
             if self.chat.active {
-                self.repo.create_subscription(&plate, self.chat.id).await?;
-                self.get_vehicles(Some(&format!("Vehículo {plate} añadido✅\ncomo tiene las alertas activas, le avisaremos si se registra")))
-                    .await?;
-                return Ok(TaskToManage::FetchTask(
-                    FetchTask::builder().plate(plate).build(),
-                ));
+                self.tf(Msg::VehicleAddedWithAlerts, &args)
             } else {
-                format!("Vehículo {plate} añadido ✅")
+                self.tf(Msg::VehicleAdded, &args)
             }
         } else if self
             .repo
@@ -62,28 +205,65 @@ impl UpdateProcessor {
             .await
             .is_err()
         {
-            format!("El vehículo {plate} ya ha sido añadido previamente 👀")
+            self.tf(Msg::VehicleAlreadyAdded, &args)
         } else {
-            format!("El vehículo {plate} ya ha sido registrado por otro usuario, le añadiremos como interesado")
+            self.tf(Msg::VehicleAddedByOther, &args)
         };
 
-        self.get_vehicles(Some(&text)).await?;
+        self.get_vehicles(Some(&text)).await
+    }
+
+    /// Stores the vehicle the lookup found before following it, partners are told as if
+    /// the sweep had found it
+    async fn follow_found_vehicle(&self, plate: &str, found: FoundVehicle) -> Result<(), BotError> {
+        let mut vehicle = self.repo.find_or_create_vehicle(plate).await?;
+
+        // Found before the chat follows it, so it isn't notified of what it's about to read
+        if vehicle.found_at.is_none() {
+            metrics::VEHICLES_FOUND_TOTAL.inc();
+            let previous_status = vehicle.check_status.take();
+            let checked_at = Utc::now();
+            self.repo
+                .modify_checked_vehicle(plate, checked_at, Some("found"))
+                .await?;
+            self.repo
+                .modify_found_vehicle(plate, found.found_at, &found.details)
+                .await?;
+            vehicle.checked_at = Some(checked_at);
+            vehicle.check_status = Some("found".to_string());
+            vehicle.found_at = Some(found.found_at);
+            vehicle.details = found.details;
+
+            WebhookDeliveryTask::queue_event(
+                self.repo,
+                VehicleEvent::Found,
+                &vehicle,
+                previous_status.as_deref(),
+            )
+            .await?;
+        }
+
+        match self.repo.create_subscription(plate, self.chat.id).await {
+            Ok(()) | Err(BotDbError::AlreadySubscribedError(_, _)) => (),
+            Err(err) => return Err(err.into()),
+        }
 
-        Ok(TaskToManage::NoTask)
+        self.api
+            .send_message_without_reply(self.chat.id, vehicle.found_at_to_text(&self.locale()))
+            .await?;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod add_vehicle_tests {
-    use std::sync::Arc;
-
     use frankenstein::{Chat, Message, Update, UpdateContent, User};
-    use tokio::sync::Mutex;
 
     use crate::db::{
-        model::{self},
+        model::{self, vehicle::FoundDetails},
         Repo,
     };
+    use crate::telegram::client::ApiClient;
 
     use super::*;
 
@@ -94,9 +274,8 @@ mod add_vehicle_tests {
         pretty_env_logger::init();
 
         let repo = Repo::repo().await.unwrap();
-        let test_queue = Repo::create_testing_queue(repo, true).await.unwrap();
 
-        let plate = "NUEVA789";
+        let plate = "7890NVS";
 
         let db_chat = repo.get_testing_chat().await.unwrap();
 
@@ -130,7 +309,7 @@ mod add_vehicle_tests {
         let content: UpdateContent = UpdateContent::Message(message);
         let update: Update = Update::builder().update_id(10000).content(content).build();
 
-        match UpdateProcessor::run(&update, Arc::new(Mutex::new(test_queue))).await {
+        match UpdateProcessor::run(&update).await {
             Ok(processor) => {
                 log::info!("{:#?}", processor);
             }
@@ -142,10 +321,119 @@ mod add_vehicle_tests {
         };
 
         let db_chat = repo.get_chat(&db_chat.id).await.unwrap();
+        let vehicles = repo.get_vehicles_by_chat_id(&db_chat.id).await.unwrap();
 
         assert_eq!(db_chat.state, model::client_state::ClientState::Initial);
-        assert!(db_chat.subscribed_vehicles.is_some_and(|subs| subs
-            .split(',')
-            .any(|subbed_vehicle| subbed_vehicle == plate)));
+        assert!(vehicles
+            .iter()
+            .any(|subbed_vehicle| subbed_vehicle.plate == plate));
+    }
+
+    #[tokio::test]
+    async fn test_follow_found_vehicle() {
+        let repo: &'static Repo = Box::leak(Box::new(
+            Repo::new_for_test("test_follow_found_vehicle")
+                .await
+                .unwrap(),
+        ));
+        let mut server = mockito::Server::new_async().await;
+        let sent = server
+            .mock("POST", "/sendMessage")
+            .with_status(200)
+            .with_body(
+                r#"{"ok": true, "result": {"message_id": 2, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let api: &'static ApiClient = Box::leak(Box::new(ApiClient::new_url(server.url()).await));
+
+        // Registered by a partner, nobody has found it yet
+        repo.find_or_create_vehicle("1234BCD").await.unwrap();
+        let (api_key, _) = repo.create_api_key("Ayuntamiento").await.unwrap();
+        repo.create_api_subscription(api_key.id, "1234BCD", None)
+            .await
+            .unwrap();
+        repo.create_partner_webhook(api_key.id, "https://example.com/hooks", None)
+            .await
+            .unwrap();
+
+        let user = User::builder()
+            .id(1)
+            .is_bot(false)
+            .first_name("Test".to_string())
+            .build();
+        let processor = UpdateProcessor::builder()
+            .api(api)
+            .repo(repo)
+            .text("/add_vehicle 1234BCD".to_string())
+            .message_id(1)
+            .from(user)
+            .command(Command::AddVehicle)
+            .chat(repo.get_chat(&1).await.unwrap())
+            .is_first(false)
+            .build();
+
+        let found = FoundVehicle {
+            found_at: Utc::now(),
+            details: FoundDetails {
+                location: Some("Campa de Ribarroja".to_string()),
+                ..Default::default()
+            },
+        };
+        processor
+            .follow_found_vehicle("1234BCD", found)
+            .await
+            .unwrap();
+        sent.assert_async().await;
+
+        let vehicle = repo.get_vehicle("1234BCD").await.unwrap();
+        assert!(vehicle.found_at.is_some());
+        assert_eq!(vehicle.check_status.as_deref(), Some("found"));
+        assert_eq!(
+            vehicle.details.location.as_deref(),
+            Some("Campa de Ribarroja")
+        );
+        assert!(repo
+            .get_vehicles_by_chat_id(&1)
+            .await
+            .unwrap()
+            .iter()
+            .any(|vehicle| vehicle.plate == "1234BCD"));
+
+        let deliveries = repo.get_pending_webhook_deliveries().await.unwrap();
+        assert_eq!(
+            deliveries
+                .iter()
+                .map(|delivery| (delivery.plate.as_str(), delivery.event.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("1234BCD", "vehicle.found")]
+        );
+
+        repo.cleanup_test_db().await.unwrap();
+    }
+
+    #[test]
+    fn test_is_plates_document() {
+        let document = |name: Option<&str>, mime_type: Option<&str>| {
+            Document::builder()
+                .file_id("f1")
+                .file_unique_id("u1")
+                .maybe_file_name(name.map(str::to_string))
+                .maybe_mime_type(mime_type.map(str::to_string))
+                .build()
+        };
+
+        assert!(is_plates_document(&document(Some("Flota.CSV"), None)));
+        assert!(is_plates_document(&document(Some("plates.txt"), None)));
+        assert!(is_plates_document(&document(None, Some("text/plain"))));
+        assert!(!is_plates_document(&document(
+            Some("photo.jpg"),
+            Some("image/jpeg")
+        )));
+        assert!(!is_plates_document(&document(
+            Some("invoice.pdf"),
+            Some("application/pdf")
+        )));
     }
 }
@@ -1,3 +1,5 @@
+use crate::i18n::Msg;
+use crate::update_handler::callback::CallbackError;
 use crate::update_handler::process_update::UpdateProcessor;
 use crate::BotError;
 
@@ -7,12 +9,24 @@ impl UpdateProcessor {
 
         let text: String = match custom_message {
             Some(message) => message,
-            None => "Operación cancelada".to_string(),
+            None => self.t(Msg::Cancelled).to_string(),
         };
         self.send_message(&text).await?;
         self.start_message(None).await
     }
 
+    /// Old or forged buttons are answered with the current menu
+    pub async fn invalid_button(&self, err: &CallbackError) -> Result<(), BotError> {
+        let text = match err {
+            CallbackError::Outdated(_) => self.t(Msg::ButtonOutdated),
+            _ => self.t(Msg::ButtonInvalid),
+        };
+        self.alert(text).await?;
+
+        self.return_to_initial().await?;
+        self.start_message(None).await
+    }
+
     pub async fn revert_state(&self) -> Result<(), BotError> {
         self.cancel(None).await
     }
@@ -0,0 +1,73 @@
+use lettre::Address;
+
+use crate::notifier::EmailNotifier;
+
+use crate::{
+    db::model::chat_channel::NotificationChannel,
+    i18n::Msg,
+    telegram::client::escape_html,
+    update_handler::{command::frontend::channels::is_enabled, process_update::UpdateProcessor},
+    BotError,
+};
+
+impl UpdateProcessor {
+    /// Stores the address written after `/set_email`
+    pub async fn set_email(&self) -> Result<(), BotError> {
+        if !EmailNotifier::is_configured() {
+            return self
+                .channels_menu(Some(self.t(Msg::EmailUnavailable)))
+                .await;
+        }
+
+        let text = self.text.trim();
+        let address = match text.parse::<Address>() {
+            Ok(address) => address,
+            Err(_) => {
+                let retry = self.tf(Msg::InvalidEmail, &[("address", &escape_html(text))]);
+                return self.set_email_prompt(Some(&retry)).await;
+            }
+        };
+
+        self.repo
+            .modify_chat_channel(
+                &self.chat.id,
+                NotificationChannel::Email,
+                Some(address.as_ref()),
+                true,
+            )
+            .await?;
+
+        let text = self.tf(
+            Msg::EmailAdded,
+            &[("address", &escape_html(address.as_ref()))],
+        );
+        self.channels_menu(Some(&text)).await
+    }
+
+    pub async fn toggle_channel(
+        &self,
+        channel: NotificationChannel,
+        enabled: bool,
+    ) -> Result<(), BotError> {
+        let channels = self.repo.get_chat_channels(&self.chat.id).await?;
+
+        // Someone has to be told when the vehicle is found
+        let others_enabled = [NotificationChannel::Telegram, NotificationChannel::Email]
+            .into_iter()
+            .filter(|other| *other != channel)
+            .any(|other| is_enabled(&channels, other));
+        if !enabled && !others_enabled {
+            return self.channels_menu(Some(self.t(Msg::ChannelNeeded))).await;
+        }
+
+        self.repo
+            .modify_chat_channel(&self.chat.id, channel, None, enabled)
+            .await?;
+
+        let text = match enabled {
+            true => self.t(Msg::ChannelEnabled),
+            false => self.t(Msg::ChannelDisabled),
+        };
+        self.channels_menu(Some(text)).await
+    }
+}
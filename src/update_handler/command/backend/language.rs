@@ -0,0 +1,27 @@
+use crate::{
+    i18n::Msg,
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
+
+impl UpdateProcessor {
+    /// `/language <code>` changes the language, the menu is shown without a valid code
+    pub async fn language(&mut self) -> Result<(), BotError> {
+        let lang = match &self.action {
+            Ok(Some(CallbackAction::SetLanguage(lang))) => *lang,
+            Err(err) => {
+                self.notice(&self.arg_error_text(err)).await?;
+                return self.language_menu().await;
+            }
+            Ok(_) => return self.language_menu().await,
+        };
+
+        self.repo.modify_language_chat(&self.chat.id, lang).await?;
+        self.chat.language_code = Some(lang.code().to_string());
+
+        self.start_message(Some(
+            &self.tf(Msg::LanguageChanged, &[("language", lang.name())]),
+        ))
+        .await
+    }
+}
@@ -1,42 +1,32 @@
-use crate::{
-    db::BotDbError,
-    update_handler::process_update::{TaskToManage, UpdateProcessor},
-    BotError,
-};
+use crate::{db::BotDbError, i18n::Msg, update_handler::process_update::UpdateProcessor, BotError};
 
 impl UpdateProcessor {
-    pub async fn remove_vehicle(&self) -> Result<TaskToManage, BotError> {
-        let mut iter = self.get_parse_iterator();
-        let plate: &str = iter.next().unwrap();
+    pub async fn remove_vehicle(&self) -> Result<(), BotError> {
+        let plate = match self.followed_plate_argument().await? {
+            Ok(plate) => plate,
+            Err(err) => {
+                return self.get_vehicles(Some(&self.arg_error_text(&err))).await;
+            }
+        };
+        let plate = plate.as_str();
 
-        // 1. Remove it from chat.subscribed_vehicles
-        // 2. Remove chat from vehicle subscribers_ids
+        // Vehicles without subscribers are left out of the sweep on their own
         match self.repo.end_subscription(plate, self.chat.id).await {
-            Ok((n_subscribers, n_subscriptions)) => {
+            Ok((_, n_subscriptions)) => {
                 if n_subscriptions == 0 {
                     self.repo.modify_active_chat(&self.chat.id, false).await?;
-                    self.start_message(Some(&format!(
-                        "El vehículo {plate} ha sido eliminado correctamente✅\n
-                    Hemos desactivado las alertas pues no tiene ningún otro coche añadido"
-                    )))
-                    .await?;
-                } else {
-                    self.get_vehicles(Some(&format!(
-                        "El vehículo {plate} ha sido eliminado correctamente✅"
-                    )))
-                    .await?;
-                };
-                // 3. If vehicle subscribers_ids is empty -> Remove task & delete vehicle
-                if n_subscribers == 0 {
-                    Ok(TaskToManage::RemoveTask(String::from(plate)))
+                    self.start_message(Some(&self.tf(Msg::VehicleRemovedLast, &[("plate", plate)])))
+                        .await
                 } else {
-                    Ok(TaskToManage::NoTask)
+                    self.get_vehicles(Some(&self.tf(Msg::VehicleRemoved, &[("plate", plate)])))
+                        .await
                 }
             }
 
             Err(BotDbError::SubscriptionError(_, _, reason)) => {
-                self.get_vehicles(Some(&reason)).await?;
-                Ok(TaskToManage::NoTask)
+                log::warn!("{reason}");
+                self.get_vehicles(Some(&self.tf(Msg::VehicleNotAdded, &[("plate", plate)])))
+                    .await
             }
             Err(err) => Err(BotError::DbError(err)),
         }
@@ -0,0 +1,59 @@
+use bb8_postgres::tokio_postgres::error::SqlState;
+
+use crate::{
+    db::BotDbError,
+    i18n::Msg,
+    update_handler::{
+        callback::CallbackAction, deep_link::StartPayload, process_update::UpdateProcessor,
+    },
+    BotError,
+};
+
+impl UpdateProcessor {
+    /// `/share_vehicle <plate>` shows a link to follow the plate along with this chat
+    pub async fn share_vehicle(&self) -> Result<(), BotError> {
+        let plate = match self.plate_argument() {
+            Ok(plate) => plate,
+            Err(err) => {
+                return self.get_vehicles(Some(&self.arg_error_text(&err))).await;
+            }
+        };
+        let back = CallbackAction::CheckVehicle(plate.clone());
+        let plate = plate.as_str();
+
+        // Only followed plates can be shared
+        let shared = match self.repo.share_subscription(self.chat.id, plate).await {
+            Ok(shared) => shared,
+            Err(BotDbError::PgError(err))
+                if err.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) =>
+            {
+                return self
+                    .get_vehicles(Some(&self.tf(Msg::VehicleNotAdded, &[("plate", plate)])))
+                    .await;
+            }
+            Err(err) => return Err(BotError::DbError(err)),
+        };
+
+        let url = StartPayload::Share(shared.token).url();
+        let text = self.tf(Msg::ShareLink, &[("plate", plate), ("url", &url)]);
+
+        let keyboard = Self::texts_to_buttons(vec![vec![(self.t(Msg::Back), back)]]);
+
+        self.edit_or_send(&text, keyboard).await?;
+
+        Ok(())
+    }
+
+    /// `share_<token>` links follow the plate of the shared subscription
+    pub async fn join_shared(&self, token: &str) -> Result<(), BotError> {
+        let Some(shared) = self.repo.get_shared_tracking(token).await? else {
+            return self.start_message(Some(self.t(Msg::InvalidLink))).await;
+        };
+
+        if self.chat.is_group() && !self.is_admin().await? {
+            return self.admin_only().await;
+        }
+
+        self.follow_vehicle(shared.plate).await
+    }
+}
@@ -1,43 +1,30 @@
-use crate::{
-    tasks::fetch::FetchTask,
-    update_handler::process_update::{TaskToManage, UpdateProcessor},
-    BotError,
-};
+use crate::{i18n::Msg, update_handler::process_update::UpdateProcessor, BotError};
 
 impl UpdateProcessor {
-    pub async fn start_fetch(&mut self) -> Result<TaskToManage, BotError> {
+    pub async fn start_fetch(&mut self) -> Result<(), BotError> {
         if self.chat.active {
-            self.api
-                .send_message_without_reply(self.chat.id, "Las alertas ya han sido activadas")
-                .await?;
-            return Ok(TaskToManage::NoTask);
+            return self.notice(self.t(Msg::AlertsAlreadyEnabled)).await;
         }
 
         self.repo.modify_active_chat(&self.chat.id, true).await?;
 
         self.chat.active = true;
 
-        let Some(subbs) = &self.chat.subscribed_vehicles else {
-            self.start_message(Some("Debe añadir vehículos para activar las alertas"))
-                .await?;
-            return Ok(TaskToManage::NoTask);
-        };
-
-        let mut tasks = vec![];
+        let vehicles = self.repo.get_vehicles_by_chat_id(&self.chat.id).await?;
 
-        let mut subbs_vec: Vec<&str> = subbs.split(',').collect();
-        subbs_vec.pop(); //Removing whitespace
-
-        for vehicles in subbs_vec {
-            let task = FetchTask::builder().plate(vehicles.to_string()).build();
-            tasks.push(task);
+        if vehicles.is_empty() {
+            return self
+                .start_message(Some(self.t(Msg::AlertsNeedVehicles)))
+                .await;
         }
 
-        self.start_message(
-            Some("Alerta activada correctamente ✅\nle avisaremos si se registra alguno de sus vehículos"),
-        )
-        .await?;
+        // Found vehicles are no longer swept, so tell about them right away
+        for vehicle in vehicles.iter().filter(|vehicle| vehicle.found_at.is_some()) {
+            self.api
+                .send_message_without_reply(self.chat.id, vehicle.found_at_to_text(&self.locale()))
+                .await?;
+        }
 
-        Ok(TaskToManage::FetchTasks(tasks))
+        self.start_message(Some(self.t(Msg::AlertsEnabled))).await
     }
 }
@@ -1,30 +1,15 @@
-use crate::{
-    update_handler::process_update::{TaskToManage, UpdateProcessor},
-    BotError,
-};
+use crate::{i18n::Msg, update_handler::process_update::UpdateProcessor, BotError};
 
 impl UpdateProcessor {
-    pub async fn stop_fetch(&mut self) -> Result<TaskToManage, BotError> {
+    pub async fn stop_fetch(&mut self) -> Result<(), BotError> {
         if !self.chat.active {
-            self.api
-                .send_message_without_reply(self.chat.id, "Las alertas ya han sido desactivadas")
-                .await?;
-            return Ok(TaskToManage::NoTask);
+            return self.notice(self.t(Msg::AlertsAlreadyDisabled)).await;
         }
 
+        // Vehicles without other active subscribers are left out of the sweep
         self.repo.modify_active_chat(&self.chat.id, false).await?;
         self.chat.active = false;
 
-        let result = if let Some(active_subs) = &self.chat.subscribed_vehicles {
-            //If he's the only subscriber, stop fetch
-            Ok(TaskToManage::RemoveTasks(active_subs.to_owned()))
-        } else {
-            Ok(TaskToManage::NoTask)
-        };
-
-        self.start_message(Some("Alertas desactivadas correctamente ✅"))
-            .await?;
-
-        result
+        self.start_message(Some(self.t(Msg::AlertsDisabled))).await
     }
 }
@@ -0,0 +1,27 @@
+use crate::{
+    i18n::Msg,
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
+
+impl UpdateProcessor {
+    /// `/timezone <IANA name>` changes the timezone, the menu is shown without a valid name
+    pub async fn timezone(&mut self) -> Result<(), BotError> {
+        let tz = match &self.action {
+            Ok(Some(CallbackAction::SetTimezone(tz))) => *tz,
+            Err(err) => {
+                self.notice(&self.arg_error_text(err)).await?;
+                return self.timezone_menu().await;
+            }
+            Ok(_) => return self.timezone_menu().await,
+        };
+
+        self.repo.modify_timezone_chat(&self.chat.id, tz).await?;
+        self.chat.timezone = Some(tz.name().to_string());
+
+        self.start_message(Some(
+            &self.tf(Msg::TimezoneChanged, &[("timezone", tz.name())]),
+        ))
+        .await
+    }
+}
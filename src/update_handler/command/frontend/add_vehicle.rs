@@ -1,23 +1,57 @@
 use crate::{
-    db::model::client_state::ClientState, update_handler::process_update::UpdateProcessor, BotError,
+    db::model::client_state::ClientState,
+    i18n::{Lang, Msg},
+    plate::{Plate, PlateError},
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
 };
 
-pub const ADD_VEHICLE_TEXT: &str =
-    "Escribe la matrícula del vehículo del que deseas recibir alertas o /cancel para cancelar";
+/// Tells the user why the plate was rejected
+pub fn invalid_plate_text(error: &PlateError, lang: Lang) -> String {
+    match error {
+        PlateError::Empty => Msg::PlateEmpty.text(lang).to_string(),
+        PlateError::InvalidCharacters(plate) => {
+            Msg::PlateInvalidCharacters.format(lang, &[("plate", plate)])
+        }
+        PlateError::UnknownProvince(plate, province) => {
+            Msg::PlateUnknownProvince.format(lang, &[("plate", plate), ("province", province)])
+        }
+        PlateError::UnknownFormat(plate) => {
+            Msg::PlateUnknownFormat.format(lang, &[("plate", plate)])
+        }
+    }
+}
 
 impl UpdateProcessor {
     pub async fn add_vehicle_prompt(&self, text: Option<&str>) -> Result<(), BotError> {
         let text = match text {
             Some(t) => t,
-            None => ADD_VEHICLE_TEXT,
+            None => self.t(Msg::AddVehiclePrompt),
+        };
+        self.prompt(text, ClientState::AddVehicle).await
+    }
+
+    /// Status of the plate with a button to follow it, opened by `add_<plate>` links
+    pub async fn follow_offer(&self, plate: &Plate) -> Result<(), BotError> {
+        let vehicle = self.repo.find_vehicle(plate.as_str()).await?;
+
+        let status = match &vehicle {
+            Some(vehicle) => vehicle.found_at_to_text(&self.locale()),
+            None => self.tf(Msg::NotTracked, &[("plate", plate.as_str())]),
         };
-        self.api
-            .send_message_without_reply(self.chat.id, text)
-            .await?;
 
-        self.repo
-            .modify_state(&self.chat.id, ClientState::AddVehicle)
-            .await?;
+        let mut rows = vec![];
+        if vehicle.and_then(|vehicle| vehicle.found_at).is_none() {
+            rows.push(vec![(
+                self.tf(Msg::FollowPlate, &[("plate", plate.as_str())]),
+                CallbackAction::FollowVehicle(plate.clone()),
+            )]);
+        }
+        rows.push(vec![(self.t(Msg::Back).to_string(), CallbackAction::Menu)]);
+
+        let keyboard = Self::texts_to_buttons(rows);
+
+        self.edit_or_send(&status, keyboard).await?;
 
         Ok(())
     }
@@ -0,0 +1,86 @@
+use crate::{
+    db::model::{
+        chat_channel::{ChatChannel, NotificationChannel},
+        client_state::ClientState,
+    },
+    i18n::Msg,
+    notifier::EmailNotifier,
+    telegram::client::escape_html,
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
+
+/// Telegram is enabled unless the chat turned it off
+pub fn is_enabled(channels: &[ChatChannel], channel: NotificationChannel) -> bool {
+    channels
+        .iter()
+        .find(|chat_channel| chat_channel.channel == channel)
+        .map(|chat_channel| {
+            chat_channel.enabled
+                && (chat_channel.address.is_some() || channel == NotificationChannel::Telegram)
+        })
+        .unwrap_or(channel == NotificationChannel::Telegram)
+}
+
+impl UpdateProcessor {
+    pub async fn channels_menu(&self, text: Option<&str>) -> Result<(), BotError> {
+        let channels = self.repo.get_chat_channels(&self.chat.id).await?;
+
+        let telegram = is_enabled(&channels, NotificationChannel::Telegram);
+        let email = channels
+            .iter()
+            .find(|chat_channel| chat_channel.channel == NotificationChannel::Email)
+            .filter(|chat_channel| chat_channel.enabled)
+            .and_then(|chat_channel| chat_channel.address.as_deref());
+
+        let mut message = format!(
+            "{}\n\nTelegram: {}",
+            text.unwrap_or(self.t(Msg::ChannelsMenu)),
+            if telegram { "✅" } else { "🔕" }
+        );
+
+        let mut rows: Vec<Vec<(&str, CallbackAction)>> = vec![vec![if telegram {
+            (self.t(Msg::DisableTelegram), CallbackAction::TelegramOff)
+        } else {
+            (self.t(Msg::EnableTelegram), CallbackAction::TelegramOn)
+        }]];
+
+        if EmailNotifier::is_configured() {
+            match email {
+                Some(address) => {
+                    message.push_str(&format!("\nEmail: ✅ {}", escape_html(address)));
+                    rows.push(vec![
+                        (self.t(Msg::ChangeEmail), CallbackAction::SetEmail),
+                        (self.t(Msg::DisableEmail), CallbackAction::EmailOff),
+                    ]);
+                }
+                None => {
+                    message.push_str("\nEmail: 🔕");
+                    rows.push(vec![(self.t(Msg::EnableEmail), CallbackAction::SetEmail)]);
+                }
+            }
+        }
+
+        rows.push(vec![(self.t(Msg::Back), CallbackAction::Menu)]);
+
+        let keyboard = Self::texts_to_buttons(rows);
+
+        self.edit_or_send(&message, keyboard).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_email_prompt(&self, text: Option<&str>) -> Result<(), BotError> {
+        if !EmailNotifier::is_configured() {
+            return self
+                .channels_menu(Some(self.t(Msg::EmailUnavailable)))
+                .await;
+        }
+
+        self.prompt(
+            text.unwrap_or(self.t(Msg::SetEmailPrompt)),
+            ClientState::SetEmail,
+        )
+        .await
+    }
+}
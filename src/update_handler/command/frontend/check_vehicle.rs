@@ -1,22 +1,42 @@
-use crate::{update_handler::process_update::UpdateProcessor, BotError};
-
-const VEHICLE_INFO: &str = "Información más reciente sobre el vehículo";
+use crate::{
+    i18n::Msg,
+    plate::PlateKind,
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
 
 impl UpdateProcessor {
     pub async fn vehicle_info(&self) -> Result<(), BotError> {
-        let mut iter = self.get_parse_iterator();
-        let plate: &str = iter.next().unwrap();
+        let plate = match self.followed_plate_argument().await? {
+            Ok(plate) => plate,
+            Err(err) => {
+                self.get_vehicles(Some(&self.arg_error_text(&err))).await?;
+                return Ok(());
+            }
+        };
 
         // Handling accessing unknow vehicle
-        let vehicle = self.repo.find_or_create_vehicle(plate).await?;
+        let vehicle = self.repo.find_or_create_vehicle(plate.as_str()).await?;
 
-        let rows = vec![vec![("⬅️ Back", "/get_my_vehicles")]];
-        let vec = Self::texts_to_buttons(rows, false);
-        let text = format!("{VEHICLE_INFO}\n\n{}\n", vehicle.found_at_to_text());
+        let mut rows = vec![vec![(self.t(Msg::Back), CallbackAction::MyVehicles)]];
+        // Unverified plates can't be shared, the link would be rejected
+        if plate.kind() != PlateKind::Unverified {
+            rows.insert(
+                0,
+                vec![(
+                    self.t(Msg::ShareVehicle),
+                    CallbackAction::ShareVehicle(plate),
+                )],
+            );
+        }
+        let vec = Self::texts_to_buttons(rows);
+        let text = format!(
+            "{}\n\n{}\n",
+            self.t(Msg::VehicleInfo),
+            vehicle.found_at_to_text(&self.locale())
+        );
 
-        self.api
-            .edit_or_send_message(self.chat.id, self.message_id, &text, vec)
-            .await?;
+        self.edit_or_send(&text, vec).await?;
 
         Ok(())
     }
@@ -1,8 +1,50 @@
+use std::fmt::Write;
+
+use crate::i18n::{Lang, Msg};
+use crate::telegram::client::escape_markdown;
+use crate::update_handler::{callback::CallbackAction, command::Command};
 use crate::BotError;
 
 use crate::update_handler::process_update::UpdateProcessor;
 
-const HELP_TEXT: &str = include_str!("../../../../resources/help.md");
+// Generated from the commands with `tu-coche-dana-bot generate-help`
+const HELP_TEXT_ES: &str = include_str!("../../../../resources/help.md");
+const HELP_TEXT_CA: &str = include_str!("../../../../resources/help.ca.md");
+const HELP_TEXT_EN: &str = include_str!("../../../../resources/help.en.md");
+
+/// File of the help in the language, relative to the root of the repository
+pub fn help_path(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Es => "resources/help.md",
+        Lang::Ca => "resources/help.ca.md",
+        Lang::En => "resources/help.en.md",
+    }
+}
+
+/// Help with the public commands, in MarkdownV2
+pub fn help_markdown(lang: Lang) -> String {
+    let mut help = format!(
+        "*Tu Coche Dana Bot*\n\n> {}\n\n*_{}_*\n\n",
+        Msg::HelpIntro.text(lang),
+        escape_markdown(Msg::HelpCommands.text(lang))
+    );
+
+    for command in Command::ALL {
+        let Some(description) = command.info().description else {
+            continue;
+        };
+        let _ = write!(
+            help,
+            "> **`/{}`**  \n  {}\n\n",
+            command.name(),
+            escape_markdown(description.text(lang))
+        );
+    }
+
+    help.push('\n');
+    help.push_str(Msg::HelpCredits.text(lang));
+    help
+}
 
 impl UpdateProcessor {
     pub async fn help_menu(&self) -> Result<(), BotError> {
@@ -10,19 +52,39 @@ impl UpdateProcessor {
         //     ("Soporte", "https://t.me/horus_soporte"),
         //     ("Seguimiento", "https://t.me/horus_seguimiento"),
         // ]];
-        let rows = vec![vec![("⬅️ Back", "/start_back")]];
-
-        let rows = Self::texts_to_buttons(rows, false);
-
-        self.api
-            .edit_or_send_message_with_parse_mode(
-                self.chat.id,
-                self.message_id,
-                HELP_TEXT,
-                rows,
-                frankenstein::ParseMode::MarkdownV2,
-            )
+        let rows = vec![vec![(self.t(Msg::Back), CallbackAction::Menu)]];
+
+        let rows = Self::texts_to_buttons(rows);
+
+        let help_text = match self.lang() {
+            Lang::Es => HELP_TEXT_ES,
+            Lang::Ca => HELP_TEXT_CA,
+            Lang::En => HELP_TEXT_EN,
+        };
+
+        self.edit_or_send_with_parse_mode(help_text, rows, frankenstein::ParseMode::MarkdownV2)
             .await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod help_tests {
+    use super::*;
+
+    #[test]
+    fn test_help_files_are_generated() {
+        for (lang, text) in [
+            (Lang::Es, HELP_TEXT_ES),
+            (Lang::Ca, HELP_TEXT_CA),
+            (Lang::En, HELP_TEXT_EN),
+        ] {
+            assert_eq!(
+                text,
+                help_markdown(lang),
+                "{} is outdated, run `tu-coche-dana-bot generate-help`",
+                help_path(lang)
+            );
+        }
+    }
+}
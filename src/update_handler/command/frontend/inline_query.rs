@@ -0,0 +1,172 @@
+use frankenstein::{
+    InlineQueryResult, InlineQueryResultArticle, InlineQueryResultsButton, InputMessageContent,
+    InputTextMessageContent, ParseMode,
+};
+
+use crate::{
+    db::model::vehicle::Vehicle,
+    i18n::{Locale, Msg},
+    plate::Plate,
+    update_handler::{deep_link::StartPayload, process_update::UpdateProcessor},
+    BotError,
+};
+
+/// Seconds Telegram keeps an answer, statuses only change once per sweep
+const INLINE_CACHE_SECONDS: u32 = 30;
+
+/// Status of the plate as known by the bot, without asking tucochedana.es
+pub fn inline_result(
+    plate: &Plate,
+    vehicle: Option<&Vehicle>,
+    locale: &Locale,
+) -> InlineQueryResult {
+    let lang = locale.lang;
+    let url = StartPayload::Add(plate.clone()).url();
+    let plate = plate.as_str();
+
+    let (description, text) = match vehicle {
+        None => {
+            let text = Msg::NotTracked.format(lang, &[("plate", plate)]);
+            (text.clone(), text)
+        }
+        Some(vehicle) if vehicle.found_at.is_some() => (
+            Msg::InlineFound.text(lang).to_string(),
+            vehicle.found_at_to_text(locale),
+        ),
+        Some(vehicle) => {
+            let mut text = vehicle.found_at_to_text(locale);
+            if let Some(checked_at) = &vehicle.checked_at {
+                let date = locale.format_datetime(checked_at);
+                text.push('\n');
+                text.push_str(&Msg::InlineLastChecked.format(lang, &[("date", &date)]));
+            }
+            (Msg::InlineNotFound.text(lang).to_string(), text)
+        }
+    };
+
+    let content = InputTextMessageContent::builder()
+        .message_text(text)
+        .parse_mode(ParseMode::Html)
+        .build();
+
+    // Found vehicles have nothing left to follow
+    let keyboard = match vehicle.and_then(|vehicle| vehicle.found_at) {
+        Some(_) => None,
+        None => Some(UpdateProcessor::urls_to_buttons(vec![vec![(
+            Msg::FollowPlate.format(lang, &[("plate", plate)]),
+            url,
+        )]])),
+    };
+
+    let article = InlineQueryResultArticle::builder()
+        .id(plate)
+        .title(plate)
+        .description(description)
+        .input_message_content(InputMessageContent::Text(content))
+        .maybe_reply_markup(keyboard)
+        .build();
+
+    InlineQueryResult::Article(article)
+}
+
+impl UpdateProcessor {
+    /// `@bot 1234BCD` answers with the status of the plate, anything else with a hint
+    pub async fn answer_inline_query(&self) -> Result<(), BotError> {
+        let Some(inline_query_id) = self.inline_query_id.as_deref() else {
+            return Ok(());
+        };
+
+        let (results, button) = match Plate::parse(&self.text) {
+            Ok(plate) => {
+                let vehicle = self.repo.find_vehicle(plate.as_str()).await?;
+                let result = inline_result(&plate, vehicle.as_ref(), &self.locale());
+                (vec![result], None)
+            }
+            Err(_) => {
+                let button = InlineQueryResultsButton::builder()
+                    .text(self.t(Msg::InlineHint))
+                    .start_parameter("inline")
+                    .build();
+                (vec![], Some(button))
+            }
+        };
+
+        self.api
+            .answer_inline_query(inline_query_id, results, button, INLINE_CACHE_SECONDS)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod inline_query_tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{i18n::Lang, update_handler::command::Command};
+
+    fn article(result: InlineQueryResult) -> InlineQueryResultArticle {
+        match result {
+            InlineQueryResult::Article(article) => article,
+            other => panic!("Unexpected result {other:?}"),
+        }
+    }
+
+    fn text(article: &InlineQueryResultArticle) -> &str {
+        match &article.input_message_content {
+            InputMessageContent::Text(content) => &content.message_text,
+            other => panic!("Unexpected content {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_inline_result() {
+        let plate = Plate::parse("1234 bcd").unwrap();
+        let locale = Locale::new(Lang::En, chrono_tz::Europe::Madrid);
+
+        // Plates nobody follows get the button too
+        let result = article(inline_result(&plate, None, &locale));
+        assert_eq!(result.title, "1234BCD");
+        assert_eq!(text(&result), "Nobody is following vehicle 1234BCD yet");
+        let button = &result.reply_markup.unwrap().inline_keyboard[0][0];
+        assert_eq!(button.text, "🔔 Follow 1234BCD");
+        assert!(button.url.as_ref().unwrap().ends_with("?start=add_1234BCD"));
+
+        let checked_at = Utc.with_ymd_and_hms(2024, 12, 1, 12, 0, 0).unwrap();
+        let vehicle = Vehicle::builder()
+            .plate("1234BCD".to_string())
+            .checked_at(checked_at)
+            .build();
+        let result = article(inline_result(&plate, Some(&vehicle), &locale));
+        assert_eq!(result.description.as_deref(), Some("Not found yet"));
+        assert!(text(&result).ends_with("13:00"));
+        assert!(result.reply_markup.is_some());
+
+        let vehicle = Vehicle::builder()
+            .plate("1234BCD".to_string())
+            .found_at(checked_at)
+            .build();
+        let result = article(inline_result(&plate, Some(&vehicle), &locale));
+        assert_eq!(result.description.as_deref(), Some("Found 🙌🏼"));
+        assert!(result.reply_markup.is_none());
+    }
+
+    #[test]
+    fn test_follow_button_opens_follow_offer() {
+        let plate = Plate::parse("1234BCD").unwrap();
+        let locale = Locale::new(Lang::Es, chrono_tz::Europe::Madrid);
+
+        let result = article(inline_result(&plate, None, &locale));
+        let button = &result.reply_markup.unwrap().inline_keyboard[0][0];
+        let (_, payload) = button.url.as_ref().unwrap().split_once("?start=").unwrap();
+
+        // Telegram sends the payload after `/start` when the link is opened
+        let (command, argument) = Command::parse(&format!("/start {payload}"));
+        assert_eq!(command, Command::Start);
+        assert_eq!(
+            argument.unwrap().parse::<StartPayload>(),
+            Ok(StartPayload::Add(plate))
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use crate::{
+    i18n::{Lang, Msg},
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
+
+impl UpdateProcessor {
+    pub async fn language_menu(&self) -> Result<(), BotError> {
+        let current = self.lang();
+
+        let mut rows: Vec<Vec<(String, CallbackAction)>> = Lang::ALL
+            .into_iter()
+            .map(|lang| {
+                let name = match lang == current {
+                    true => format!("✅ {}", lang.name()),
+                    false => lang.name().to_string(),
+                };
+                vec![(name, CallbackAction::SetLanguage(lang))]
+            })
+            .collect();
+        rows.push(vec![(self.t(Msg::Back).to_string(), CallbackAction::Menu)]);
+
+        let keyboard = Self::texts_to_buttons(rows);
+
+        self.edit_or_send(self.t(Msg::LanguageMenu), keyboard)
+            .await?;
+
+        Ok(())
+    }
+}
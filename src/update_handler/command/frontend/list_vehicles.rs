@@ -1,44 +1,60 @@
-use crate::{update_handler::process_update::UpdateProcessor, BotError};
+use crate::{
+    i18n::Msg,
+    plate::Plate,
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
 
-pub const VEHICLES_MENU_TEXT: &str = "Vehículos añadidos";
-pub const ADD_VEHICLE: &str = "Añadir un vehículo";
 pub const DELETE_EMOJI: &str = "❌";
 
+/// Longest unverified plate whose buttons fit in `MAX_CALLBACK_LEN`
+const MAX_UNVERIFIED_LEN: usize = 32;
+
 impl UpdateProcessor {
     pub async fn get_vehicles(&self, text: Option<&str>) -> Result<(), BotError> {
         let vehicles = self.repo.get_vehicles_by_chat_id(&self.chat.id).await?;
 
-        let mut rows: Vec<Vec<(String, String)>> = vec![];
-
-        vehicles.into_iter().enumerate().for_each(|(num, vehicle)| {
-            rows.push(vec![]);
-
-            rows[num].push((
-                vehicle.plate.clone(),
-                format!("/check_vehicle {}", vehicle.plate),
-            ));
-
-            rows[num].push((
-                DELETE_EMOJI.to_string(),
-                format!("/delete_vehicle {}", vehicle.plate),
-            ));
-        });
+        let mut rows: Vec<Vec<(String, CallbackAction)>> = vec![];
+
+        for vehicle in vehicles {
+            // Plates are validated before being stored, older ones may not pass and are
+            // listed anyway so they can be removed
+            let plate = match Plate::parse(&vehicle.plate) {
+                Ok(plate) => plate,
+                Err(err) => {
+                    log::warn!("Stored plate {} is invalid: {err}", vehicle.plate);
+                    match Plate::unverified(&vehicle.plate) {
+                        Ok(plate) if plate.as_str().len() <= MAX_UNVERIFIED_LEN => plate,
+                        _ => continue,
+                    }
+                }
+            };
+
+            rows.push(vec![
+                (
+                    vehicle.plate.clone(),
+                    CallbackAction::CheckVehicle(plate.clone()),
+                ),
+                (
+                    DELETE_EMOJI.to_string(),
+                    CallbackAction::DeleteVehicle(plate),
+                ),
+            ]);
+        }
         rows.push(vec![(
-            ADD_VEHICLE.to_string(),
-            "/add_vehicle_message".to_string(),
+            self.t(Msg::StartAddVehicle).to_string(),
+            CallbackAction::AddVehiclePrompt,
         )]);
-        rows.push(vec![("⬅️ Back".to_string(), "/start_back".to_string())]);
+        rows.push(vec![(self.t(Msg::Back).to_string(), CallbackAction::Menu)]);
 
-        let vec = Self::texts_to_buttons(rows, false);
+        let vec = Self::texts_to_buttons(rows);
 
         let message = match text {
-            Some(text) => text,
-            None => VEHICLES_MENU_TEXT,
+            Some(text) => self.with_mention(text),
+            None => self.t(Msg::VehiclesMenu).to_string(),
         };
 
-        self.api
-            .edit_or_send_message(self.chat.id, self.message_id, message, vec)
-            .await?;
+        self.edit_or_send(&message, vec).await?;
 
         Ok(())
     }
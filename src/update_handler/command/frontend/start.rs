@@ -1,50 +1,81 @@
+use crate::i18n::Msg;
 use crate::BotError;
 
-pub const START_OPTIONS_1: &str = "Añadir un vehículo";
-pub const START_OPTIONS_1_2: &str = "Mis vehículos";
-pub const START_OPTIONS_2: &str = "🚨 Activar alertas";
-pub const START_OPTIONS_3: &str = "🔕 Desactivar alertas";
-pub const START_OPTIONS_4: &str = "Ayuda";
+use crate::update_handler::{
+    callback::CallbackAction, deep_link::StartPayload, process_update::UpdateProcessor,
+};
 
-pub const WELCOME_MESSAGE: &str = "¡Bienvenido! Este bot se encuentra en desarrollo.\nEste proyecto no está afiliado con **tucochedana.es**";
+impl UpdateProcessor {
+    /// `/start` shows the menu, the ones sent by deep links carry a `StartPayload`
+    pub async fn start(&self) -> Result<(), BotError> {
+        let Some(payload) = self.argument() else {
+            return self.start_message(None).await;
+        };
 
-use crate::update_handler::process_update::{UpdateProcessor, SELECT_COMMAND_TEXT};
+        let payload = match payload.parse::<StartPayload>() {
+            Ok(StartPayload::Inline) => return self.start_message(None).await,
+            Ok(payload) => payload,
+            Err(err) => {
+                log::warn!("Invalid start payload: {err}");
+                return self.start_message(Some(self.t(Msg::InvalidLink))).await;
+            }
+        };
 
-impl UpdateProcessor {
-    pub async fn start_message(&self, text: Option<&str>) -> Result<(), BotError> {
-        //Handling new user
+        self.welcome().await?;
+
+        match payload {
+            StartPayload::Add(plate) => self.follow_offer(&plate).await,
+            StartPayload::Share(token) => self.join_shared(&token).await,
+            StartPayload::Inline => Ok(()),
+        }
+    }
 
+    /// Only shown to new users
+    async fn welcome(&self) -> Result<(), BotError> {
         if self.is_first {
             self.api
-                .send_message_without_reply(self.chat.id, WELCOME_MESSAGE)
+                .send_message_without_reply(self.chat.id, self.t(Msg::Welcome))
                 .await?;
         }
+        Ok(())
+    }
+
+    pub async fn start_message(&self, text: Option<&str>) -> Result<(), BotError> {
+        self.welcome().await?;
 
         let alert_row = if !self.chat.active {
-            (START_OPTIONS_2, "/start_fetch")
+            (self.t(Msg::StartEnableAlerts), CallbackAction::StartFetch)
         } else {
-            (START_OPTIONS_3, "/stop_fetch")
+            (self.t(Msg::StartDisableAlerts), CallbackAction::StopFetch)
         };
 
         let rows = vec![
             vec![
-                (START_OPTIONS_1, "/add_vehicle_message"),
-                (START_OPTIONS_1_2, "/get_my_vehicles"),
+                (
+                    self.t(Msg::StartAddVehicle),
+                    CallbackAction::AddVehiclePrompt,
+                ),
+                (self.t(Msg::StartMyVehicles), CallbackAction::MyVehicles),
             ],
             vec![alert_row],
-            vec![(START_OPTIONS_4, "/help")],
+            vec![
+                (self.t(Msg::StartChannels), CallbackAction::Channels),
+                (self.t(Msg::StartLanguage), CallbackAction::LanguageMenu),
+            ],
+            vec![
+                (self.t(Msg::StartTimezone), CallbackAction::TimezoneMenu),
+                (self.t(Msg::StartHelp), CallbackAction::Help),
+            ],
         ];
 
-        let vec = Self::texts_to_buttons(rows, false);
+        let vec = Self::texts_to_buttons(rows);
 
         let text = match text {
-            Some(t) => t,
-            None => SELECT_COMMAND_TEXT,
+            Some(t) => self.with_mention(t),
+            None => self.t(Msg::SelectCommand).to_string(),
         };
 
-        self.api
-            .edit_or_send_message(self.chat.id, self.message_id, text, vec)
-            .await?;
+        self.edit_or_send(&text, vec).await?;
 
         Ok(())
     }
@@ -0,0 +1,48 @@
+use chrono::Utc;
+use chrono_tz::Tz;
+
+use crate::{
+    i18n::Msg,
+    update_handler::{callback::CallbackAction, process_update::UpdateProcessor},
+    BotError,
+};
+
+/// Timezones offered in the menu, any other IANA name can be typed
+pub const TIMEZONES: [Tz; 4] = [
+    chrono_tz::Europe::Madrid,
+    chrono_tz::Atlantic::Canary,
+    chrono_tz::Europe::London,
+    chrono_tz::UTC,
+];
+
+impl UpdateProcessor {
+    pub async fn timezone_menu(&self) -> Result<(), BotError> {
+        let locale = self.locale();
+
+        let mut rows: Vec<Vec<(String, CallbackAction)>> = TIMEZONES
+            .into_iter()
+            .map(|tz| {
+                let name = match tz == locale.tz {
+                    true => format!("✅ {}", tz.name()),
+                    false => tz.name().to_string(),
+                };
+                vec![(name, CallbackAction::SetTimezone(tz))]
+            })
+            .collect();
+        rows.push(vec![(self.t(Msg::Back).to_string(), CallbackAction::Menu)]);
+
+        let keyboard = Self::texts_to_buttons(rows);
+
+        let text = self.tf(
+            Msg::TimezoneMenu,
+            &[
+                ("time", &locale.format_datetime(&Utc::now())),
+                ("timezone", locale.tz.name()),
+            ],
+        );
+
+        self.edit_or_send(&text, keyboard).await?;
+
+        Ok(())
+    }
+}
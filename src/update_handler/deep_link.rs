@@ -0,0 +1,141 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+    plate::{Plate, PlateError},
+    BOT_NAME,
+};
+
+/// Longest `start` parameter accepted by Telegram
+pub const MAX_PAYLOAD_LEN: usize = 64;
+
+/// Longest token of a shared tracking, see `SharedTracking::generate_token`
+const MAX_TOKEN_LEN: usize = 32;
+
+/// What `t.me/<bot>?start=<payload>` links ask the bot to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPayload {
+    /// `add_<plate>` offers to follow the plate
+    Add(Plate),
+    /// `share_<token>` follows the plate of a shared tracking
+    Share(String),
+    /// `inline`, the hint shown in inline mode
+    Inline,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PayloadError {
+    #[error("Payload is {0} characters long, {MAX_PAYLOAD_LEN} at most")]
+    TooLong(usize),
+    #[error("Payload '{0}' contains characters other than A-Z, a-z, 0-9, _ and -")]
+    InvalidCharacters(String),
+    #[error("Unknown payload '{0}'")]
+    Unknown(String),
+    #[error(transparent)]
+    InvalidPlate(#[from] PlateError),
+    #[error("Invalid share token '{0}'")]
+    InvalidToken(String),
+}
+
+impl StartPayload {
+    /// Link that opens the bot with the payload
+    pub fn url(&self) -> String {
+        format!(
+            "https://t.me/{}?start={self}",
+            BOT_NAME.trim_start_matches('@')
+        )
+    }
+}
+
+impl FromStr for StartPayload {
+    type Err = PayloadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > MAX_PAYLOAD_LEN {
+            return Err(PayloadError::TooLong(s.len()));
+        }
+
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if !s.chars().all(valid) {
+            return Err(PayloadError::InvalidCharacters(s.to_string()));
+        }
+
+        match s.split_once('_') {
+            Some(("add", plate)) => Ok(StartPayload::Add(Plate::parse(plate)?)),
+            Some(("share", token)) => {
+                if token.is_empty()
+                    || token.len() > MAX_TOKEN_LEN
+                    || !token.chars().all(|c| c.is_ascii_alphanumeric())
+                {
+                    return Err(PayloadError::InvalidToken(token.to_string()));
+                }
+                Ok(StartPayload::Share(token.to_string()))
+            }
+            None if s == "inline" => Ok(StartPayload::Inline),
+            _ => Err(PayloadError::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for StartPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartPayload::Add(plate) => write!(f, "add_{plate}"),
+            StartPayload::Share(token) => write!(f, "share_{token}"),
+            StartPayload::Inline => write!(f, "inline"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod deep_link_tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_round_trip() {
+        let payloads = [
+            StartPayload::Add(Plate::parse("1234BCD").unwrap()),
+            StartPayload::Add(Plate::parse("PGC1234A").unwrap()),
+            StartPayload::Share("aZ09aZ09aZ09aZ09aZ09".to_string()),
+            StartPayload::Inline,
+        ];
+
+        for payload in payloads {
+            let encoded = payload.to_string();
+            assert!(encoded.len() <= MAX_PAYLOAD_LEN);
+            assert_eq!(encoded.parse::<StartPayload>(), Ok(payload.clone()));
+            assert!(payload.url().ends_with(&format!("?start={encoded}")));
+        }
+    }
+
+    #[test]
+    fn test_invalid_payloads() {
+        assert_eq!(
+            "add_1234abc".parse::<StartPayload>(),
+            Err(PayloadError::InvalidPlate(PlateError::UnknownFormat(
+                "1234ABC".to_string()
+            )))
+        );
+        assert_eq!(
+            "share_".parse::<StartPayload>(),
+            Err(PayloadError::InvalidToken(String::new()))
+        );
+        assert_eq!(
+            "share_a-b".parse::<StartPayload>(),
+            Err(PayloadError::InvalidToken("a-b".to_string()))
+        );
+        assert_eq!(
+            "add_1234 BCD".parse::<StartPayload>(),
+            Err(PayloadError::InvalidCharacters("add_1234 BCD".to_string()))
+        );
+        assert_eq!(
+            "x".repeat(65).parse::<StartPayload>(),
+            Err(PayloadError::TooLong(65))
+        );
+        assert_eq!(
+            "hello".parse::<StartPayload>(),
+            Err(PayloadError::Unknown("hello".to_string()))
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+};
+
+use frankenstein::{MaybeInaccessibleMessage, Update, UpdateContent};
+use futures::FutureExt;
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        Notify,
+    },
+    task::{JoinHandle, JoinSet},
+};
+
+use super::process_update::UpdateProcessor;
+use crate::{db::Repo, BotError};
+
+/// Updates waiting to be processed before new ones are refused
+const DISPATCH_CAPACITY: usize = 1024;
+
+/// Hands the updates over to a supervisor task so they're processed in the background.
+///
+/// Every update runs in its own task, the ones of a chat one after another in the order they
+/// came so its state isn't changed by two at once. Errors and panics are logged and never
+/// reach whoever received the update
+#[derive(Debug, Clone)]
+pub struct UpdateDispatcher {
+    sender: mpsc::Sender<Update>,
+    /// Asks the supervisor to stop taking updates
+    close: Arc<Notify>,
+    supervisor: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl UpdateDispatcher {
+    /// Spawns the supervisor, it stops once every dispatcher has been dropped or it's drained
+    pub fn start() -> Self {
+        Self::start_with(Self::process)
+    }
+
+    /// Supervisor running `handler` for every update
+    fn start_with<F, Fut>(handler: F) -> Self
+    where
+        F: Fn(Update) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(DISPATCH_CAPACITY);
+        let close = Arc::new(Notify::new());
+
+        let supervisor = tokio::spawn(Self::supervise(receiver, close.clone(), handler));
+
+        UpdateDispatcher {
+            sender,
+            close,
+            supervisor: Arc::new(Mutex::new(Some(supervisor))),
+        }
+    }
+
+    /// Refuses new updates and waits for the ones already acknowledged to be processed,
+    /// Telegram won't send them again
+    pub async fn drain(&self) {
+        self.close.notify_one();
+
+        let supervisor = self.supervisor.lock().unwrap().take();
+        if let Some(supervisor) = supervisor {
+            log::info!("Waiting for the pending updates");
+            if let Err(err) = supervisor.await {
+                log::error!("Update supervisor panicked: {err}");
+            }
+        }
+    }
+
+    /// Queues the update, it only fails when the queue is full or the supervisor is gone.
+    /// The error is the id of the refused update
+    pub fn dispatch(&self, update: Update) -> Result<(), u32> {
+        self.sender.try_send(update).map_err(|err| match err {
+            TrySendError::Full(update) => {
+                log::error!("Too many pending updates, refusing {}", update.update_id);
+                update.update_id
+            }
+            TrySendError::Closed(update) => {
+                log::error!(
+                    "Update supervisor is not running, refusing {}",
+                    update.update_id
+                );
+                update.update_id
+            }
+        })
+    }
+
+    /// Waits for room in the queue instead of refusing the update
+    pub async fn dispatch_wait(&self, update: Update) -> Result<(), u32> {
+        self.sender.send(update).await.map_err(|err| {
+            log::error!(
+                "Update supervisor is not running, refusing {}",
+                err.0.update_id
+            );
+            err.0.update_id
+        })
+    }
+
+    async fn supervise<F, Fut>(mut receiver: mpsc::Receiver<Update>, close: Arc<Notify>, handler: F)
+    where
+        F: Fn(Update) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut tasks = JoinSet::new();
+        // Chats with an update running, and the ones of theirs waiting for it
+        let mut waiting: HashMap<i64, VecDeque<Update>> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => match update {
+                    Some(update) => {
+                        let chat_id = Self::chat_id(&update);
+                        match chat_id.and_then(|chat_id| waiting.get_mut(&chat_id)) {
+                            Some(queue) => queue.push_back(update),
+                            None => {
+                                if let Some(chat_id) = chat_id {
+                                    waiting.insert(chat_id, VecDeque::new());
+                                }
+                                tasks.spawn(Self::run(&handler, chat_id, update));
+                            }
+                        }
+                    }
+                    None => break,
+                },
+                Some(result) = tasks.join_next() => {
+                    Self::run_next(result, &mut waiting, &mut tasks, &handler);
+                }
+                // The updates already queued are still received
+                _ = close.notified() => receiver.close(),
+            }
+        }
+
+        // Let the updates already received finish
+        while let Some(result) = tasks.join_next().await {
+            Self::run_next(result, &mut waiting, &mut tasks, &handler);
+        }
+    }
+
+    /// Runs the handler catching its panics, so the chat's next update isn't left waiting
+    fn run<F, Fut>(
+        handler: &F,
+        chat_id: Option<i64>,
+        update: Update,
+    ) -> impl Future<Output = Option<i64>> + Send + 'static
+    where
+        F: Fn(Update) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let update_id = update.update_id;
+        let future = AssertUnwindSafe(handler(update)).catch_unwind();
+
+        async move {
+            if future.await.is_err() {
+                log::error!("Update {update_id} panicked");
+            }
+            chat_id
+        }
+    }
+
+    /// Starts the next update of the chat whose update has finished
+    fn run_next<F, Fut>(
+        result: Result<Option<i64>, tokio::task::JoinError>,
+        waiting: &mut HashMap<i64, VecDeque<Update>>,
+        tasks: &mut JoinSet<Option<i64>>,
+        handler: &F,
+    ) where
+        F: Fn(Update) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let chat_id = match result {
+            Ok(Some(chat_id)) => chat_id,
+            Ok(None) => return,
+            Err(err) => {
+                log::error!("Update task was cancelled: {err}");
+                return;
+            }
+        };
+
+        match waiting.get_mut(&chat_id).and_then(VecDeque::pop_front) {
+            Some(update) => {
+                tasks.spawn(Self::run(handler, Some(chat_id), update));
+            }
+            None => {
+                waiting.remove(&chat_id);
+            }
+        }
+    }
+
+    /// Chat the update belongs to, inline queries are of the private chat of the user
+    fn chat_id(update: &Update) -> Option<i64> {
+        match &update.content {
+            UpdateContent::Message(message) => Some(message.chat.id),
+            UpdateContent::CallbackQuery(callback) => match &callback.message {
+                Some(MaybeInaccessibleMessage::Message(message)) => Some(message.chat.id),
+                Some(MaybeInaccessibleMessage::InaccessibleMessage(message)) => {
+                    Some(message.chat.id)
+                }
+                None => Some(callback.from.id as i64),
+            },
+            UpdateContent::InlineQuery(query) => Some(query.from.id as i64),
+            _ => None,
+        }
+    }
+
+    async fn process(update: Update) {
+        let repo = match Repo::repo().await {
+            Ok(repo) => Some(repo),
+            Err(err) => {
+                log::error!("Could not check update {}: {err}", update.update_id);
+                None
+            }
+        };
+
+        Self::process_once(repo, update, |update| async move {
+            UpdateProcessor::run(&update).await.map(|_| ())
+        })
+        .await;
+    }
+
+    /// Skips the updates already processed or being processed. The claim of a failed update
+    /// is released, so it's processed when Telegram sends it again, and the ones interrupted
+    /// by a crash expire
+    async fn process_once<F, Fut>(repo: Option<&Repo>, update: Update, run: F)
+    where
+        F: FnOnce(Update) -> Fut,
+        Fut: Future<Output = Result<(), BotError>>,
+    {
+        let update_id = update.update_id;
+
+        // Telegram delivers the same update again when it doesn't get an answer in time
+        if let Some(repo) = repo {
+            match repo.claim_update(update_id).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    log::info!(
+                        "Update {update_id} is already processed or being processed, skipping it"
+                    );
+                    return;
+                }
+                Err(err) => log::error!("Could not claim update {update_id}: {err}"),
+            }
+        }
+
+        let result = run(update).await;
+        if let Err(err) = &result {
+            log::error!("Update {update_id} could not be processed: {err}");
+        }
+
+        if let Some(repo) = repo {
+            let stored = match result {
+                Ok(()) => repo.modify_processed_update(update_id).await,
+                Err(_) => repo.release_update(update_id).await,
+            };
+            if let Err(err) = stored {
+                log::error!("Could not store the outcome of update {update_id}: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod dispatcher_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dispatch_full_queue() {
+        let (sender, _receiver) = mpsc::channel(1);
+        let dispatcher = UpdateDispatcher {
+            sender,
+            close: Arc::new(Notify::new()),
+            supervisor: Arc::new(Mutex::new(None)),
+        };
+
+        let update: Update = serde_json::from_str(
+            r#"{"update_id": 1, "message": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+        )
+        .unwrap();
+        assert!(dispatcher.dispatch(update.clone()).is_ok());
+
+        assert_eq!(dispatcher.dispatch(update), Err(1));
+    }
+
+    #[tokio::test]
+    async fn test_drain_pending_updates() {
+        let processed = Arc::new(Mutex::new(vec![]));
+
+        let dispatcher = UpdateDispatcher::start_with({
+            let processed = processed.clone();
+            move |update: Update| {
+                let processed = processed.clone();
+                async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    processed.lock().unwrap().push(update.update_id);
+                }
+            }
+        });
+
+        for update_id in 1..=3 {
+            let update: Update = serde_json::from_value(serde_json::json!({
+                "update_id": update_id,
+                "message": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}
+            }))
+            .unwrap();
+            dispatcher.dispatch(update).unwrap();
+        }
+
+        dispatcher.drain().await;
+
+        let mut processed = processed.lock().unwrap().clone();
+        processed.sort();
+        assert_eq!(processed, vec![1, 2, 3]);
+
+        // Nothing is taken after draining
+        let update: Update = serde_json::from_str(
+            r#"{"update_id": 4, "message": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(dispatcher.dispatch(update), Err(4));
+    }
+
+    #[tokio::test]
+    async fn test_updates_of_a_chat_in_order() {
+        let processed = Arc::new(Mutex::new(vec![]));
+
+        let dispatcher = UpdateDispatcher::start_with({
+            let processed = processed.clone();
+            move |update: Update| {
+                let processed = processed.clone();
+                async move {
+                    // The first update of chat 1 is the slowest one, the second one panics
+                    match update.update_id {
+                        1 => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+                        2 => panic!("Update 2 failed"),
+                        _ => (),
+                    }
+                    processed.lock().unwrap().push(update.update_id);
+                }
+            }
+        });
+
+        for (update_id, chat_id) in [(1, 1), (2, 1), (3, 1), (4, 2)] {
+            let update: Update = serde_json::from_value(serde_json::json!({
+                "update_id": update_id,
+                "message": {"message_id": 1, "date": 0, "chat": {"id": chat_id, "type": "private"}}
+            }))
+            .unwrap();
+            dispatcher.dispatch(update).unwrap();
+        }
+
+        dispatcher.drain().await;
+
+        // Chat 2 doesn't wait for chat 1
+        assert_eq!(*processed.lock().unwrap(), vec![4, 1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_process_update_once() {
+        let db_controller = Repo::new_for_test("test_process_update_once")
+            .await
+            .unwrap();
+        let runs = Arc::new(Mutex::new(0));
+
+        let update: Update = serde_json::from_str(
+            r#"{"update_id": 7, "message": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+        )
+        .unwrap();
+
+        let run = |fail: bool| {
+            let runs = runs.clone();
+            move |_: Update| async move {
+                *runs.lock().unwrap() += 1;
+                match fail {
+                    true => Err(BotError::ChannelUnavailable(
+                        crate::db::model::chat_channel::NotificationChannel::Email,
+                    )),
+                    false => Ok(()),
+                }
+            }
+        };
+
+        // A failed update isn't remembered, so it's processed when it comes again
+        UpdateDispatcher::process_once(Some(&db_controller), update.clone(), run(true)).await;
+        assert!(!db_controller.check_processed_update(7).await.unwrap());
+
+        UpdateDispatcher::process_once(Some(&db_controller), update.clone(), run(false)).await;
+        UpdateDispatcher::process_once(Some(&db_controller), update, run(false)).await;
+        assert_eq!(*runs.lock().unwrap(), 2);
+        assert!(db_controller.check_processed_update(7).await.unwrap());
+
+        // A duplicate delivered while the update is running is skipped
+        let update: Update = serde_json::from_str(
+            r#"{"update_id": 8, "message": {"message_id": 1, "date": 0, "chat": {"id": 1, "type": "private"}}}"#,
+        )
+        .unwrap();
+        let slow_run = || {
+            let runs = runs.clone();
+            move |_: Update| async move {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                *runs.lock().unwrap() += 1;
+                Ok(())
+            }
+        };
+        tokio::join!(
+            UpdateDispatcher::process_once(Some(&db_controller), update.clone(), slow_run()),
+            UpdateDispatcher::process_once(Some(&db_controller), update, slow_run()),
+        );
+        assert_eq!(*runs.lock().unwrap(), 3);
+        assert!(db_controller.check_processed_update(8).await.unwrap());
+
+        db_controller.cleanup_test_db().await.unwrap();
+    }
+}
@@ -1,31 +1,22 @@
-use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 
+use crate::db::model::chat::ChatKind;
+use crate::db::model::chat_channel::NotificationChannel;
 use crate::db::model::client_state::ClientState;
 use crate::db::{model::chat::Chat, Repo};
+use crate::i18n::Msg;
 
-use crate::tasks::fetch::FetchTask;
 use crate::telegram::client::ApiClient;
-use crate::BotError;
+use crate::{metrics, BotError};
 
-use super::command::Command;
+use super::args::ArgError;
+use super::callback::CallbackAction;
+use super::command::{backend::add_vehicle::is_plates_document, Command};
 use bon::Builder;
-use fang::{AsyncQueue, AsyncQueueable, NoTls};
 use frankenstein::{
-    InlineKeyboardMarkup, MaybeInaccessibleMessage, Message, Update, UpdateContent,
+    CallbackQuery, Chat as TelegramChat, Document, InlineKeyboardMarkup, InlineQuery,
+    MaybeInaccessibleMessage, Message, Update, UpdateContent, User,
 };
-use tokio::sync::Mutex;
-
-pub const SELECT_COMMAND_TEXT: &str = "Seleccione un comando";
-
-#[derive(Debug, Clone)]
-pub enum TaskToManage {
-    FetchTask(FetchTask),
-    FetchTasks(Vec<FetchTask>),
-    RemoveTask(String),
-    RemoveTasks(String),
-    NoTask,
-}
 
 /// Telegram's Update event handler
 #[derive(Builder, Debug)]
@@ -33,9 +24,26 @@ pub struct UpdateProcessor {
     pub api: &'static ApiClient,
     pub repo: &'static Repo,
     pub text: String,
-    pub callback_data: Option<String>,
+    /// Action of the pressed button or of the typed command and its arguments
+    #[builder(default = Ok(None))]
+    pub action: Result<Option<CallbackAction>, ArgError>,
+    /// Every button press is answered, even when nothing is shown
+    pub callback_query_id: Option<String>,
+    #[builder(default)]
+    pub callback_answered: AtomicBool,
+    /// Set when the update is an inline query, there is no message to answer then
+    pub inline_query_id: Option<String>,
     pub message_id: i32,
+    /// Only the messages of the pressed buttons are edited
+    #[builder(default)]
+    pub editable: bool,
+    /// User acting, the chat may be a group
+    pub from: User,
+    /// Chat the message was sent on behalf of, the group itself for anonymous admins
+    pub sender_chat_id: Option<i64>,
     pub inline_keyboard: Option<Box<InlineKeyboardMarkup>>,
+    /// Lists of plates can be sent as a file, the caption is the text then
+    pub document: Option<Document>,
     pub command: Command,
     pub chat: Chat,
     pub is_first: bool,
@@ -46,186 +54,332 @@ impl UpdateProcessor {
         let repo = Repo::repo().await?;
         let api = ApiClient::api_client().await;
 
-        let (message, callback_data): (&Message, Option<String>) = match &update.content {
-            UpdateContent::CallbackQuery(callback) => {
-                match &callback.message {
-                    None => {
-                        // Log or handle the case where there is no message attached
-                        panic!("No message attached to the callback query");
-                    }
-                    Some(MaybeInaccessibleMessage::Message(message)) => {
-                        // Valid message case
-                        (
-                            message,
-                            Some(callback.data.as_ref().unwrap().clone()), // Safe unwrap since we know there is data
-                        )
-                    }
-                    Some(MaybeInaccessibleMessage::InaccessibleMessage(inaccessible_message)) => {
-                        // Handle the inaccessible message case
-                        // Deberíamos paniquear ?
-                        panic!(
-                            "Inaccessible message. Chat: {}, Message ID: {}",
-                            inaccessible_message.chat.id, inaccessible_message.message_id
-                        );
-                    }
+        // Messages older than 48 hours are inaccessible, a new one is sent instead of editing
+        let (tg_chat, message_id, message, callback): (
+            &TelegramChat,
+            i32,
+            Option<&Message>,
+            Option<&CallbackQuery>,
+        ) = match &update.content {
+            UpdateContent::CallbackQuery(callback) => match &callback.message {
+                None => {
+                    return Err(BotError::UpdateNotMessage(
+                        "callback query without message".to_string(),
+                    ));
                 }
+                Some(MaybeInaccessibleMessage::Message(message)) => (
+                    &message.chat,
+                    message.message_id,
+                    Some(message),
+                    Some(callback),
+                ),
+                Some(MaybeInaccessibleMessage::InaccessibleMessage(inaccessible_message)) => (
+                    &inaccessible_message.chat,
+                    inaccessible_message.message_id,
+                    None,
+                    Some(callback),
+                ),
+            },
+            UpdateContent::InlineQuery(query) => {
+                return Self::create_inline(repo, api, query).await
             }
             UpdateContent::Message(message) => {
-                let error = message.text.is_none() && message.successful_payment.is_none();
+                let error = message.text.is_none()
+                    && message.document.is_none()
+                    && message.successful_payment.is_none()
+                    && Self::chat_migration(message).is_none();
 
                 if error {
                     log::error!("Update doesn't contain any text {:?}", message);
                     return Err(BotError::UpdateNotMessage("no text".to_string()));
                 }
 
-                (message, None)
+                (&message.chat, message.message_id, Some(message), None)
             }
             _ => {
-                log::error!("Update is not a message or callback {:?}", update);
+                log::error!(
+                    "Update is not a message, callback or inline query {:?}",
+                    update
+                );
 
                 return Err(BotError::UpdateNotMessage("no message".to_string()));
             }
         };
 
-        let text = message.text.as_ref().unwrap();
+        let callback_data = match callback {
+            Some(callback) => match &callback.data {
+                Some(data) => Some(data.clone()),
+                None => {
+                    return Err(BotError::UpdateNotMessage(
+                        "callback query without data".to_string(),
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        // The sender of a callback's message is the bot, the user is the one pressing the button
+        let from = match (callback, message) {
+            (Some(callback), _) => Some(callback.from.clone()),
+            (None, Some(message)) => message.from.as_deref().cloned(),
+            (None, None) => None,
+        };
 
-        let chat_id: i64 = message.chat.id;
-        let user = message.from.as_ref().unwrap().clone();
+        // Payments don't have text, documents may have a caption
+        let text = message
+            .and_then(|message| message.text.clone().or(message.caption.clone()))
+            .unwrap_or_default();
 
-        let username = match user.username {
-            Some(name) => format!("@{}", name),
-            None => user.first_name,
+        let Some(user) = from else {
+            return Err(BotError::UpdateNotMessage("no sender".to_string()));
+        };
+
+        let migration = message.and_then(Self::chat_migration);
+        let chat_id: i64 = match migration {
+            Some((from, to)) => {
+                if repo.migrate_chat(&from, &to).await? {
+                    log::info!("Chat {from} migrated to {to}");
+                }
+                to
+            }
+            None => tg_chat.id,
+        };
+
+        let kind = ChatKind::from(tg_chat.type_field);
+        // Groups are named after their title, users after their username
+        let username = match (kind, &tg_chat.title, &user.username) {
+            (ChatKind::Group, Some(title), _) => title.clone(),
+            (_, _, Some(name)) => format!("@{}", name),
+            _ => user.first_name.clone(),
         };
 
         let (chat, is_first) = repo
-            .find_or_create_chat(&chat_id, user.id, &username, &user.language_code)
+            .find_or_create_chat(&chat_id, user.id, &username, &user.language_code, kind)
             .await?;
 
-        let command = match callback_data.as_ref() {
-            Some(callback) => {
-                let command = callback.split_ascii_whitespace().next().unwrap();
-                Command::from_str(command).unwrap()
+        // Parsing a command never fails, unknown ones are `Command::UnknownCommand`
+        let (command, action) = match (migration, callback_data.as_deref()) {
+            (Some(_), _) => (Command::ChatMigrated, Ok(None)),
+            (None, Some(data)) => match CallbackAction::decode(data) {
+                Ok(action) => (action.command(), Ok(Some(action))),
+                Err(err) => {
+                    log::warn!("Invalid button {data}: {err}");
+                    (Command::InvalidButton(err), Ok(None))
+                }
+            },
+            (None, None) => {
+                let (command, arg) = Command::parse(&text);
+                let action = CallbackAction::from_command(&command, arg.as_deref());
+                (command, action)
             }
-            None => Command::from_str(text).unwrap(),
         };
 
-        let keyboard = message.reply_markup.clone();
+        let keyboard = message.and_then(|message| message.reply_markup.clone());
 
         let processor = Self::builder()
             .repo(repo)
             .api(api)
-            .message_id(message.message_id)
-            .text(text.clone())
-            .maybe_callback_data(callback_data)
+            .message_id(message_id)
+            .editable(callback.is_some() && message.is_some())
+            .text(text)
+            .action(action)
+            .maybe_callback_query_id(callback.map(|callback| callback.id.clone()))
+            .from(user)
+            .maybe_sender_chat_id(
+                message.and_then(|message| message.sender_chat.as_ref().map(|chat| chat.id)),
+            )
             .chat(chat)
             .command(command)
             .maybe_inline_keyboard(keyboard)
+            .maybe_document(message.and_then(|message| message.document.as_deref().cloned()))
             .is_first(is_first)
             .build();
 
         Ok(processor)
     }
 
-    pub async fn run(
-        update: &Update,
-        queue: Arc<Mutex<AsyncQueue<NoTls>>>,
-    ) -> Result<UpdateProcessor, BotError> {
+    /// Old and new ids of a group that became a supergroup, both groups get a message
+    fn chat_migration(message: &Message) -> Option<(i64, i64)> {
+        match (message.migrate_to_chat_id, message.migrate_from_chat_id) {
+            (Some(to), _) => Some((message.chat.id, to)),
+            (None, Some(from)) => Some((from, message.chat.id)),
+            (None, None) => None,
+        }
+    }
+
+    /// `@bot <plate>` typed in any chat. The private chat of the user is only read,
+    /// it isn't created until the user talks to the bot
+    async fn create_inline(
+        repo: &'static Repo,
+        api: &'static ApiClient,
+        query: &InlineQuery,
+    ) -> Result<Self, BotError> {
+        let user = &query.from;
+        let chat_id = user.id as i64;
+
+        let chat = match repo.find_chat(&chat_id).await? {
+            Some(chat) => chat,
+            None => Chat::builder()
+                .id(chat_id)
+                .user_id(user.id)
+                .username(user.first_name.clone())
+                .state(ClientState::Initial)
+                .active(false)
+                .maybe_language_code(user.language_code.clone())
+                .build(),
+        };
+
+        let processor = Self::builder()
+            .repo(repo)
+            .api(api)
+            .message_id(0)
+            .text(query.query.clone())
+            .inline_query_id(query.id.clone())
+            .from(user.clone())
+            .chat(chat)
+            .command(Command::InlineQuery)
+            .is_first(false)
+            .build();
+
+        Ok(processor)
+    }
+
+    pub async fn run(update: &Update) -> Result<UpdateProcessor, BotError> {
         let mut processor = match UpdateProcessor::create(update).await {
             Ok(processor) => processor,
             Err(err) => {
                 log::error!("Failed to initialize the processor {:?}", err);
+                metrics::UPDATES_TOTAL
+                    .with_label_values(&["none", "invalid"])
+                    .inc();
+
+                // The button would keep spinning otherwise
+                if let UpdateContent::CallbackQuery(callback) = &update.content {
+                    let api = ApiClient::api_client().await;
+                    if let Err(err) = api.answer_callback_query(&callback.id, None, false).await {
+                        log::warn!("Failed to answer the callback query: {err}");
+                    }
+                }
                 return Err(err);
             }
         };
 
-        match processor.process().await {
-            Err(error) => {
-                log::error!(
-                    "Failed to process the update {:?} - {:?}. Reverting...",
-                    update,
-                    error
-                );
+        let command = processor.command.name();
+        let timer = metrics::UPDATE_DURATION
+            .with_label_values(&[command])
+            .start_timer();
+
+        let outcome = if let Err(error) = processor.process().await {
+            log::error!(
+                "Failed to process the update {:?} - {:?}. Reverting...",
+                update,
+                error
+            );
 
+            if let Err(err) = processor.alert(processor.t(Msg::UpdateFailed)).await {
+                log::warn!("Failed to answer the callback query: {err}");
+            }
+
+            // Inline queries don't change the state and there is no chat to send the menu to
+            if processor.inline_query_id.is_none() {
                 if let Err(err) = processor.revert_state().await {
                     log::error!("Failed to revert: {:?}", err);
+                    metrics::UPDATES_TOTAL
+                        .with_label_values(&[command, "error"])
+                        .inc();
                     return Err(err);
                 }
             }
+            "error"
+        } else {
+            "ok"
+        };
 
-            Ok(option) => match option {
-                TaskToManage::FetchTasks(tasks) => {
-                    for task in tasks {
-                        queue.try_lock().unwrap().schedule_task(&task).await?;
-                    }
-                }
-                TaskToManage::FetchTask(task) => {
-                    queue.try_lock().unwrap().schedule_task(&task).await?;
-                }
-
-                TaskToManage::RemoveTasks(subscribers) => {
-                    Self::remove_tasks(subscribers).await?;
-                }
-
-                TaskToManage::RemoveTask(plate) => {
-                    processor.repo.delete_tasks_by_plate(&plate).await?;
-                }
-
-                TaskToManage::NoTask => (),
-            },
+        // Handlers only answer the buttons that show a toast
+        if let Err(err) = processor.answer_callback(None, false).await {
+            log::warn!("Failed to answer the callback query: {err}");
         }
 
+        timer.observe_duration();
+        metrics::UPDATES_TOTAL
+            .with_label_values(&[command, outcome])
+            .inc();
+
         Ok(processor)
     }
 
-    async fn process(&mut self) -> Result<TaskToManage, BotError> {
+    async fn process(&mut self) -> Result<(), BotError> {
         if Command::Cancel == self.command {
-            self.cancel(None).await?;
-            return Ok(TaskToManage::NoTask);
+            // Only the member that opened the prompt can cancel it
+            if self.is_others_prompt() {
+                return Ok(());
+            }
+            return self.cancel(None).await;
+        }
+
+        if let Command::InvalidButton(err) = &self.command {
+            return self.invalid_button(err).await;
+        }
+
+        if Command::InlineQuery == self.command {
+            return self.answer_inline_query().await;
+        }
+
+        if Command::ChatMigrated == self.command {
+            return Ok(());
+        }
+
+        if self.chat.is_group() {
+            // Members talk to each other, only what is meant for the bot is answered
+            if self.state() == ClientState::Initial
+                && matches!(self.command, Command::UnknownCommand(_))
+            {
+                return Ok(());
+            }
+
+            if self.requires_admin() && !self.is_admin().await? {
+                return self.admin_only().await;
+            }
         }
 
-        match self.chat.state {
+        match self.state() {
             ClientState::Initial => self.process_initial().await,
 
             ClientState::AddVehicle => {
                 let res = if let Command::UnknownCommand(_) = self.command {
                     self.add_vehicle().await
                 } else {
-                    Ok(TaskToManage::NoTask)
+                    Ok(())
                 };
                 self.repo
                     .modify_state(&self.chat.id, ClientState::Initial)
                     .await?;
                 res
             }
+
+            ClientState::SetEmail => {
+                self.return_to_initial().await?;
+                match self.command {
+                    Command::UnknownCommand(_) => self.set_email().await,
+                    _ => self.process_initial().await,
+                }
+            }
         }
     }
 
-    async fn process_initial(&mut self) -> Result<TaskToManage, BotError> {
+    async fn process_initial(&mut self) -> Result<(), BotError> {
         match &self.command {
-            Command::Help => {
-                self.help_menu().await?;
-                Ok(TaskToManage::NoTask)
-            }
+            Command::Help => self.help_menu().await,
 
-            Command::Start => {
-                self.start_message(None).await?;
-                Ok(TaskToManage::NoTask)
-            }
+            Command::Start => self.start().await,
 
-            Command::StartBack => {
-                self.start_message(None).await?;
-                Ok(TaskToManage::NoTask)
-            }
+            Command::StartBack => self.start_message(None).await,
 
-            Command::AddVehicleMessage => {
-                self.add_vehicle_prompt(None).await?;
-                Ok(TaskToManage::NoTask)
-            }
+            Command::AddVehicleMessage => self.add_vehicle_prompt(None).await,
 
-            Command::MyAddedVehicles => {
-                self.get_vehicles(None).await?;
-                Ok(TaskToManage::NoTask)
-            }
+            Command::AddVehicle => self.add_vehicle().await,
+
+            Command::MyAddedVehicles => self.get_vehicles(None).await,
 
             Command::RemoveVehicle => self.remove_vehicle().await,
 
@@ -233,32 +387,40 @@ impl UpdateProcessor {
 
             Command::StopFetch => self.stop_fetch().await,
 
-            Command::VehicleInfo => {
-                self.vehicle_info().await?;
-                Ok(TaskToManage::NoTask)
-            }
+            Command::VehicleInfo => self.vehicle_info().await,
+
+            Command::ShareVehicle => self.share_vehicle().await,
 
-            Command::UnknownCommand(string) => {
-                self.unknown_command(string).await?;
-                Ok(TaskToManage::NoTask)
+            Command::Channels => self.channels_menu(None).await,
+
+            Command::SetEmailMessage => self.set_email_prompt(None).await,
+
+            Command::EmailOff => self.toggle_channel(NotificationChannel::Email, false).await,
+
+            Command::TelegramOn => {
+                self.toggle_channel(NotificationChannel::Telegram, true)
+                    .await
             }
-            _ => Ok(TaskToManage::NoTask),
-        }
-    }
 
-    async fn remove_tasks(mut subscriptions: String) -> Result<(), BotError> {
-        let repo = Repo::repo().await?;
+            Command::Language => self.language().await,
 
-        subscriptions.pop();
+            Command::Timezone => self.timezone().await,
 
-        for plate in subscriptions.split(',').map(str::trim) {
-            log::info!("Attempting to remove plate {plate}");
-            if repo.get_n_subscribers_by_plate(plate).await? == 0 {
-                log::info!("Removing plate {plate}");
-                repo.delete_tasks_by_plate(plate).await?;
+            Command::TelegramOff => {
+                self.toggle_channel(NotificationChannel::Telegram, false)
+                    .await
             }
-        }
 
-        Ok(())
+            // Lists of plates sent without going through the prompt, other files are ignored
+            Command::UnknownCommand(_) if self.document.is_some() => {
+                match self.document.as_ref().is_some_and(is_plates_document) {
+                    true => self.add_vehicle().await,
+                    false => Ok(()),
+                }
+            }
+
+            Command::UnknownCommand(string) => self.unknown_command(string).await,
+            _ => Ok(()),
+        }
     }
 }
@@ -1,9 +1,12 @@
+use crate::tasks::cleanup::CleanupTask;
+use crate::tasks::sweep::SweepTask;
 use crate::DATABASE_URL;
+use crate::NOTIFICATION_TASK_NAME;
 use crate::TASK_NAME;
 
 use fang::asynk::async_queue::AsyncQueue;
 use fang::asynk::async_worker_pool::AsyncWorkerPool;
-//use fang::AsyncQueueable;
+use fang::AsyncQueueable;
 use fang::FangError;
 use fang::NoTls;
 use fang::SleepParams;
@@ -11,11 +14,12 @@ use fang::SleepParams;
 use std::time::Duration;
 
 const MAX_WORKERS: u32 = 15u32;
+const MAX_NOTIFICATION_WORKERS: u32 = 5u32;
 
 pub async fn start_workers() -> Result<AsyncQueue<NoTls>, FangError> {
     let mut queue: AsyncQueue<NoTls> = AsyncQueue::builder()
         .uri(DATABASE_URL.to_string())
-        .max_pool_size(MAX_WORKERS)
+        .max_pool_size(MAX_WORKERS + MAX_NOTIFICATION_WORKERS)
         .build();
 
     queue.connect(NoTls).await.unwrap();
@@ -29,12 +33,25 @@ pub async fn start_workers() -> Result<AsyncQueue<NoTls>, FangError> {
 
     let mut pool_scheduled_fetch: AsyncWorkerPool<AsyncQueue<NoTls>> = AsyncWorkerPool::builder()
         .number_of_workers(MAX_WORKERS)
-        .sleep_params(params)
+        .sleep_params(params.clone())
         .queue(queue.clone())
         .task_type(TASK_NAME)
         .build();
 
     pool_scheduled_fetch.start().await;
 
+    let mut pool_notifications: AsyncWorkerPool<AsyncQueue<NoTls>> = AsyncWorkerPool::builder()
+        .number_of_workers(MAX_NOTIFICATION_WORKERS)
+        .sleep_params(params)
+        .queue(queue.clone())
+        .task_type(NOTIFICATION_TASK_NAME)
+        .build();
+
+    pool_notifications.start().await;
+
+    // Unique, so it's only created the first time
+    queue.schedule_task(&SweepTask::default()).await?;
+    queue.schedule_task(&CleanupTask::default()).await?;
+
     Ok(queue)
 }
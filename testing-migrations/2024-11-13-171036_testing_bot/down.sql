@@ -1,5 +1,8 @@
+-- Delete the test data from the `subscriptions` table
+DELETE FROM subscriptions WHERE chat_id IN (1, 2, 3);
+
 -- Delete the test data from the `chats` table
 DELETE FROM chats WHERE id IN (1, 2, 3);
 
 -- Delete the test data from the `vehicles` table
-DELETE FROM vehicles WHERE plate IN ('ABC123', 'DEF456', 'GHI789');
\ No newline at end of file
+DELETE FROM vehicles WHERE plate IN ('ABC123', 'DEF456', 'GHI789');
@@ -5,15 +5,13 @@ INSERT INTO
         id,
         user_id,
         username,
-        language_code,
-        subscribed_vehicles
+        language_code
     )
 VALUES (
         1,
         E'\\x6430000000000000',
         'user1',
-        'en',
-        'ABC123,DEF456,'
+        'en'
     )
 ON CONFLICT (id) DO NOTHING;
 
@@ -22,15 +20,13 @@ INSERT INTO
         id,
         user_id,
         username,
-        language_code,
-        subscribed_vehicles
+        language_code
     )
 VALUES (
         2,
         E'\\x5EA6245100000000',
         'user2',
-        'fr',
-        'DEF456,'
+        'fr'
     )
 ON CONFLICT (id) DO NOTHING;
 
@@ -50,16 +46,23 @@ VALUES (
 ON CONFLICT (id) DO NOTHING;
 -- Insert test data into the `vehicles` table
 INSERT INTO
-    vehicles (plate, subscribers_ids)
-VALUES ('ABC123', '1,')
+    vehicles (plate)
+VALUES ('ABC123')
 ON CONFLICT (plate) DO NOTHING;
 
 INSERT INTO
-    vehicles (plate, subscribers_ids)
-VALUES ('DEF456', '1,2,')
+    vehicles (plate)
+VALUES ('DEF456')
 ON CONFLICT (plate) DO NOTHING;
 
 INSERT INTO
     vehicles (plate)
 VALUES ('GHI789')
-ON CONFLICT (plate) DO NOTHING;
\ No newline at end of file
+ON CONFLICT (plate) DO NOTHING;
+-- Insert test data into the `subscriptions` table
+INSERT INTO
+    subscriptions (chat_id, plate)
+VALUES (1, 'ABC123'),
+    (1, 'DEF456'),
+    (2, 'DEF456')
+ON CONFLICT (chat_id, plate) DO NOTHING;